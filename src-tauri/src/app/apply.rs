@@ -0,0 +1,255 @@
+use crate::{
+  app::menu,
+  conf::{AppConf, Theme, UpdatePolicy},
+};
+use log::{error, info};
+use std::sync::Mutex;
+use tauri::{AppHandle, GlobalShortcutManager, LogicalSize, Manager};
+use wry::application::accelerator::Accelerator;
+
+/// The config the running app reflects. Changes are diffed against it, so a setting is only
+/// applied once no matter whether it came from the menu or from an edited `quick.conf.json`.
+pub struct AppliedConf(pub Mutex<AppConf>);
+
+/// Brings the running app in line with `conf` without restarting it. Returns the changed settings
+/// that are only read at startup and so still need a restart.
+pub fn apply(app: &AppHandle, conf: AppConf) -> Vec<&'static str> {
+  let state = app.state::<AppliedConf>();
+  let mut current = state.0.lock().unwrap();
+  let old = std::mem::replace(&mut *current, conf.clone());
+  drop(current);
+
+  if old.stay_on_top != conf.stay_on_top {
+    info!("apply_stay_on_top: {}", conf.stay_on_top);
+    if let Some(win) = app.get_window("core") {
+      win
+        .set_always_on_top(conf.stay_on_top)
+        .unwrap_or_else(|err| error!("apply_stay_on_top_error: {}", err));
+    }
+    set_selected(app, "stay_on_top", conf.stay_on_top);
+  }
+
+  if old.theme != conf.theme {
+    info!("apply_theme: {}", conf.theme.as_str());
+    let mode = match conf.theme.window_theme() {
+      tauri::Theme::Dark => "dark",
+      _ => "light",
+    };
+    // restyles pages that loaded `core.js`, the native window theme follows after a restart
+    for win in app.windows().values() {
+      win
+        .eval(&format!(
+          "window.__setTheme && window.__setTheme('{}')",
+          mode
+        ))
+        .unwrap_or_else(|err| error!("apply_theme_error: {}", err));
+    }
+    set_selected(app, "theme_light", conf.theme == Theme::Light);
+    set_selected(app, "theme_dark", conf.theme == Theme::Dark);
+    set_selected(app, "theme_system", conf.theme == Theme::System);
+  }
+
+  if old.auto_update != conf.auto_update {
+    set_selected(
+      app,
+      "update_prompt",
+      conf.auto_update == UpdatePolicy::Prompt,
+    );
+    set_selected(
+      app,
+      "update_silent",
+      conf.auto_update == UpdatePolicy::Silent,
+    );
+    set_selected(
+      app,
+      "update_disable",
+      conf.auto_update == UpdatePolicy::Disable,
+    );
+  }
+
+  if old.popup_search != conf.popup_search {
+    set_selected(app, "popup_search", conf.popup_search);
+    for label in ["core", "tray"] {
+      if let Some(win) = app.get_window(label) {
+        win
+          .eval("window.location.reload()")
+          .unwrap_or_else(|err| error!("apply_popup_search_error: {}", err));
+      }
+    }
+  }
+
+  if old.global_shortcut != conf.global_shortcut {
+    if let Some(v) = &old.global_shortcut {
+      info!("global_shortcut_unregister: `{}`", v);
+      app
+        .global_shortcut_manager()
+        .unregister(v)
+        .unwrap_or_else(|err| error!("global_shortcut_unregister_error: {}", err));
+    }
+    if let Some(v) = &conf.global_shortcut {
+      register_shortcut(app, v);
+    }
+  }
+
+  if old.tray != conf.tray {
+    info!("apply_tray: {}", conf.tray);
+    if conf.tray {
+      menu::tray_menu()
+        .build(app)
+        .map(|_| ())
+        .unwrap_or_else(|err| error!("tray_build_error: {}", err));
+    } else {
+      if let Some(tray) = app.tray_handle_by_id(menu::TRAY_ID) {
+        tray
+          .destroy()
+          .unwrap_or_else(|err| error!("tray_destroy_error: {}", err));
+      }
+      if let Some(win) = app.get_window("tray") {
+        win
+          .hide()
+          .unwrap_or_else(|err| error!("tray_hide_error: {}", err));
+      }
+    }
+    set_selected(app, "system_tray", conf.tray);
+  }
+
+  if (old.main_width, old.main_height) != (conf.main_width, conf.main_height) {
+    if let Some(win) = app.get_window("core") {
+      win
+        .set_size(LogicalSize::new(conf.main_width, conf.main_height))
+        .unwrap_or_else(|err| error!("apply_size_error: {}", err));
+    }
+  }
+  if (old.tray_width, old.tray_height) != (conf.tray_width, conf.tray_height) {
+    if let Some(win) = app.get_window("tray") {
+      win
+        .set_size(LogicalSize::new(conf.tray_width, conf.tray_height))
+        .unwrap_or_else(|err| error!("apply_size_error: {}", err));
+    }
+  }
+
+  if old.titlebar != conf.titlebar {
+    set_selected(app, "titlebar", conf.titlebar);
+  }
+
+  if old.profile != conf.profile {
+    menu::refresh_profiles(app, &conf.profile);
+  }
+
+  restart_required(&old, &conf)
+}
+
+/// The settings that differ between `old` and `conf` but are only read at startup. A config the
+/// app wrote itself was applied before the watcher reloads it, so it never asks for a restart.
+pub fn restart_required(old: &AppConf, conf: &AppConf) -> Vec<&'static str> {
+  // Window chrome, the native window theme and the dock icon can only be set up when the app
+  // starts, the origins and user agents when the windows are built.
+  let restart = [
+    ("theme", old.theme != conf.theme),
+    ("titlebar", old.titlebar != conf.titlebar),
+    ("hide_dock_icon", old.hide_dock_icon != conf.hide_dock_icon),
+    (
+      "save_window_state",
+      old.save_window_state != conf.save_window_state,
+    ),
+    ("main_origin", old.main_origin != conf.main_origin),
+    ("main_dashboard", old.main_dashboard != conf.main_dashboard),
+    ("ua_window", old.ua_window != conf.ua_window),
+    ("tray_origin", old.tray_origin != conf.tray_origin),
+    ("tray_dashboard", old.tray_dashboard != conf.tray_dashboard),
+    ("ua_tray", old.ua_tray != conf.ua_tray),
+    // the user script is injected when the windows are built
+    ("profile", old.profile != conf.profile),
+  ];
+  restart
+    .into_iter()
+    .filter_map(|(key, changed)| changed.then_some(key))
+    .collect()
+}
+
+/// Writes `conf`, applies it and, if some of the changes only take effect after a restart, asks
+/// the user whether to restart now.
+pub fn save(app: &AppHandle, conf: AppConf) {
+  let restart = apply(app, conf.write());
+  if restart.is_empty() {
+    return;
+  }
+
+  info!("apply_restart_required: {:?}", restart);
+  let handle = app.clone();
+  tauri::api::dialog::ask(
+    app.get_window("core").as_ref(),
+    "Restart QuickType",
+    format!(
+      "Changes to {} take effect after restarting QuickType. Restart now?",
+      restart.join(", ")
+    ),
+    move |is_ok| {
+      if is_ok {
+        tauri::api::process::restart(&handle.env());
+      }
+    },
+  );
+}
+
+/// Registers `shortcut` to toggle the main window.
+pub fn register_shortcut(app: &AppHandle, shortcut: &str) {
+  info!("global_shortcut: `{}`", shortcut);
+  if let Err(err) = shortcut.parse::<Accelerator>() {
+    error!("global_shortcut_parse_error: {}", err);
+    return;
+  }
+
+  info!("global_shortcut_register");
+  let handle = app.app_handle();
+  app
+    .global_shortcut_manager()
+    .register(shortcut, move || {
+      if let Some(w) = handle.get_window("core") {
+        if w.is_visible().unwrap() {
+          w.hide().unwrap();
+        } else {
+          w.show().unwrap();
+          w.set_focus().unwrap();
+        }
+      }
+    })
+    .unwrap_or_else(|err| {
+      error!("global_shortcut_register_error: {}", err);
+    });
+}
+
+// The menu is attached to every window on Windows and Linux, keep the check marks in sync.
+fn set_selected(app: &AppHandle, id: &str, selected: bool) {
+  for win in app.windows().values() {
+    if let Some(item) = win.menu_handle().try_get_item(id) {
+      item
+        .set_selected(selected)
+        .unwrap_or_else(|err| error!("menu_selected_error: {}", err));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn a_reloaded_self_write_changes_nothing() {
+    let conf = AppConf::default();
+    assert!(restart_required(&conf, &conf.clone()).is_empty());
+  }
+
+  #[test]
+  fn only_startup_settings_need_a_restart() {
+    let old = AppConf::default();
+    let conf = AppConf {
+      stay_on_top: !old.stay_on_top,
+      tray_width: old.tray_width + 10.0,
+      theme: Theme::Dark,
+      ua_tray: "Test".into(),
+      ..old.clone()
+    };
+    assert_eq!(restart_required(&old, &conf), ["theme", "ua_tray"]);
+  }
+}
@@ -0,0 +1,416 @@
+use crate::{
+  app::{apply, menu},
+  conf::{migrate, AppConf, ConfError, APP_CONF_PATH},
+  utils,
+};
+use log::{error, info};
+use serde::{ser::Serializer, Serialize};
+use serde_json::{Map, Value};
+use std::{
+  fs::{self, File},
+  io,
+  path::{Path, PathBuf},
+  time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{api::dialog, AppHandle, Manager};
+use walkdir::WalkDir;
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+/// Describes the bundle, written as the last entry of the archive.
+pub const MANIFEST: &str = "quicktype.bundle.json";
+// Hidden, so they are skipped by `export`.
+const STAGING_DIR: &str = ".import";
+const BACKUP_DIR: &str = ".import-backup";
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+  #[error(transparent)]
+  Io(#[from] std::io::Error),
+  #[error(transparent)]
+  Zip(#[from] zip::result::ZipError),
+  #[error(transparent)]
+  Json(#[from] serde_json::Error),
+  #[error(transparent)]
+  Conf(#[from] ConfError),
+  #[error(transparent)]
+  Other(#[from] anyhow::Error),
+  #[error("not a QuickType settings bundle: {0}")]
+  Invalid(String),
+}
+
+impl Serialize for Error {
+  fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    serializer.serialize_str(self.to_string().as_ref())
+  }
+}
+
+/// A setting whose value differs between the running config and an imported bundle.
+#[derive(Serialize, Debug, Clone)]
+pub struct Change {
+  pub key: String,
+  pub from: Value,
+  pub to: Value,
+}
+
+// Logs, backups and half-written temp files are not part of the user's settings.
+fn is_bundled(path: &Path) -> bool {
+  !matches!(
+    path.extension().and_then(|e| e.to_str()),
+    Some("log" | "bak" | "tmp" | "zip")
+  )
+}
+
+/// Packs the config, user scripts, profiles, download indexes and saved files under
+/// `utils::app_root()` into a zip archive at `path`.
+pub fn export(path: &Path) -> Result<()> {
+  info!("bundle_export: {}", path.display());
+  let root = utils::app_root();
+  let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+  let mut zip = ZipWriter::new(utils::create_file(path)?);
+
+  for entry in WalkDir::new(&root)
+    .min_depth(1)
+    .into_iter()
+    .filter_entry(|e| !utils::is_hidden(e))
+    .filter_map(|e| e.ok())
+  {
+    let file = entry.path();
+    if !entry.file_type().is_file() || !is_bundled(file) {
+      continue;
+    }
+    let name = file.strip_prefix(&root).unwrap_or(file);
+    let name: Vec<_> = name.iter().map(|c| c.to_string_lossy()).collect();
+    zip.start_file(name.join("/"), options)?;
+    io::copy(&mut File::open(file)?, &mut zip)?;
+  }
+
+  let package = utils::get_tauri_conf().unwrap().package;
+  zip.start_file(MANIFEST, options)?;
+  serde_json::to_writer_pretty(
+    &mut zip,
+    &serde_json::json!({
+      "app_version": package.version,
+      "conf_version": migrate::CONF_VERSION,
+    }),
+  )?;
+  zip.finish()?;
+  Ok(())
+}
+
+/// Unpacks the bundle at `path` into a staging directory and checks it, without touching the
+/// current settings. Returns the staging directory and the settings the import would change.
+pub fn stage(path: &Path) -> Result<(PathBuf, Vec<Change>)> {
+  let staging = utils::app_root().join(STAGING_DIR);
+  if staging.exists() {
+    fs::remove_dir_all(&staging)?;
+  }
+
+  let mut archive = ZipArchive::new(File::open(path)?)?;
+  if archive.by_name(MANIFEST).is_err() || archive.by_name(APP_CONF_PATH).is_err() {
+    return Err(Error::Invalid(format!(
+      "{} or {} is missing",
+      MANIFEST, APP_CONF_PATH
+    )));
+  }
+  for i in 0..archive.len() {
+    let mut file = archive.by_index(i)?;
+    let name = match file.enclosed_name() {
+      Some(v) => v.to_path_buf(),
+      None => return Err(Error::Invalid(format!("unsafe path `{}`", file.name()))),
+    };
+    if file.is_dir() || name == Path::new(MANIFEST) {
+      continue;
+    }
+    let target = staging.join(name);
+    if let Some(dir) = target.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    io::copy(&mut file, &mut File::create(target)?)?;
+  }
+
+  let mut imported: Map<String, Value> =
+    serde_json::from_str(&fs::read_to_string(staging.join(APP_CONF_PATH))?)?;
+  migrate::upgrade(&mut imported);
+  AppConf::validate(&imported)?;
+  // compare complete configs, so settings missing from an older bundle show up as defaults
+  let imported = serde_json::from_value::<AppConf>(Value::Object(imported))?.to_map();
+  utils::write_json(&staging.join(APP_CONF_PATH), &imported)?;
+
+  let current = AppConf::read().to_map();
+  let changes = imported
+    .into_iter()
+    .filter(|(key, value)| current.get(key) != Some(value))
+    .map(|(key, to)| Change {
+      from: current.get(&key).cloned().unwrap_or(Value::Null),
+      key,
+      to,
+    })
+    .collect();
+
+  Ok((staging, changes))
+}
+
+/// Moves the files of a staged import over the current ones, then writes the config. The files
+/// being replaced are set aside first and put back if any step fails, so an import is applied
+/// either completely or not at all.
+pub fn commit(staging: &Path) -> Result<()> {
+  let root = utils::app_root();
+  let backup = root.join(BACKUP_DIR);
+  if backup.exists() {
+    fs::remove_dir_all(&backup)?;
+  }
+
+  // `(target, where the file it replaced was set aside)`
+  let mut moved: Vec<(PathBuf, Option<PathBuf>)> = vec![];
+  let res = (|| -> Result<()> {
+    for entry in WalkDir::new(staging)
+      .min_depth(1)
+      .into_iter()
+      .filter_map(|e| e.ok())
+    {
+      let file = entry.path();
+      let name = file.strip_prefix(staging).unwrap_or(file);
+      if !entry.file_type().is_file() || name == Path::new(APP_CONF_PATH) {
+        continue;
+      }
+      let target = root.join(name);
+      let saved = if target.exists() {
+        let saved = backup.join(name);
+        create_parent(&saved)?;
+        fs::rename(&target, &saved)?;
+        Some(saved)
+      } else {
+        None
+      };
+      moved.push((target.clone(), saved));
+      create_parent(&target)?;
+      fs::rename(file, &target)?;
+    }
+    // `--config` may point to another filesystem, where a rename can't reach
+    let conf: Map<String, Value> =
+      serde_json::from_str(&fs::read_to_string(staging.join(APP_CONF_PATH))?)?;
+    utils::write_json(&AppConf::file_path(), &conf)?;
+    Ok(())
+  })();
+
+  if let Err(err) = res {
+    error!("bundle_import: {}, rolling back", err);
+    for (target, saved) in moved.into_iter().rev() {
+      let restored = match saved {
+        Some(saved) => fs::rename(&saved, &target),
+        None => fs::remove_file(&target),
+      };
+      restored.unwrap_or_else(|err| error!("bundle_rollback: {}: {}", target.display(), err));
+    }
+    fs::remove_dir_all(&backup).ok();
+    fs::remove_dir_all(staging).ok();
+    return Err(err);
+  }
+
+  if backup.exists() {
+    fs::remove_dir_all(&backup)?;
+  }
+  fs::remove_dir_all(staging)?;
+  info!("bundle_import: done");
+  Ok(())
+}
+
+fn create_parent(path: &Path) -> io::Result<()> {
+  match path.parent() {
+    Some(dir) => fs::create_dir_all(dir),
+    None => Ok(()),
+  }
+}
+
+const FILTER_NAME: &str = "QuickType Settings";
+
+/// Menu flow: asks where to save the bundle, then exports to it.
+pub fn export_dialog(app: &AppHandle) {
+  let app = app.clone();
+  let secs = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default();
+  dialog::FileDialogBuilder::new()
+    .add_filter(FILTER_NAME, &["zip"])
+    .set_file_name(&format!("quicktype-settings-{}.zip", secs))
+    .save_file(move |path| {
+      if let Some(path) = path {
+        let msg = match export(&path) {
+          Ok(_) => format!("PATH: {}", path.display()),
+          Err(err) => {
+            error!("bundle_export: {}", err);
+            err.to_string()
+          }
+        };
+        dialog::message(app.get_window("core").as_ref(), "Export Settings", msg);
+      }
+    });
+}
+
+/// Menu flow: picks a bundle, shows the settings it would change and imports it once confirmed.
+pub fn import_dialog(app: &AppHandle) {
+  let app = app.clone();
+  dialog::FileDialogBuilder::new()
+    .add_filter(FILTER_NAME, &["zip"])
+    .pick_file(move |path| {
+      let path = match path {
+        Some(v) => v,
+        None => return,
+      };
+      let win = app.get_window("core");
+      let (staging, changes) = match stage(&path) {
+        Ok(v) => v,
+        Err(err) => {
+          error!("bundle_import: {}", err);
+          dialog::message(win.as_ref(), "Import Settings", err.to_string());
+          return;
+        }
+      };
+
+      let mut msg: Vec<String> = changes
+        .iter()
+        .map(|c| format!("{}: {} -> {}", c.key, c.from, c.to))
+        .collect();
+      if msg.is_empty() {
+        msg.push("No settings change, saved files and scripts will be replaced.".into());
+      }
+      let handle = app.clone();
+      dialog::ask(
+        win.as_ref(),
+        "Import Settings",
+        format!("{}\n\nImport these settings?", msg.join("\n")),
+        move |is_ok| {
+          let res = if is_ok {
+            commit(&staging).map(|_| {
+              let app_conf = AppConf::read();
+              menu::refresh_profiles(&handle, &app_conf.profile);
+              apply::save(&handle, app_conf);
+            })
+          } else {
+            fs::remove_dir_all(&staging).map_err(Into::into)
+          };
+          if let Err(err) = res {
+            error!("bundle_import: {}", err);
+            dialog::message(
+              handle.get_window("core").as_ref(),
+              "Import Settings",
+              err.to_string(),
+            );
+          }
+        },
+      );
+    });
+}
+
+pub mod cmd {
+  use super::{Change, Result};
+  use crate::{
+    app::{apply, menu},
+    conf::AppConf,
+  };
+  use std::path::PathBuf;
+  use tauri::{command, AppHandle};
+
+  #[command]
+  pub fn export_settings(path: PathBuf) -> Result<PathBuf> {
+    super::export(&path)?;
+    Ok(path)
+  }
+
+  /// Returns the settings the bundle at `path` would change. Nothing is written unless `commit`
+  /// is set, so the settings UI can show the diff and ask before importing.
+  #[command]
+  pub fn import_settings(app: AppHandle, path: PathBuf, commit: bool) -> Result<Vec<Change>> {
+    let (staging, changes) = super::stage(&path)?;
+    if commit {
+      super::commit(&staging)?;
+      let app_conf = AppConf::read();
+      menu::refresh_profiles(&app, &app_conf.profile);
+      apply::save(&app, app_conf);
+    } else {
+      std::fs::remove_dir_all(staging)?;
+    }
+    Ok(changes)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cli;
+
+  // Exports a data directory with a config, a user script, a profile and a log, and returns the
+  // bundle, kept outside of it.
+  fn exported(home: &Path) -> PathBuf {
+    AppConf {
+      main_origin: "https://exported.example.com".into(),
+      ..AppConf::default()
+    }
+    .write();
+    fs::write(home.join("main.js"), "// exported").unwrap();
+    fs::create_dir_all(home.join("profiles")).unwrap();
+    fs::write(home.join("profiles/local.json"), "{}").unwrap();
+    fs::write(home.join("quick_type.log"), "").unwrap();
+
+    let path = home.with_extension("zip");
+    export(&path).unwrap();
+    path
+  }
+
+  #[test]
+  fn bundle_round_trips() {
+    let (_home, home) = cli::test_home();
+    let path = exported(&home);
+    fs::remove_dir_all(&home).unwrap();
+    AppConf::default().write();
+    fs::write(home.join("main.js"), "// edited").unwrap();
+
+    let (staging, changes) = stage(&path).unwrap();
+    let keys: Vec<_> = changes.iter().map(|c| c.key.as_str()).collect();
+    assert_eq!(keys, ["main_origin"]);
+    assert_eq!(changes[0].to, "https://exported.example.com");
+    // nothing changes until the import is committed
+    assert_eq!(
+      fs::read_to_string(home.join("main.js")).unwrap(),
+      "// edited"
+    );
+
+    commit(&staging).unwrap();
+    assert_eq!(AppConf::read().main_origin, "https://exported.example.com");
+    assert_eq!(
+      fs::read_to_string(home.join("main.js")).unwrap(),
+      "// exported"
+    );
+    assert!(home.join("profiles/local.json").exists());
+    assert!(!home.join("quick_type.log").exists());
+    assert!(!staging.exists());
+  }
+
+  #[test]
+  fn failed_import_is_rolled_back() {
+    let (_home, home) = cli::test_home();
+    let path = exported(&home);
+    fs::remove_dir_all(&home).unwrap();
+    fs::create_dir_all(&home).unwrap();
+    fs::write(home.join("main.js"), "// edited").unwrap();
+
+    let (staging, _) = stage(&path).unwrap();
+    // the config is written last, a directory in its place makes that step fail
+    fs::create_dir_all(AppConf::file_path().join("taken")).unwrap();
+    assert!(commit(&staging).is_err());
+
+    assert_eq!(
+      fs::read_to_string(home.join("main.js")).unwrap(),
+      "// edited"
+    );
+    assert!(!home.join("profiles/local.json").exists());
+    assert!(!home.join(BACKUP_DIR).exists());
+    assert!(!staging.exists());
+  }
+}
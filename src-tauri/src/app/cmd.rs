@@ -1,6 +1,6 @@
 use crate::utils;
 use log::error;
-use std::{fs, path::PathBuf};
+use std::path::PathBuf;
 use tauri::{api, command, AppHandle, Manager};
 
 #[command]
@@ -20,28 +20,26 @@ pub fn fullscreen(app: AppHandle) {
 
 #[command]
 pub fn download(app: AppHandle, name: String, blob: Vec<u8>) {
-  let win = app.app_handle().get_window("core");
-  let path = utils::app_root().join(PathBuf::from(name));
-  utils::create_file(&path).unwrap();
-  fs::write(&path, blob).unwrap();
-  tauri::api::dialog::message(
-    win.as_ref(),
-    "Save File",
-    format!("PATH: {}", path.display()),
-  );
+  save(&app, &name, &blob);
 }
 
 #[command]
 pub fn save_file(app: AppHandle, name: String, content: String) {
-  let win = app.app_handle().get_window("core");
+  save(&app, &name, content.as_bytes());
+}
+
+// Writes `name` in the data directory atomically and reports where it went.
+fn save(app: &AppHandle, name: &str, contents: &[u8]) {
+  let win = app.get_window("core");
   let path = utils::app_root().join(PathBuf::from(name));
-  utils::create_file(&path).unwrap();
-  fs::write(&path, content).unwrap();
-  tauri::api::dialog::message(
-    win.as_ref(),
-    "Save File",
-    format!("PATH: {}", path.display()),
-  );
+  let msg = match utils::write_atomic(&path, contents) {
+    Ok(_) => format!("PATH: {}", path.display()),
+    Err(err) => {
+      error!("save_file: {}: {}", path.display(), err);
+      format!("Failed to save {}: {}", path.display(), err)
+    }
+  };
+  tauri::api::dialog::message(win.as_ref(), "Save File", msg);
 }
 
 #[command]
@@ -0,0 +1,464 @@
+use super::{
+  naming::{Case, Namer, GO},
+  SourceFile,
+};
+use crate::app::infer::{ClassId, EnumId, Format, TopLevel, Type, TypeGraph};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+  collections::{BTreeSet, HashMap},
+  fmt::Write,
+};
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Options {
+  pub package: String,
+  pub files: Files,
+  pub optional_fields: OptionalFields,
+}
+
+impl Default for Options {
+  fn default() -> Self {
+    Self {
+      package: "models".into(),
+      files: Files::default(),
+      optional_fields: OptionalFields::default(),
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Files {
+  #[default]
+  Single,
+  /// `user.go`, `plan.go`, ...
+  PerType,
+}
+
+/// How fields that can be missing or `null` are typed. Optional fields are `omitempty` either way.
+/// `omitempty` never leaves out a struct, so those are pointers either way too.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum OptionalFields {
+  /// `*string`, `nil` when missing or `null`.
+  #[default]
+  Pointer,
+  /// `string`, the zero value when missing or `null`. Structs and `time.Time` are still pointers.
+  OmitEmpty,
+}
+
+// One top-level declaration and the packages it imports.
+struct Decl {
+  name: String,
+  code: String,
+  imports: BTreeSet<&'static str>,
+}
+
+struct Generator<'a> {
+  graph: &'a TypeGraph,
+  options: &'a Options,
+  namer: Namer,
+  class_names: HashMap<ClassId, String>,
+  enum_names: HashMap<EnumId, String>,
+  union_names: Vec<(Vec<Type>, String)>,
+  /// Imports of the declaration being written.
+  imports: BTreeSet<&'static str>,
+}
+
+pub fn generate(graph: &TypeGraph, options: &Options) -> Vec<SourceFile> {
+  let order = super::class_order(graph);
+  let mut namer = Namer::default();
+  let class_names = order
+    .iter()
+    .map(|&id| (id, namer.unique(GO.type_name(&graph.class(id).name))))
+    .collect();
+  let enums = super::enum_order(graph, &order);
+  let enum_names = enums
+    .iter()
+    .map(|&id| (id, namer.unique(GO.type_name(&graph.enum_(id).name))))
+    .collect();
+  let union_names = super::unions(graph, &order)
+    .into_iter()
+    .map(|(hint, members)| (members, namer.unique(GO.type_name(&hint))))
+    .collect();
+  let mut gen = Generator {
+    graph,
+    options,
+    namer,
+    class_names,
+    enum_names,
+    union_names,
+    imports: BTreeSet::new(),
+  };
+
+  let mut decls = vec![];
+  let mut top_names = vec![];
+  for top in &graph.top_levels {
+    let name = match gen.declared_name(top) {
+      Some(name) => name,
+      None => {
+        let name = gen.namer.unique(GO.type_name(&top.name));
+        let code = format!("type {} = {}\n", name, gen.go_type(&top.ty));
+        decls.push(gen.decl(name.clone(), code));
+        name
+      }
+    };
+    top_names.push(name);
+  }
+  for &id in &order {
+    let code = gen.write_struct(id);
+    decls.push(gen.decl(gen.class_names[&id].clone(), code));
+  }
+  for &id in &enums {
+    let code = gen.write_enum(id);
+    decls.push(gen.decl(gen.enum_names[&id].clone(), code));
+  }
+  for (members, name) in gen.union_names.clone() {
+    let code = gen.write_union(&name, &members);
+    decls.push(gen.decl(name, code));
+  }
+
+  let example = top_names.first().map(|name| {
+    format!(
+      "// Example code that decodes and encodes the model:\n//\n//\tvar model {}\n//\terr := json.Unmarshal(data, &model)\n//\tdata, err = json.Marshal(model)\n\n",
+      name
+    )
+  });
+  match options.files {
+    Files::Single => {
+      let imports = decls
+        .iter()
+        .flat_map(|d| d.imports.iter().copied())
+        .collect();
+      let body: Vec<&str> = decls.iter().map(|d| d.code.as_str()).collect();
+      let name = graph.top_levels.first().map_or("", |t| t.name.as_str());
+      vec![SourceFile {
+        name: file_name(name),
+        content: gen.file(example.as_deref(), &imports, &body.join("\n")),
+      }]
+    }
+    Files::PerType => decls
+      .iter()
+      .enumerate()
+      .map(|(i, decl)| SourceFile {
+        name: file_name(&decl.name),
+        content: gen.file(
+          example.as_deref().filter(|_| i == 0),
+          &decl.imports,
+          &decl.code,
+        ),
+      })
+      .collect(),
+  }
+}
+
+// `user_profile.go`. Suffixes the go tool reads as a test or a build constraint are avoided.
+fn file_name(name: &str) -> String {
+  const SUFFIXES: &[&str] = &[
+    "test", "linux", "windows", "darwin", "freebsd", "android", "ios", "js", "wasm", "386",
+    "amd64", "arm", "arm64",
+  ];
+  let mut file = Case::Snake.apply(name);
+  if file.is_empty() {
+    file = "models".into();
+  }
+  if SUFFIXES.iter().any(|s| file.ends_with(&format!("_{}", s))) {
+    file.push_str("_model");
+  }
+  format!("{}.go", file)
+}
+
+// Types that are `nil` already and need no pointer to be missing.
+fn is_nilable(ty: &Type) -> bool {
+  matches!(
+    ty,
+    Type::Any | Type::Null | Type::Array { .. } | Type::Map { .. }
+  )
+}
+
+// Types `omitempty` doesn't leave out when they are zero: structs, unions and `time.Time`.
+fn is_struct(ty: &Type) -> bool {
+  matches!(
+    ty,
+    Type::Class { .. }
+      | Type::Union { .. }
+      | Type::Format {
+        format: Format::DateTime
+      }
+  )
+}
+
+// Whether encoding/json takes `key` as the name in a tag, it falls back to the field name
+// otherwise. Commas and backquotes would break the tag, and while quotes and backslashes can be
+// escaped, encoding/json ignores a name holding them too.
+fn is_valid_tag(key: &str) -> bool {
+  !key.is_empty()
+    && key
+      .chars()
+      .all(|c| c.is_alphanumeric() || "!#$%&()*+-./:;<=>?@[]^_{|}~ ".contains(c))
+}
+
+// The order members are tried in when decoding a union, narrower ones first.
+fn rank(ty: &Type) -> usize {
+  match ty {
+    Type::Bool => 0,
+    Type::Integer => 1,
+    Type::Double => 2,
+    Type::Format { .. } => 3,
+    Type::Enum { .. } | Type::String => 4,
+    Type::Array { .. } => 5,
+    Type::Class { .. } => 6,
+    Type::Map { .. } => 7,
+    _ => 8,
+  }
+}
+
+impl Generator<'_> {
+  fn decl(&mut self, name: String, code: String) -> Decl {
+    Decl {
+      name,
+      code,
+      imports: std::mem::take(&mut self.imports),
+    }
+  }
+
+  fn file(&self, example: Option<&str>, imports: &BTreeSet<&str>, body: &str) -> String {
+    let mut out = String::new();
+    out.push_str(example.unwrap_or_default());
+    writeln!(out, "package {}\n", self.options.package).unwrap();
+    match imports.len() {
+      0 => {}
+      1 => writeln!(out, "import {:?}\n", imports.iter().next().unwrap()).unwrap(),
+      _ => {
+        out.push_str("import (\n");
+        for import in imports {
+          writeln!(out, "\t{:?}", import).unwrap();
+        }
+        out.push_str(")\n\n");
+      }
+    }
+    out.push_str(body);
+    out
+  }
+
+  // Top levels that are a class, enum or union of the same name need no alias. They go by the
+  // name it was declared under, which has a suffix when it clashed with another.
+  fn declared_name(&self, top: &TopLevel) -> Option<String> {
+    let name = GO.type_name(&top.name);
+    match &top.ty {
+      Type::Class { id } => {
+        (GO.type_name(&self.graph.class(*id).name) == name).then(|| self.class_names[id].clone())
+      }
+      Type::Enum { id } => {
+        (GO.type_name(&self.graph.enum_(*id).name) == name).then(|| self.enum_names[id].clone())
+      }
+      Type::Union { members } => Some(self.union_name(members)).filter(|v| *v == name),
+      _ => None,
+    }
+  }
+
+  fn go_type(&mut self, ty: &Type) -> String {
+    match ty {
+      Type::Any | Type::Null => "interface{}".into(),
+      Type::Bool => "bool".into(),
+      Type::Integer => "int64".into(),
+      Type::Double => "float64".into(),
+      Type::Format {
+        format: Format::DateTime,
+      } => {
+        self.imports.insert("time");
+        "time.Time".into()
+      }
+      Type::String | Type::Format { .. } => "string".into(),
+      Type::Enum { id } => self.enum_names[id].clone(),
+      Type::Array { items } => format!("[]{}", self.go_type(items)),
+      Type::Map { values } => format!("map[string]{}", self.go_type(values)),
+      Type::Class { id } => self.class_names[id].clone(),
+      Type::Union { members } => self.union_name(members),
+      Type::Nullable { inner } if is_nilable(inner) => self.go_type(inner),
+      Type::Nullable { inner } => format!("*{}", self.go_type(inner)),
+    }
+  }
+
+  fn union_name(&self, members: &[Type]) -> String {
+    self
+      .union_names
+      .iter()
+      .find(|(m, _)| m == members)
+      .map(|(_, name)| name.clone())
+      .unwrap_or_else(|| "interface{}".into())
+  }
+
+  fn write_struct(&mut self, id: ClassId) -> String {
+    let graph = self.graph;
+    let mut namer = Namer::default();
+    let mut fields = vec![];
+    let mut skipped = vec![];
+    for property in &graph.class(id).properties {
+      if !is_valid_tag(&property.name) {
+        skipped.push(Value::from(property.name.as_str()).to_string());
+        continue;
+      }
+      let (inner, nullable) = property.ty.non_null();
+      let mut ty = self.go_type(inner);
+      let missing = (nullable || property.optional)
+        && (self.options.optional_fields == OptionalFields::Pointer || is_struct(inner));
+      // a struct can't contain itself
+      if !is_nilable(inner) && (missing || super::reaches_inline(graph, inner, id)) {
+        ty = format!("*{}", ty);
+      }
+      let key = property.rename.as_ref().unwrap_or(&property.name);
+      let name = namer.unique(GO.field_name(key));
+      let omitempty = if property.optional { ",omitempty" } else { "" };
+      let tag = format!("`json:\"{}{}\"`", property.name, omitempty);
+      fields.push([name, ty, tag]);
+    }
+    let mut out = String::new();
+    if !skipped.is_empty() {
+      writeln!(
+        out,
+        "// Not decoded, encoding/json can't name these keys in a tag: {}.",
+        skipped.join(", ")
+      )
+      .unwrap();
+    }
+    write!(
+      out,
+      "type {} struct {{\n{}}}\n",
+      self.class_names[&id],
+      aligned(&fields)
+    )
+    .unwrap();
+    out
+  }
+
+  fn write_enum(&mut self, id: EnumId) -> String {
+    let name = self.enum_names[&id].clone();
+    let constants: Vec<[String; 3]> = self
+      .graph
+      .enum_(id)
+      .values
+      .iter()
+      .map(|value| {
+        let constant = self
+          .namer
+          .unique(GO.type_name(&format!("{} {}", name, value)));
+        [
+          constant,
+          name.clone(),
+          format!("= {}", Value::from(value.as_str())),
+        ]
+      })
+      .collect();
+    format!(
+      "type {} string\n\nconst (\n{})\n",
+      name,
+      aligned(&constants)
+    )
+  }
+
+  // Go has no unions, a union is a struct with one field per member, of which one is set.
+  fn write_union(&mut self, name: &str, members: &[Type]) -> String {
+    self.imports.extend(["encoding/json", "fmt"]);
+    let mut members: Vec<&Type> = members.iter().collect();
+    members.sort_by_key(|m| rank(m));
+    let mut namer = Namer::default();
+    let fields: Vec<[String; 3]> = members
+      .iter()
+      .map(|member| {
+        let ty = self.go_type(member);
+        let ty = if is_nilable(member) {
+          ty
+        } else {
+          format!("*{}", ty)
+        };
+        [namer.unique(self.variant_name(member)), ty, String::new()]
+      })
+      .collect();
+
+    let mut out = String::new();
+    writeln!(
+      out,
+      "// {} is one of several types, one field is set.",
+      name
+    )
+    .unwrap();
+    write!(out, "type {} struct {{\n{}}}\n\n", name, aligned(&fields)).unwrap();
+
+    writeln!(
+      out,
+      "func (x *{}) UnmarshalJSON(data []byte) error {{",
+      name
+    )
+    .unwrap();
+    writeln!(out, "\t*x = {}{{}}", name).unwrap();
+    out.push_str("\tif string(data) == \"null\" {\n\t\treturn nil\n\t}\n");
+    for [field, ..] in &fields {
+      writeln!(
+        out,
+        "\tif err := json.Unmarshal(data, &x.{0}); err == nil {{\n\t\treturn nil\n\t}}\n\tx.{0} = nil",
+        field
+      )
+      .unwrap();
+    }
+    writeln!(
+      out,
+      "\treturn fmt.Errorf(\"cannot unmarshal %s into {}\", data)\n}}\n",
+      name
+    )
+    .unwrap();
+
+    writeln!(out, "func (x {}) MarshalJSON() ([]byte, error) {{", name).unwrap();
+    for [field, ..] in &fields {
+      writeln!(
+        out,
+        "\tif x.{0} != nil {{\n\t\treturn json.Marshal(x.{0})\n\t}}",
+        field
+      )
+      .unwrap();
+    }
+    out.push_str("\treturn []byte(\"null\"), nil\n}\n");
+    out
+  }
+
+  fn variant_name(&self, ty: &Type) -> String {
+    match ty {
+      Type::Any | Type::Null => "Value".into(),
+      Type::Bool => "Bool".into(),
+      Type::Integer => "Integer".into(),
+      Type::Double => "Double".into(),
+      Type::String | Type::Format { .. } => "String".into(),
+      Type::Enum { id } => self.enum_names[id].clone(),
+      Type::Array { items } => format!("{}Array", self.variant_name(items)),
+      Type::Map { values } => format!("{}Map", self.variant_name(values)),
+      Type::Class { id } => self.class_names[id].clone(),
+      Type::Union { members } => self.union_name(members),
+      Type::Nullable { inner } => self.variant_name(inner),
+    }
+  }
+}
+
+// Indented lines with their columns lined up, as gofmt does.
+fn aligned(rows: &[[String; 3]]) -> String {
+  let width = |i: usize| rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0);
+  let (name_width, type_width) = (width(0), width(1));
+  let mut out = String::new();
+  for [name, ty, rest] in rows {
+    let line = if rest.is_empty() {
+      format!("\t{:<w$} {}", name, ty, w = name_width)
+    } else {
+      format!(
+        "\t{:<w$} {:<t$} {}",
+        name,
+        ty,
+        rest,
+        w = name_width,
+        t = type_width
+      )
+    };
+    writeln!(out, "{}", line).unwrap();
+  }
+  out
+}
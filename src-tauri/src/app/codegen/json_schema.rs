@@ -0,0 +1,179 @@
+use super::{
+  naming::{Case, Namer, JSON_SCHEMA},
+  SourceFile,
+};
+use crate::app::infer::{ClassId, EnumId, Format, Type, TypeGraph};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Options {
+  pub draft: Draft,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum Draft {
+  #[serde(rename = "draft-07")]
+  Draft07,
+  #[default]
+  #[serde(rename = "2020-12")]
+  Draft2020_12,
+}
+
+impl Draft {
+  fn uri(self) -> &'static str {
+    match self {
+      Self::Draft07 => "http://json-schema.org/draft-07/schema#",
+      Self::Draft2020_12 => "https://json-schema.org/draft/2020-12/schema",
+    }
+  }
+
+  // Where named schemas live.
+  fn definitions(self) -> &'static str {
+    match self {
+      Self::Draft07 => "definitions",
+      Self::Draft2020_12 => "$defs",
+    }
+  }
+}
+
+struct Generator<'a> {
+  graph: &'a TypeGraph,
+  options: &'a Options,
+  class_names: HashMap<ClassId, String>,
+  enum_names: HashMap<EnumId, String>,
+}
+
+/// One `<name>.schema.json` per top level, holding the classes and enums it uses.
+pub fn generate(graph: &TypeGraph, options: &Options) -> Vec<SourceFile> {
+  graph
+    .top_levels
+    .iter()
+    .map(|top| {
+      let single = TypeGraph {
+        top_levels: vec![top.clone()],
+        ..graph.clone()
+      };
+      let order = super::class_order(&single);
+      let enums = super::enum_order(&single, &order);
+      let mut namer = Namer::default();
+      let gen = Generator {
+        graph,
+        options,
+        class_names: order
+          .iter()
+          .map(|&id| {
+            (
+              id,
+              namer.unique(JSON_SCHEMA.type_name(&graph.class(id).name)),
+            )
+          })
+          .collect(),
+        enum_names: enums
+          .iter()
+          .map(|&id| {
+            (
+              id,
+              namer.unique(JSON_SCHEMA.type_name(&graph.enum_(id).name)),
+            )
+          })
+          .collect(),
+      };
+
+      let mut definitions = Map::new();
+      for &id in &order {
+        definitions.insert(gen.class_names[&id].clone(), gen.class(id));
+      }
+      for &id in &enums {
+        definitions.insert(
+          gen.enum_names[&id].clone(),
+          json!({ "type": "string", "enum": graph.enum_(id).values }),
+        );
+      }
+
+      let mut root = Map::new();
+      root.insert("$schema".into(), options.draft.uri().into());
+      root.insert("title".into(), JSON_SCHEMA.type_name(&top.name).into());
+      match gen.schema(&top.ty) {
+        Value::Object(schema) => root.extend(schema),
+        _ => unreachable!(),
+      }
+      if !definitions.is_empty() {
+        root.insert(options.draft.definitions().into(), definitions.into());
+      }
+
+      let file = Case::Kebab.apply(&top.name);
+      SourceFile {
+        name: format!(
+          "{}.schema.json",
+          if file.is_empty() { "models" } else { &file }
+        ),
+        content: format!("{}\n", serde_json::to_string_pretty(&root).unwrap()),
+      }
+    })
+    .collect()
+}
+
+impl Generator<'_> {
+  fn reference(&self, name: &str) -> Value {
+    json!({ "$ref": format!("#/{}/{}", self.options.draft.definitions(), name) })
+  }
+
+  fn schema(&self, ty: &Type) -> Value {
+    match ty {
+      Type::Any => json!({}),
+      Type::Null => json!({ "type": "null" }),
+      Type::Bool => json!({ "type": "boolean" }),
+      Type::Integer => json!({ "type": "integer" }),
+      Type::Double => json!({ "type": "number" }),
+      Type::String => json!({ "type": "string" }),
+      Type::Format {
+        format: Format::IntegerString,
+      } => json!({ "type": "string", "pattern": "^-?(0|[1-9]\\d*)$" }),
+      Type::Format {
+        format: Format::BoolString,
+      } => json!({ "type": "string", "enum": ["true", "false"] }),
+      Type::Format { format } => json!({ "type": "string", "format": format.as_str() }),
+      Type::Enum { id } => self.reference(&self.enum_names[id]),
+      Type::Array { items } => json!({ "type": "array", "items": self.schema(items) }),
+      Type::Map { values } => {
+        json!({ "type": "object", "additionalProperties": self.schema(values) })
+      }
+      Type::Class { id } => self.reference(&self.class_names[id]),
+      Type::Union { members } => {
+        json!({ "anyOf": members.iter().map(|m| self.schema(m)).collect::<Vec<_>>() })
+      }
+      Type::Nullable { inner } => match self.schema(inner) {
+        // `{ "type": ["string", "null"] }` when nothing else constrains the value
+        Value::Object(schema)
+          if schema.len() == 1 && schema.get("type").is_some_and(Value::is_string) =>
+        {
+          json!({ "type": [schema["type"].clone(), "null"] })
+        }
+        schema => json!({ "anyOf": [schema, { "type": "null" }] }),
+      },
+    }
+  }
+
+  fn class(&self, id: ClassId) -> Value {
+    let class = self.graph.class(id);
+    let properties: Map<String, Value> = class
+      .properties
+      .iter()
+      .map(|p| (p.name.clone(), self.schema(&p.ty)))
+      .collect();
+    let required: Vec<&str> = class
+      .properties
+      .iter()
+      .filter(|p| !p.optional)
+      .map(|p| p.name.as_str())
+      .collect();
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+      schema["required"] = required.into();
+    }
+    schema
+  }
+}
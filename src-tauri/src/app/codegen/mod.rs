@@ -0,0 +1,185 @@
+//! Offline code generators, each turns an `infer::TypeGraph` into source files for one language.
+
+pub mod go;
+pub mod json_schema;
+pub mod naming;
+pub mod python;
+pub mod rust;
+pub mod typescript;
+
+#[cfg(test)]
+mod tests;
+
+use crate::app::infer::{self, ClassId, EnumId, Type, TypeGraph};
+use serde::{ser::Serializer, Deserialize, Serialize};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+  #[error(transparent)]
+  Infer(#[from] infer::Error),
+}
+
+impl Serialize for Error {
+  fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    serializer.serialize_str(self.to_string().as_ref())
+  }
+}
+
+/// The language to generate and its options: `{ "lang": "rust", "visibility": "pub", ... }`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "lang", rename_all = "camelCase")]
+pub enum Target {
+  Rust(rust::Options),
+  #[serde(rename = "typescript")]
+  TypeScript(typescript::Options),
+  JsonSchema(json_schema::Options),
+  Go(go::Options),
+  Python(python::Options),
+}
+
+/// A generated file, ready for `cmd::save_file` as it is.
+#[derive(Serialize, Debug, Clone)]
+pub struct SourceFile {
+  pub name: String,
+  pub content: String,
+}
+
+pub fn generate(graph: &TypeGraph, target: &Target) -> Vec<SourceFile> {
+  match target {
+    Target::Rust(options) => rust::generate(graph, options),
+    Target::TypeScript(options) => typescript::generate(graph, options),
+    Target::JsonSchema(options) => json_schema::generate(graph, options),
+    Target::Go(options) => go::generate(graph, options),
+    Target::Python(options) => python::generate(graph, options),
+  }
+}
+
+/// Classes reachable from the top levels, in the order they are first referenced.
+pub fn class_order(graph: &TypeGraph) -> Vec<ClassId> {
+  let mut order = vec![];
+  let mut pending: Vec<&Type> = graph.top_levels.iter().map(|t| &t.ty).collect();
+  pending.reverse();
+  while let Some(ty) = pending.pop() {
+    let mut found = vec![];
+    visit(ty, &mut |ty| {
+      if let Type::Class { id } = ty {
+        found.push(*id);
+      }
+    });
+    for id in found {
+      if !order.contains(&id) {
+        order.push(id);
+        let class = graph.class(id);
+        pending.extend(class.properties.iter().rev().map(|p| &p.ty));
+      }
+    }
+  }
+  order
+}
+
+/// Calls `f` on `ty` and every type nested in it, without following class references.
+pub fn visit<'a>(ty: &'a Type, f: &mut impl FnMut(&'a Type)) {
+  f(ty);
+  match ty {
+    Type::Array { items: inner } | Type::Map { values: inner } | Type::Nullable { inner } => {
+      visit(inner, f)
+    }
+    Type::Union { members } => members.iter().for_each(|m| visit(m, f)),
+    _ => {}
+  }
+}
+
+// Every type used by a top level or a property of `order`, with the name it is used under.
+fn uses<'a>(graph: &'a TypeGraph, order: &[ClassId]) -> Vec<(&'a str, &'a Type)> {
+  let mut uses: Vec<(&str, &Type)> = graph
+    .top_levels
+    .iter()
+    .map(|t| (t.name.as_str(), &t.ty))
+    .collect();
+  for &id in order {
+    uses.extend(
+      graph
+        .class(id)
+        .properties
+        .iter()
+        .map(|p| (p.name.as_str(), &p.ty)),
+    );
+  }
+  uses
+}
+
+/// Unions that need a declaration of their own, with the key each is first used under, or its
+/// top-level name if no key uses it. Identical unions are listed once. Keys come first, so the
+/// name of an alias isn't given to an anonymous union that happens to have the same members.
+pub fn unions(graph: &TypeGraph, order: &[ClassId]) -> Vec<(String, Vec<Type>)> {
+  let mut uses = uses(graph, order);
+  uses.rotate_left(graph.top_levels.len());
+  let mut unions: Vec<(String, Vec<Type>)> = vec![];
+  for (name, ty) in uses {
+    visit(ty, &mut |ty| {
+      if let Type::Union { members } = ty {
+        if !unions.iter().any(|(_, m)| m == members) {
+          unions.push((name.to_string(), members.clone()));
+        }
+      }
+    });
+  }
+  unions
+}
+
+/// Enums used by the top levels and the classes of `order`, in the order they are first used.
+pub fn enum_order(graph: &TypeGraph, order: &[ClassId]) -> Vec<EnumId> {
+  let mut enums = vec![];
+  for (_, ty) in uses(graph, order) {
+    visit(ty, &mut |ty| {
+      if let Type::Enum { id } = ty {
+        if !enums.contains(id) {
+          enums.push(*id);
+        }
+      }
+    });
+  }
+  enums
+}
+
+/// Whether `to` can be reached from `from` through properties that are stored inline, i.e. not
+/// behind an array or a map. Such cycles need indirection in languages like Rust and Go.
+pub fn reaches_inline(graph: &TypeGraph, from: &Type, to: ClassId) -> bool {
+  let mut seen = vec![];
+  let mut pending = vec![from];
+  while let Some(ty) = pending.pop() {
+    match ty {
+      Type::Class { id } if *id == to => return true,
+      Type::Class { id } if !seen.contains(id) => {
+        seen.push(*id);
+        pending.extend(graph.class(*id).properties.iter().map(|p| &p.ty));
+      }
+      Type::Nullable { inner } => pending.push(inner),
+      Type::Union { members } => pending.extend(members),
+      _ => {}
+    }
+  }
+  false
+}
+
+pub mod cmd {
+  use super::{Result, SourceFile, Target};
+  use crate::app::infer::{self, Options, Source};
+  use tauri::command;
+
+  /// Infers the types of `sources` and generates code for them, without reaching the web app.
+  #[command]
+  pub async fn generate_code(
+    sources: Vec<Source>,
+    target: Target,
+    options: Option<Options>,
+  ) -> Result<Vec<SourceFile>> {
+    let graph = infer::infer(&sources, &options.unwrap_or_default())?;
+    Ok(super::generate(&graph, &target))
+  }
+}
@@ -0,0 +1,422 @@
+//! Turns JSON keys and type hints into identifiers that are legal and idiomatic in each target
+//! language: `first-name`, `2fa`, `type` or `Ümlaut` become `first_name`, `field_2fa`, `r#type`
+//! and `umlaut` in Rust.
+
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Case {
+  /// `FirstName`
+  Pascal,
+  /// `firstName`
+  Camel,
+  /// `first_name`
+  Snake,
+  /// `first-name`
+  Kebab,
+  /// `FIRST_NAME`
+  ScreamingSnake,
+}
+
+impl Case {
+  pub fn apply(self, s: &str) -> String {
+    self.apply_with(s, &[])
+  }
+
+  /// Like `apply`, with the words of `initialisms` kept in capitals in `Pascal` and `Camel` case:
+  /// `user_id` -> `UserID`.
+  pub fn apply_with(self, s: &str, initialisms: &[&str]) -> String {
+    let words = words(s);
+    let title = |w: &String| {
+      let upper = w.to_uppercase();
+      if initialisms.contains(&upper.as_str()) {
+        upper
+      } else {
+        capitalize(w)
+      }
+    };
+    match self {
+      Self::Pascal => words.iter().map(title).collect(),
+      Self::Camel => words
+        .iter()
+        .enumerate()
+        .map(|(i, w)| if i == 0 { w.to_lowercase() } else { title(w) })
+        .collect(),
+      Self::Snake => join(&words, "_", str::to_lowercase),
+      Self::Kebab => join(&words, "-", str::to_lowercase),
+      Self::ScreamingSnake => join(&words, "_", str::to_uppercase),
+    }
+  }
+}
+
+fn join(words: &[String], separator: &str, f: fn(&str) -> String) -> String {
+  words
+    .iter()
+    .map(|w| f(w))
+    .collect::<Vec<_>>()
+    .join(separator)
+}
+
+fn capitalize(word: &str) -> String {
+  let mut chars = word.chars();
+  match chars.next() {
+    Some(first) => first
+      .to_uppercase()
+      .chain(chars.flat_map(char::to_lowercase))
+      .collect(),
+    None => String::new(),
+  }
+}
+
+/// Splits a JSON key into ASCII words: `userID` -> `user`, `ID`; `first-name` -> `first`,
+/// `name`; `HTTPServer` -> `HTTP`, `Server`. Other characters are transliterated or dropped.
+pub fn words(s: &str) -> Vec<String> {
+  let chars: Vec<char> = transliterate(s).chars().collect();
+  let mut words = vec![];
+  let mut word = String::new();
+  for (i, &c) in chars.iter().enumerate() {
+    if !c.is_ascii_alphanumeric() {
+      if !word.is_empty() {
+        words.push(std::mem::take(&mut word));
+      }
+      continue;
+    }
+    if let Some(&prev) = word.chars().last().as_ref() {
+      let next = chars.get(i + 1);
+      let boundary = c.is_ascii_uppercase()
+        && (prev.is_ascii_lowercase()
+          || prev.is_ascii_digit()
+          || (prev.is_ascii_uppercase() && next.is_some_and(|n| n.is_ascii_lowercase())));
+      if boundary {
+        words.push(std::mem::take(&mut word));
+      }
+    }
+    word.push(c);
+  }
+  if !word.is_empty() {
+    words.push(word);
+  }
+  words
+}
+
+// Latin letters with diacritics and ligatures, by their ASCII spelling.
+const TRANSLITERATIONS: &[(&str, &str)] = &[
+  ("ÀÁÂÃÄÅĀĂĄ", "A"),
+  ("àáâãäåāăą", "a"),
+  ("ÇĆĈĊČ", "C"),
+  ("çćĉċč", "c"),
+  ("ĎĐÐ", "D"),
+  ("ďđð", "d"),
+  ("ÈÉÊËĒĔĖĘĚ", "E"),
+  ("èéêëēĕėęě", "e"),
+  ("ĜĞĠĢ", "G"),
+  ("ĝğġģ", "g"),
+  ("ĤĦ", "H"),
+  ("ĥħ", "h"),
+  ("ÌÍÎÏĨĪĬĮİ", "I"),
+  ("ìíîïĩīĭįı", "i"),
+  ("Ĵ", "J"),
+  ("ĵ", "j"),
+  ("Ķ", "K"),
+  ("ķ", "k"),
+  ("ĹĻĽĿŁ", "L"),
+  ("ĺļľŀł", "l"),
+  ("ÑŃŅŇ", "N"),
+  ("ñńņň", "n"),
+  ("ÒÓÔÕÖØŌŎŐ", "O"),
+  ("òóôõöøōŏő", "o"),
+  ("ŔŖŘ", "R"),
+  ("ŕŗř", "r"),
+  ("ŚŜŞŠ", "S"),
+  ("śŝşš", "s"),
+  ("ŢŤŦ", "T"),
+  ("ţťŧ", "t"),
+  ("ÙÚÛÜŨŪŬŮŰŲ", "U"),
+  ("ùúûüũūŭůűų", "u"),
+  ("Ŵ", "W"),
+  ("ŵ", "w"),
+  ("ÝŸŶ", "Y"),
+  ("ýÿŷ", "y"),
+  ("ŹŻŽ", "Z"),
+  ("źżž", "z"),
+  ("Æ", "AE"),
+  ("æ", "ae"),
+  ("Œ", "OE"),
+  ("œ", "oe"),
+  ("Þ", "TH"),
+  ("þ", "th"),
+  ("ß", "ss"),
+];
+
+/// `Ümlaut` -> `Umlaut`. Characters without an ASCII spelling are kept as they are.
+pub fn transliterate(s: &str) -> String {
+  let mut out = String::with_capacity(s.len());
+  for c in s.chars() {
+    if c.is_ascii() {
+      out.push(c);
+      continue;
+    }
+    match TRANSLITERATIONS.iter().find(|(from, _)| from.contains(c)) {
+      Some((_, to)) => out.push_str(to),
+      None => out.push(c),
+    }
+  }
+  out
+}
+
+/// How one target language spells identifiers.
+pub struct Language {
+  pub types: Case,
+  pub fields: Case,
+  /// Words that can't be used as identifiers as they are.
+  pub keywords: &'static [&'static str],
+  /// Makes a keyword usable as an identifier.
+  pub escape: fn(&str) -> String,
+  /// Words spelled in capitals inside type and field names, e.g. `ID` and `URL`.
+  pub initialisms: &'static [&'static str],
+}
+
+impl Language {
+  /// `first-name` -> `FirstName`, `2fa` -> `T2fa`.
+  pub fn type_name(&self, hint: &str) -> String {
+    self.identifier(self.types, hint, "Type", "T")
+  }
+
+  /// `first-name` -> `first_name` in Rust, `2fa` -> `field_2fa`.
+  pub fn field_name(&self, key: &str) -> String {
+    self.identifier(self.fields, key, "field", "field")
+  }
+
+  /// `in progress` -> `IN_PROGRESS`, for enum members.
+  pub fn constant_name(&self, value: &str) -> String {
+    self.identifier(Case::ScreamingSnake, value, "value", "value")
+  }
+
+  fn identifier(&self, case: Case, s: &str, empty: &str, prefix: &str) -> String {
+    let name = case.apply_with(s, self.initialisms);
+    let name = match name.chars().next() {
+      None => case.apply(empty),
+      Some(c) if c.is_ascii_digit() => {
+        case.apply_with(&format!("{}_{}", prefix, name), self.initialisms)
+      }
+      Some(_) => name,
+    };
+    if self.keywords.contains(&name.as_str()) {
+      (self.escape)(&name)
+    } else {
+      name
+    }
+  }
+}
+
+pub const RUST: Language = Language {
+  types: Case::Pascal,
+  fields: Case::Snake,
+  keywords: &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+  ],
+  escape: |name| match name {
+    // can't be raw identifiers
+    "self" | "Self" | "super" | "crate" => format!("{}_", name),
+    _ => format!("r#{}", name),
+  },
+  initialisms: &[],
+};
+
+pub const TYPESCRIPT: Language = Language {
+  types: Case::Pascal,
+  fields: Case::Camel,
+  keywords: &[
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "implements",
+    "interface",
+    "let",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "static",
+    "yield",
+  ],
+  escape: |name| format!("{}_", name),
+  initialisms: &[],
+};
+
+pub const PYTHON: Language = Language {
+  types: Case::Pascal,
+  fields: Case::Snake,
+  // and the builtins the generated annotations use
+  keywords: &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield", "bool", "float", "int", "str",
+  ],
+  escape: |name| format!("{}_", name),
+  initialisms: &[],
+};
+
+/// Fields are exported, `encoding/json` only sees those. Capitalized names are never keywords.
+pub const GO: Language = Language {
+  types: Case::Pascal,
+  fields: Case::Pascal,
+  keywords: &[],
+  escape: |name| name.into(),
+  // the ones golint knows
+  initialisms: &[
+    "ACL", "API", "ASCII", "CPU", "CSS", "DNS", "EOF", "GUID", "HTML", "HTTP", "HTTPS", "ID", "IP",
+    "JSON", "LHS", "QPS", "RAM", "RHS", "RPC", "SLA", "SMTP", "SQL", "SSH", "TCP", "TLS", "TTL",
+    "UDP", "UI", "UID", "UUID", "URI", "URL", "UTF8", "VM", "XML", "XMPP", "XSRF", "XSS",
+  ],
+};
+
+/// Definition names in a JSON Schema, any string would do.
+pub const JSON_SCHEMA: Language = Language {
+  types: Case::Pascal,
+  fields: Case::Camel,
+  keywords: &[],
+  escape: |name| name.into(),
+  initialisms: &[],
+};
+
+/// Hands out names that are unique within one scope, numbering repeats in the order they are asked
+/// for: `Item`, `Item2`.
+#[derive(Default)]
+pub struct Namer {
+  taken: HashSet<String>,
+}
+
+impl Namer {
+  pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(reserved: I) -> Self {
+    Self {
+      taken: reserved.into_iter().map(Into::into).collect(),
+    }
+  }
+
+  pub fn unique(&mut self, name: String) -> String {
+    let mut candidate = name.clone();
+    let mut n = 1;
+    while self.taken.contains(&candidate) {
+      n += 1;
+      candidate = format!("{}{}", name, n);
+    }
+    self.taken.insert(candidate.clone());
+    candidate
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn keys_are_split_into_words() {
+    assert_eq!(words("userID"), ["user", "ID"]);
+    assert_eq!(words("first-name"), ["first", "name"]);
+    assert_eq!(words("HTTPServer"), ["HTTP", "Server"]);
+    assert_eq!(words("__snake_case__"), ["snake", "case"]);
+    assert_eq!(words("page2Count"), ["page2", "Count"]);
+    assert_eq!(words("Ümlaut größe"), ["Umlaut", "grosse"]);
+    assert!(words(" -_ ").is_empty());
+  }
+
+  #[test]
+  fn words_are_cased() {
+    let cased: Vec<String> = [
+      Case::Pascal,
+      Case::Camel,
+      Case::Snake,
+      Case::Kebab,
+      Case::ScreamingSnake,
+    ]
+    .iter()
+    .map(|case| case.apply("user_ID url"))
+    .collect();
+    assert_eq!(
+      cased,
+      [
+        "UserIdUrl",
+        "userIdUrl",
+        "user_id_url",
+        "user-id-url",
+        "USER_ID_URL"
+      ]
+    );
+    assert_eq!(
+      Case::Pascal.apply_with("user_id url", &["ID", "URL"]),
+      "UserIDURL"
+    );
+    assert_eq!(Case::Camel.apply_with("id_url", &["ID", "URL"]), "idURL");
+    assert_eq!(Case::Snake.apply_with("userID", &["ID"]), "user_id");
+  }
+
+  #[test]
+  fn latin_letters_are_transliterated() {
+    assert_eq!(transliterate("Ærøskøbing"), "AEroskobing");
+    assert_eq!(transliterate("Straße"), "Strasse");
+    assert_eq!(transliterate("日本 ok"), "日本 ok");
+  }
+
+  #[test]
+  fn identifiers_are_legal() {
+    assert_eq!(RUST.field_name("type"), "r#type");
+    assert_eq!(RUST.field_name("self"), "self_");
+    assert_eq!(RUST.type_name("Self"), "Self_");
+    assert_eq!(RUST.field_name("2fa"), "field_2fa");
+    assert_eq!(RUST.type_name("2fa"), "T2fa");
+    assert_eq!(RUST.field_name("日本"), "field");
+    assert_eq!(TYPESCRIPT.field_name("class"), "class_");
+    assert_eq!(PYTHON.field_name("from"), "from_");
+    assert_eq!(PYTHON.field_name("str"), "str_");
+    assert_eq!(GO.field_name("user_id"), "UserID");
+    assert_eq!(GO.constant_name("in progress"), "IN_PROGRESS");
+  }
+
+  #[test]
+  fn repeated_names_are_numbered() {
+    let mut namer = Namer::new(["Item"]);
+    assert_eq!(namer.unique("Item".into()), "Item2");
+    assert_eq!(namer.unique("User".into()), "User");
+    assert_eq!(namer.unique("Item".into()), "Item3");
+    assert_eq!(namer.unique("User".into()), "User2");
+  }
+}
@@ -0,0 +1,652 @@
+use super::{
+  naming::{Namer, PYTHON},
+  SourceFile,
+};
+use crate::app::infer::{ClassId, EnumId, Format, Property, TopLevel, Type, TypeGraph};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+  collections::{BTreeSet, HashMap},
+  fmt::Write,
+};
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Options {
+  pub style: Style,
+}
+
+/// The generated code runs on Python 3.8 and later.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Style {
+  /// `@dataclass` classes with `from_dict` and `to_dict`.
+  #[default]
+  Dataclass,
+  /// pydantic v2 `BaseModel`s.
+  Pydantic,
+  /// `TypedDict`s describing the parsed JSON as it is.
+  TypedDict,
+}
+
+// Names the generated code imports.
+const PRELUDE: &[&str] = &[
+  "Any",
+  "BaseModel",
+  "ConfigDict",
+  "Dict",
+  "Enum",
+  "Field",
+  "List",
+  "Literal",
+  "Optional",
+  "TypeAdapter",
+  "TypedDict",
+  "UUID",
+  "Union",
+];
+
+// Attributes of `BaseModel` a field would shadow, and imported types: pydantic evaluates
+// `date: Optional[date]` in the class body, where `date` is the field.
+const MODEL_RESERVED: &[&str] = &[
+  "construct",
+  "copy",
+  "dict",
+  "fields",
+  "json",
+  "model_config",
+  "model_fields",
+  "schema",
+  "validate",
+  "date",
+  "datetime",
+  "time",
+];
+
+struct Generator<'a> {
+  graph: &'a TypeGraph,
+  options: &'a Options,
+  class_names: HashMap<ClassId, String>,
+  enum_names: HashMap<EnumId, String>,
+  /// `(module, name)`
+  imports: BTreeSet<(&'static str, &'static str)>,
+}
+
+pub fn generate(graph: &TypeGraph, options: &Options) -> Vec<SourceFile> {
+  let order = super::class_order(graph);
+  let mut namer = Namer::new(PRELUDE.iter().copied());
+  let class_names = order
+    .iter()
+    .map(|&id| (id, namer.unique(PYTHON.type_name(&graph.class(id).name))))
+    .collect();
+  let enums = super::enum_order(graph, &order);
+  let enum_names = enums
+    .iter()
+    .map(|&id| (id, namer.unique(PYTHON.type_name(&graph.enum_(id).name))))
+    .collect();
+  let mut gen = Generator {
+    graph,
+    options,
+    class_names,
+    enum_names,
+    imports: BTreeSet::new(),
+  };
+
+  let mut blocks = vec![];
+  for &id in &enums {
+    blocks.push(gen.write_enum(id));
+  }
+  // dependencies first, so pydantic can resolve most annotations right away
+  for &id in order.iter().rev() {
+    blocks.push(match options.style {
+      Style::Dataclass => gen.write_dataclass(id),
+      Style::Pydantic => gen.write_model(id),
+      Style::TypedDict => gen.write_typed_dict(id),
+    });
+  }
+  let mut top_names = vec![];
+  for top in &graph.top_levels {
+    let name = match gen.declared_name(top) {
+      Some(name) => name,
+      None => {
+        let name = namer.unique(PYTHON.type_name(&top.name));
+        blocks.push(format!("{} = {}\n", name, gen.py_type(&top.ty)));
+        name
+      }
+    };
+    top_names.push(name);
+  }
+  if options.style == Style::Dataclass {
+    for top in &graph.top_levels {
+      blocks.push(gen.write_functions(top));
+    }
+  }
+
+  let mut content = String::new();
+  if let (Some(top), Some(name)) = (graph.top_levels.first(), top_names.first()) {
+    content.push_str("# Example code that parses and serializes the model:\n#\n");
+    content.push_str(&gen.example(top, name));
+    content.push('\n');
+  }
+  content.push_str("from __future__ import annotations\n\n");
+  gen.write_imports(&mut content);
+  content.push_str(&blocks.join("\n\n"));
+
+  let file = graph
+    .top_levels
+    .first()
+    .map_or("models".into(), |t| PYTHON.field_name(&t.name));
+  vec![SourceFile {
+    name: format!("{}.py", file.trim_end_matches('_')),
+    content,
+  }]
+}
+
+fn is_identifier(key: &str) -> bool {
+  key
+    .chars()
+    .next()
+    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+    && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    && !PYTHON.keywords.contains(&key)
+}
+
+// A Python string literal.
+fn literal(s: &str) -> String {
+  Value::from(s).to_string()
+}
+
+// The order members are checked in when converting a union, `bool` before `int`.
+fn rank(ty: &Type) -> usize {
+  match ty {
+    Type::Bool => 0,
+    Type::Integer => 1,
+    Type::Double => 2,
+    Type::String | Type::Format { .. } | Type::Enum { .. } => 3,
+    Type::Array { .. } => 4,
+    Type::Class { .. } | Type::Map { .. } => 5,
+    _ => 6,
+  }
+}
+
+impl Generator<'_> {
+  fn import(&mut self, module: &'static str, name: &'static str) {
+    self.imports.insert((module, name));
+  }
+
+  // The standard library first, then pydantic, one line per module.
+  fn write_imports(&self, out: &mut String) {
+    let mut modules: Vec<(&str, Vec<&str>)> = vec![];
+    for &(module, name) in &self.imports {
+      match modules.last_mut() {
+        Some((last, names)) if *last == module => names.push(name),
+        _ => modules.push((module, vec![name])),
+      }
+    }
+    let (third_party, std): (Vec<_>, Vec<_>) = modules
+      .into_iter()
+      .partition(|(module, _)| *module == "pydantic");
+    for group in [std, third_party] {
+      if group.is_empty() {
+        continue;
+      }
+      for (module, names) in group {
+        writeln!(out, "from {} import {}", module, names.join(", ")).unwrap();
+      }
+      out.push('\n');
+    }
+    out.push('\n');
+  }
+
+  // `name` is what the top level is declared as.
+  fn example(&self, top: &TopLevel, name: &str) -> String {
+    match self.options.style {
+      Style::Dataclass => {
+        let function = PYTHON.field_name(&top.name);
+        let function = function.trim_end_matches('_');
+        format!(
+          "#     model = {0}_from_dict(json.loads(text))\n#     text = json.dumps({0}_to_dict(model))\n",
+          function
+        )
+      }
+      Style::Pydantic if self.declared_name(top).is_some() => format!(
+        "#     model = {}.model_validate_json(text)\n#     text = model.model_dump_json(by_alias=True, exclude_unset=True)\n",
+        name
+      ),
+      Style::Pydantic => format!(
+        "#     model = TypeAdapter({}).validate_json(text)\n#     text = TypeAdapter({0}).dump_json(model, by_alias=True)\n",
+        name
+      ),
+      Style::TypedDict => format!("#     model: {} = json.loads(text)\n", name),
+    }
+  }
+
+  // Top levels that are a class or enum of the same name need no alias. They go by the name it
+  // was declared under, which has a suffix when it clashed with another or with an import.
+  fn declared_name(&self, top: &TopLevel) -> Option<String> {
+    let name = PYTHON.type_name(&top.name);
+    match &top.ty {
+      Type::Class { id } => (PYTHON.type_name(&self.graph.class(*id).name) == name)
+        .then(|| self.class_names[id].clone()),
+      Type::Enum { id } => {
+        (PYTHON.type_name(&self.graph.enum_(*id).name) == name).then(|| self.enum_names[id].clone())
+      }
+      _ => None,
+    }
+  }
+
+  fn py_type(&mut self, ty: &Type) -> String {
+    let pydantic = self.options.style == Style::Pydantic;
+    match ty {
+      Type::Any => {
+        self.import("typing", "Any");
+        "Any".into()
+      }
+      Type::Null => "None".into(),
+      Type::Bool => "bool".into(),
+      Type::Integer => "int".into(),
+      Type::Double => "float".into(),
+      // pydantic parses these, the other styles keep the string
+      Type::Format { format } if pydantic => match format {
+        Format::DateTime => {
+          self.import("datetime", "datetime");
+          "datetime".into()
+        }
+        Format::Date => {
+          self.import("datetime", "date");
+          "date".into()
+        }
+        Format::Time => {
+          self.import("datetime", "time");
+          "time".into()
+        }
+        Format::Uuid => {
+          self.import("uuid", "UUID");
+          "UUID".into()
+        }
+        _ => "str".into(),
+      },
+      Type::String | Type::Format { .. } => "str".into(),
+      Type::Enum { id } => self.enum_names[id].clone(),
+      Type::Array { items } => {
+        self.import("typing", "List");
+        format!("List[{}]", self.py_type(items))
+      }
+      Type::Map { values } => {
+        self.import("typing", "Dict");
+        format!("Dict[str, {}]", self.py_type(values))
+      }
+      Type::Class { id } => self.class_names[id].clone(),
+      Type::Union { members } => {
+        self.import("typing", "Union");
+        let members: Vec<String> = members.iter().map(|m| self.py_type(m)).collect();
+        format!("Union[{}]", members.join(", "))
+      }
+      Type::Nullable { inner } => {
+        self.import("typing", "Optional");
+        format!("Optional[{}]", self.py_type(inner))
+      }
+    }
+  }
+
+  fn write_enum(&mut self, id: EnumId) -> String {
+    let name = self.enum_names[&id].clone();
+    let values = &self.graph.enum_(id).values;
+    let mut out = String::new();
+    if self.options.style == Style::TypedDict {
+      self.import("typing", "Literal");
+      let values: Vec<String> = values.iter().map(|v| literal(v)).collect();
+      writeln!(out, "{} = Literal[{}]", name, values.join(", ")).unwrap();
+      return out;
+    }
+    self.import("enum", "Enum");
+    writeln!(out, "class {}(str, Enum):", name).unwrap();
+    let mut namer = Namer::default();
+    for value in values {
+      let member = namer.unique(PYTHON.constant_name(value));
+      writeln!(out, "    {} = {}", member, literal(value)).unwrap();
+    }
+    out
+  }
+
+  // Field names for the properties of a class, not clashing with `reserved`.
+  fn field_names(&self, properties: &[Property], reserved: &[&str]) -> Vec<String> {
+    let mut namer = Namer::new(reserved.iter().copied());
+    properties
+      .iter()
+      .map(|p| namer.unique(PYTHON.field_name(p.rename.as_ref().unwrap_or(&p.name))))
+      .collect()
+  }
+
+  fn write_dataclass(&mut self, id: ClassId) -> String {
+    self.import("dataclasses", "dataclass");
+    self.import("typing", "Any");
+    self.import("typing", "Dict");
+    let class = self.graph.class(id);
+    let name = self.class_names[&id].clone();
+    let fields = self.field_names(&class.properties, &["from_dict", "to_dict"]);
+    let mut fields: Vec<(&String, &Property)> = fields.iter().zip(&class.properties).collect();
+    // fields with a default come last
+    fields.sort_by_key(|(_, p)| p.optional);
+
+    let mut out = format!("@dataclass\nclass {}:\n", name);
+    for (field, property) in &fields {
+      if property.optional {
+        let ty = self.py_type(&Type::nullable(property.ty.clone()));
+        writeln!(out, "    {}: {} = None", field, ty).unwrap();
+      } else {
+        writeln!(out, "    {}: {}", field, self.py_type(&property.ty)).unwrap();
+      }
+    }
+    if !fields.is_empty() {
+      out.push('\n');
+    }
+
+    out.push_str("    @staticmethod\n");
+    writeln!(out, "    def from_dict(obj: Any) -> {}:", name).unwrap();
+    writeln!(out, "        return {}(", name).unwrap();
+    for (field, property) in &fields {
+      let key = literal(&property.name);
+      let value = if property.optional {
+        self.decode_expr(
+          &Type::nullable(property.ty.clone()),
+          &format!("obj.get({})", key),
+          0,
+        )
+      } else {
+        self.decode_expr(&property.ty, &format!("obj[{}]", key), 0)
+      };
+      writeln!(out, "            {}={},", field, value).unwrap();
+    }
+    out.push_str("        )\n\n");
+
+    out.push_str("    def to_dict(self) -> Dict[str, Any]:\n");
+    out.push_str("        result: Dict[str, Any] = {}\n");
+    for (field, property) in &fields {
+      let key = literal(&property.name);
+      let value = format!("self.{}", field);
+      if property.optional {
+        let (inner, _) = property.ty.non_null();
+        writeln!(out, "        if {} is not None:", value).unwrap();
+        writeln!(
+          out,
+          "            result[{}] = {}",
+          key,
+          self.encode_expr(inner, &value, 0)
+        )
+        .unwrap();
+      } else {
+        writeln!(
+          out,
+          "        result[{}] = {}",
+          key,
+          self.encode_expr(&property.ty, &value, 0)
+        )
+        .unwrap();
+      }
+    }
+    out.push_str("        return result\n");
+    out
+  }
+
+  // `<name>_from_dict` and `<name>_to_dict` for a top level.
+  fn write_functions(&mut self, top: &TopLevel) -> String {
+    let function = PYTHON.field_name(&top.name);
+    let function = function.trim_end_matches('_');
+    let ty = self.py_type(&top.ty);
+    format!(
+      "def {0}_from_dict(obj: Any) -> {1}:\n    return {2}\n\n\ndef {0}_to_dict(x: {1}) -> Any:\n    return {3}\n",
+      function,
+      ty,
+      self.decode_expr(&top.ty, "obj", 0),
+      self.encode_expr(&top.ty, "x", 0)
+    )
+  }
+
+  // An expression converting parsed JSON `value` to `ty`. `depth` numbers comprehension variables.
+  fn decode_expr(&self, ty: &Type, value: &str, depth: usize) -> String {
+    if !self.converts(ty, true) {
+      return value.into();
+    }
+    let v = format!("v{}", depth);
+    match ty {
+      Type::Double => format!("float({})", value),
+      Type::Enum { id } => format!("{}({})", self.enum_names[id], value),
+      Type::Class { id } => format!("{}.from_dict({})", self.class_names[id], value),
+      Type::Array { items } => format!(
+        "[{} for {} in {}]",
+        self.decode_expr(items, &v, depth + 1),
+        v,
+        value
+      ),
+      Type::Map { values } => format!(
+        "{{k{0}: {1} for k{0}, {2} in {3}.items()}}",
+        depth,
+        self.decode_expr(values, &v, depth + 1),
+        v,
+        value
+      ),
+      Type::Nullable { inner } => format!(
+        "None if {0} is None else {1}",
+        value,
+        self.decode_expr(inner, value, depth)
+      ),
+      Type::Union { members } => {
+        let mut members: Vec<&Type> = members.iter().collect();
+        members.sort_by_key(|m| rank(m));
+        let last = members.pop().unwrap();
+        let mut out = String::new();
+        for member in members {
+          let check = match member {
+            Type::Bool => "bool",
+            Type::Integer => "int",
+            Type::Double => "(int, float)",
+            Type::Array { .. } => "list",
+            Type::Class { .. } | Type::Map { .. } => "dict",
+            _ => "str",
+          };
+          write!(
+            out,
+            "{} if isinstance({}, {}) else ",
+            self.decode_expr(member, value, depth),
+            value,
+            check
+          )
+          .unwrap();
+        }
+        out.push_str(&self.decode_expr(last, value, depth));
+        out
+      }
+      _ => value.into(),
+    }
+  }
+
+  // An expression converting `value` of type `ty` back to JSON.
+  fn encode_expr(&self, ty: &Type, value: &str, depth: usize) -> String {
+    if !self.converts(ty, false) {
+      return value.into();
+    }
+    let v = format!("v{}", depth);
+    match ty {
+      Type::Enum { .. } => format!("{}.value", value),
+      Type::Class { .. } => format!("{}.to_dict()", value),
+      Type::Array { items } => format!(
+        "[{} for {} in {}]",
+        self.encode_expr(items, &v, depth + 1),
+        v,
+        value
+      ),
+      Type::Map { values } => format!(
+        "{{k{0}: {1} for k{0}, {2} in {3}.items()}}",
+        depth,
+        self.encode_expr(values, &v, depth + 1),
+        v,
+        value
+      ),
+      Type::Nullable { inner } => format!(
+        "None if {0} is None else {1}",
+        value,
+        self.encode_expr(inner, value, depth)
+      ),
+      Type::Union { members } => {
+        // members converted as they are go last, without a check
+        let (mut converted, rest): (Vec<&Type>, Vec<&Type>) =
+          members.iter().partition(|m| self.converts(m, false));
+        let last = if rest.is_empty() {
+          converted.pop().map(|m| self.encode_expr(m, value, depth))
+        } else {
+          Some(value.to_string())
+        };
+        let mut out = String::new();
+        for member in converted {
+          let check = match member {
+            Type::Enum { id } => self.enum_names[id].clone(),
+            Type::Class { id } => self.class_names[id].clone(),
+            Type::Array { .. } => "list".into(),
+            _ => "dict".into(),
+          };
+          write!(
+            out,
+            "{} if isinstance({}, {}) else ",
+            self.encode_expr(member, value, depth),
+            value,
+            check
+          )
+          .unwrap();
+        }
+        out.push_str(&last.unwrap_or_else(|| value.into()));
+        out
+      }
+      _ => value.into(),
+    }
+  }
+
+  // Whether values of `ty` need converting when parsed, or when written back to JSON.
+  fn converts(&self, ty: &Type, parsing: bool) -> bool {
+    match ty {
+      // JSON has no `1.0`, an integer can stand for a float
+      Type::Double => parsing,
+      Type::Enum { .. } | Type::Class { .. } => true,
+      Type::Array { items: inner } | Type::Map { values: inner } | Type::Nullable { inner } => {
+        self.converts(inner, parsing)
+      }
+      Type::Union { members } => members.iter().any(|m| self.converts(m, parsing)),
+      _ => false,
+    }
+  }
+
+  fn write_model(&mut self, id: ClassId) -> String {
+    self.import("pydantic", "BaseModel");
+    let class = self.graph.class(id);
+    let fields = self.field_names(&class.properties, MODEL_RESERVED);
+    let mut lines = vec![];
+    let mut aliased = false;
+    for (field, property) in fields.iter().zip(&class.properties) {
+      let ty = if property.optional {
+        Type::nullable(property.ty.clone())
+      } else {
+        property.ty.clone()
+      };
+      let ty = self.py_type(&ty);
+      let alias = (*field != property.name).then(|| literal(&property.name));
+      aliased |= alias.is_some();
+      let default = match (property.optional, alias) {
+        (false, None) => String::new(),
+        (true, None) => " = None".into(),
+        (false, Some(alias)) => format!(" = Field(alias={})", alias),
+        (true, Some(alias)) => format!(" = Field(default=None, alias={})", alias),
+      };
+      lines.push(format!("    {}: {}{}", field, ty, default));
+    }
+
+    let mut out = format!("class {}(BaseModel):\n", self.class_names[&id]);
+    if aliased {
+      self.import("pydantic", "ConfigDict");
+      self.import("pydantic", "Field");
+      out.push_str("    model_config = ConfigDict(populate_by_name=True)\n\n");
+    }
+    if lines.is_empty() {
+      out.push_str("    pass\n");
+    }
+    for line in lines {
+      writeln!(out, "{}", line).unwrap();
+    }
+    out
+  }
+
+  fn write_typed_dict(&mut self, id: ClassId) -> String {
+    self.import("typing", "TypedDict");
+    let name = self.class_names[&id].clone();
+    let class = self.graph.class(id);
+    let (optional, required): (Vec<&Property>, Vec<&Property>) =
+      class.properties.iter().partition(|p| p.optional);
+    let mut out = String::new();
+    match (required.is_empty(), optional.is_empty()) {
+      (_, true) => self.write_typed_dict_part(&mut out, &name, &[], &required, true),
+      (true, false) => self.write_typed_dict_part(&mut out, &name, &[], &optional, false),
+      // optional keys in a subclass, `NotRequired` needs Python 3.11
+      (false, false) => {
+        let base = format!("_{}Required", name);
+        self.write_typed_dict_part(&mut out, &base, &[], &required, true);
+        out.push_str("\n\n");
+        if optional.iter().all(|p| is_identifier(&p.name)) {
+          self.write_typed_dict_part(&mut out, &name, &[&base], &optional, false);
+        } else {
+          let optional_base = format!("_{}Optional", name);
+          self.write_typed_dict_part(&mut out, &optional_base, &[], &optional, false);
+          out.push_str("\n\n");
+          self.write_typed_dict_part(&mut out, &name, &[&base, &optional_base], &[], true);
+        }
+      }
+    }
+    out
+  }
+
+  // Keys that can't be attribute names need the functional syntax, whose types are evaluated
+  // right away and so are quoted. It takes no bases, callers only pass them with no properties
+  // or attribute names.
+  fn write_typed_dict_part(
+    &mut self,
+    out: &mut String,
+    name: &str,
+    bases: &[&str],
+    properties: &[&Property],
+    total: bool,
+  ) {
+    if !properties.iter().all(|p| is_identifier(&p.name)) {
+      writeln!(out, "{} = TypedDict(\n    {},\n    {{", name, literal(name)).unwrap();
+      for property in properties {
+        let ty = self.py_type(&property.ty);
+        writeln!(
+          out,
+          "        {}: {},",
+          literal(&property.name),
+          literal(&ty)
+        )
+        .unwrap();
+      }
+      out.push_str("    },\n");
+      if !total {
+        out.push_str("    total=False,\n");
+      }
+      out.push_str(")\n");
+      return;
+    }
+
+    let mut header = if bases.is_empty() {
+      vec!["TypedDict"]
+    } else {
+      bases.to_vec()
+    };
+    if !total {
+      header.push("total=False");
+    }
+    writeln!(out, "class {}({}):", name, header.join(", ")).unwrap();
+    if properties.is_empty() {
+      out.push_str("    pass\n");
+    }
+    for property in properties {
+      writeln!(out, "    {}: {}", property.name, self.py_type(&property.ty)).unwrap();
+    }
+  }
+}
@@ -0,0 +1,310 @@
+use super::{
+  naming::{Namer, RUST},
+  SourceFile,
+};
+use crate::app::infer::{ClassId, EnumId, TopLevel, Type, TypeGraph};
+use serde::Deserialize;
+use std::{collections::HashMap, fmt::Write};
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Options {
+  /// Traits derived on every generated type.
+  pub derives: Vec<String>,
+  pub visibility: Visibility,
+  /// Adds `#[serde(deny_unknown_fields)]` to structs.
+  pub deny_unknown_fields: bool,
+}
+
+impl Default for Options {
+  fn default() -> Self {
+    Self {
+      derives: ["Debug", "Clone", "Serialize", "Deserialize"]
+        .map(String::from)
+        .to_vec(),
+      visibility: Visibility::Public,
+      deny_unknown_fields: false,
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum Visibility {
+  Private,
+  Crate,
+  Public,
+}
+
+impl Visibility {
+  fn prefix(self) -> &'static str {
+    match self {
+      Self::Private => "",
+      Self::Crate => "pub(crate) ",
+      Self::Public => "pub ",
+    }
+  }
+}
+
+// Type names the generated code already uses.
+const PRELUDE: &[&str] = &[
+  "Option",
+  "Result",
+  "Vec",
+  "String",
+  "Box",
+  "HashMap",
+  "Value",
+  "Serialize",
+  "Deserialize",
+];
+
+struct Generator<'a> {
+  graph: &'a TypeGraph,
+  options: &'a Options,
+  class_names: HashMap<ClassId, String>,
+  enum_names: HashMap<EnumId, String>,
+  union_names: Vec<(Vec<Type>, String)>,
+  uses_map: bool,
+  uses_value: bool,
+}
+
+pub fn generate(graph: &TypeGraph, options: &Options) -> Vec<SourceFile> {
+  let order = super::class_order(graph);
+  let mut namer = Namer::new(PRELUDE.iter().copied());
+  let class_names: HashMap<ClassId, String> = order
+    .iter()
+    .map(|&id| (id, namer.unique(RUST.type_name(&graph.class(id).name))))
+    .collect();
+  let enums = super::enum_order(graph, &order);
+  let enum_names = enums
+    .iter()
+    .map(|&id| (id, namer.unique(RUST.type_name(&graph.enum_(id).name))))
+    .collect();
+  let union_names = super::unions(graph, &order)
+    .into_iter()
+    .map(|(hint, members)| (members, namer.unique(RUST.type_name(&hint))))
+    .collect();
+  let mut gen = Generator {
+    graph,
+    options,
+    class_names,
+    enum_names,
+    union_names,
+    uses_map: false,
+    uses_value: false,
+  };
+
+  let mut body = String::new();
+  let mut top_names = vec![];
+  for top in &graph.top_levels {
+    let name = match gen.declared_name(top) {
+      Some(name) => name,
+      None => {
+        let ty = gen.rust_type(&top.ty);
+        let name = namer.unique(RUST.type_name(&top.name));
+        writeln!(
+          body,
+          "{}type {} = {};\n",
+          options.visibility.prefix(),
+          name,
+          ty
+        )
+        .unwrap();
+        name
+      }
+    };
+    top_names.push(name);
+  }
+  for &id in &order {
+    gen.write_struct(&mut body, id);
+  }
+  for &id in &enums {
+    gen.write_enum(&mut body, id);
+  }
+  for (members, name) in gen.union_names.clone() {
+    gen.write_union(&mut body, &name, &members);
+  }
+
+  let mut content = String::new();
+  if let Some(name) = top_names.first() {
+    content.push_str("// Example code that deserializes and serializes the model:\n//\n");
+    writeln!(
+      content,
+      "// let model: {} = serde_json::from_str(&json).unwrap();",
+      name
+    )
+    .unwrap();
+    content.push_str("// let json = serde_json::to_string(&model).unwrap();\n\n");
+  }
+  content.push_str("use serde::{Deserialize, Serialize};\n");
+  if gen.uses_value {
+    content.push_str("use serde_json::Value;\n");
+  }
+  if gen.uses_map {
+    content.push_str("use std::collections::HashMap;\n");
+  }
+  content.push('\n');
+  content.push_str(body.trim_end());
+  content.push('\n');
+
+  let file = graph
+    .top_levels
+    .first()
+    .map_or("models".into(), |t| RUST.field_name(&t.name));
+  vec![SourceFile {
+    name: format!("{}.rs", file.trim_start_matches("r#")),
+    content,
+  }]
+}
+
+impl Generator<'_> {
+  // Top levels that are a class, enum or union of the same name need no alias. They go by the
+  // name it was declared under, which has a suffix when it clashed with another.
+  fn declared_name(&self, top: &TopLevel) -> Option<String> {
+    let name = RUST.type_name(&top.name);
+    match &top.ty {
+      Type::Class { id } => {
+        (RUST.type_name(&self.graph.class(*id).name) == name).then(|| self.class_names[id].clone())
+      }
+      Type::Enum { id } => {
+        (RUST.type_name(&self.graph.enum_(*id).name) == name).then(|| self.enum_names[id].clone())
+      }
+      Type::Union { members } => Some(self.union_name(members)).filter(|v| *v == name),
+      _ => None,
+    }
+  }
+
+  fn rust_type(&mut self, ty: &Type) -> String {
+    match ty {
+      Type::Any => {
+        self.uses_value = true;
+        "Value".into()
+      }
+      Type::Null => {
+        self.uses_value = true;
+        "Option<Value>".into()
+      }
+      Type::Bool => "bool".into(),
+      Type::Integer => "i64".into(),
+      Type::Double => "f64".into(),
+      Type::String | Type::Format { .. } => "String".into(),
+      Type::Enum { id } => self.enum_names[id].clone(),
+      Type::Array { items } => format!("Vec<{}>", self.rust_type(items)),
+      Type::Map { values } => {
+        self.uses_map = true;
+        format!("HashMap<String, {}>", self.rust_type(values))
+      }
+      Type::Class { id } => self.class_names[id].clone(),
+      Type::Union { members } => self.union_name(members),
+      Type::Nullable { inner } => format!("Option<{}>", self.rust_type(inner)),
+    }
+  }
+
+  fn union_name(&self, members: &[Type]) -> String {
+    self
+      .union_names
+      .iter()
+      .find(|(m, _)| m == members)
+      .map(|(_, name)| name.clone())
+      .unwrap_or_else(|| "Value".into())
+  }
+
+  fn derives(&self, is_enum: bool) -> String {
+    let derives: Vec<&str> = self
+      .options
+      .derives
+      .iter()
+      .map(String::as_str)
+      // an enum has no obvious default variant
+      .filter(|d| !is_enum || *d != "Default")
+      .collect();
+    if derives.is_empty() {
+      String::new()
+    } else {
+      format!("#[derive({})]\n", derives.join(", "))
+    }
+  }
+
+  fn write_struct(&mut self, out: &mut String, id: ClassId) {
+    let graph = self.graph;
+    let class = graph.class(id);
+    let vis = self.options.visibility.prefix();
+    out.push_str(&self.derives(false));
+    if self.options.deny_unknown_fields {
+      out.push_str("#[serde(deny_unknown_fields)]\n");
+    }
+    writeln!(out, "{}struct {} {{", vis, self.class_names[&id]).unwrap();
+
+    let mut namer = Namer::default();
+    for property in &class.properties {
+      let (inner, nullable) = property.ty.non_null();
+      let mut ty = self.rust_type(inner);
+      if super::reaches_inline(graph, inner, id) {
+        ty = format!("Box<{}>", ty);
+      }
+      if (nullable || property.optional) && *inner != Type::Null {
+        ty = format!("Option<{}>", ty);
+      }
+
+      let key = property.rename.as_ref().unwrap_or(&property.name);
+      let name = namer.unique(RUST.field_name(key));
+      let mut attrs = vec![];
+      if name.trim_start_matches("r#") != property.name {
+        attrs.push(format!("rename = {:?}", property.name));
+      }
+      if property.optional {
+        attrs.push("skip_serializing_if = \"Option::is_none\"".into());
+      }
+      if !attrs.is_empty() {
+        writeln!(out, "    #[serde({})]", attrs.join(", ")).unwrap();
+      }
+      writeln!(out, "    {}{}: {},", vis, name, ty).unwrap();
+    }
+    out.push_str("}\n\n");
+  }
+
+  fn write_enum(&mut self, out: &mut String, id: EnumId) {
+    out.push_str(&self.derives(true));
+    let name = &self.enum_names[&id];
+    writeln!(out, "{}enum {} {{", self.options.visibility.prefix(), name).unwrap();
+    let mut namer = Namer::default();
+    for value in &self.graph.enum_(id).values {
+      let variant = namer.unique(RUST.type_name(value));
+      if variant != *value {
+        writeln!(out, "    #[serde(rename = {:?})]", value).unwrap();
+      }
+      writeln!(out, "    {},", variant).unwrap();
+    }
+    out.push_str("}\n\n");
+  }
+
+  fn write_union(&mut self, out: &mut String, name: &str, members: &[Type]) {
+    out.push_str(&self.derives(true));
+    out.push_str("#[serde(untagged)]\n");
+    writeln!(out, "{}enum {} {{", self.options.visibility.prefix(), name).unwrap();
+    let mut namer = Namer::default();
+    for member in members {
+      let variant = namer.unique(self.variant_name(member));
+      let ty = self.rust_type(member);
+      writeln!(out, "    {}({}),", variant, ty).unwrap();
+    }
+    out.push_str("}\n\n");
+  }
+
+  fn variant_name(&self, ty: &Type) -> String {
+    match ty {
+      Type::Any | Type::Null => "Value".into(),
+      Type::Bool => "Bool".into(),
+      Type::Integer => "Integer".into(),
+      Type::Double => "Double".into(),
+      Type::String | Type::Format { .. } => "String".into(),
+      Type::Enum { id } => self.enum_names[id].clone(),
+      Type::Array { items } => format!("{}Array", self.variant_name(items)),
+      Type::Map { values } => format!("{}Map", self.variant_name(values)),
+      Type::Class { id } => self.class_names[id].clone(),
+      Type::Union { members } => self.union_name(members),
+      Type::Nullable { inner } => self.variant_name(inner),
+    }
+  }
+}
@@ -0,0 +1,254 @@
+use super::{generate, Target};
+use crate::app::infer::{self, Options, Source, TypeGraph};
+use serde_json::{json, Value};
+
+// A class, a nested class, an optional key, a nullable one, a date and a union.
+fn user_graph() -> TypeGraph {
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "user",
+    "samples": [
+      r#"{
+        "id": 1, "name": "Ann", "email": "ann@example.com", "created_at": "2024-01-02T03:04:05Z",
+        "tags": ["a"], "manager": null, "address": { "city": "Paris", "zip": "75001" }, "ref": 7
+      }"#,
+      r#"{
+        "id": 2, "name": "Bob", "created_at": "2024-02-03T04:05:06Z",
+        "tags": [], "manager": "Ann", "address": { "city": "Rome", "zip": "00100" }, "ref": "x-1"
+      }"#,
+    ],
+  }]))
+  .unwrap();
+  infer::infer(&sources, &Options::default()).unwrap()
+}
+
+// Generated files must match the ones under `snapshots`, which change along with the output.
+fn assert_snapshot(lang: &str, name: &str, expected: &str) {
+  assert_snapshot_of(&user_graph(), json!({ "lang": lang }), name, expected);
+}
+
+fn assert_snapshot_of(graph: &TypeGraph, target: Value, name: &str, expected: &str) {
+  let target: Target = serde_json::from_value(target).unwrap();
+  let files = generate(graph, &target);
+  assert_eq!(files.len(), 1);
+  assert_eq!(files[0].name, name);
+  assert_eq!(files[0].content, expected);
+}
+
+#[test]
+fn rust() {
+  assert_snapshot("rust", "user.rs", include_str!("snapshots/user.rs.snap"));
+}
+
+#[test]
+fn rust_options() {
+  let target: Target = serde_json::from_value(json!({
+    "lang": "rust",
+    "derives": ["Debug"],
+    "visibility": "crate",
+    "denyUnknownFields": true,
+  }))
+  .unwrap();
+  let files = generate(&user_graph(), &target);
+  assert!(files[0].content.contains(
+    "#[derive(Debug)]\n#[serde(deny_unknown_fields)]\npub(crate) struct Address {\n    \
+pub(crate) city: String,"
+  ));
+}
+
+#[test]
+fn rust_top_level_clashing_with_the_prelude() {
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "result",
+    "samples": [r#"{ "ok": true }"#],
+  }]))
+  .unwrap();
+  let graph = infer::infer(&sources, &Options::default()).unwrap();
+  let target: Target = serde_json::from_value(json!({ "lang": "rust" })).unwrap();
+  let content = &generate(&graph, &target)[0].content;
+  assert!(content.contains("// let model: Result2 = serde_json::from_str(&json).unwrap();"));
+  assert!(content.contains("pub struct Result2 {"));
+  assert!(!content.contains("type "));
+}
+
+#[test]
+fn typescript() {
+  assert_snapshot(
+    "typescript",
+    "user.ts",
+    include_str!("snapshots/user.ts.snap"),
+  );
+}
+
+#[test]
+fn typescript_top_level_clashing_with_a_global() {
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "date",
+    "samples": [r#"{ "day": 1 }"#],
+  }]))
+  .unwrap();
+  let graph = infer::infer(&sources, &Options::default()).unwrap();
+  let target: Target =
+    serde_json::from_value(json!({ "lang": "typescript", "runtime": "guards" })).unwrap();
+  let content = &generate(&graph, &target)[0].content;
+  assert!(content.contains("// if (isDate2(model)) { ... }"));
+  assert!(content.contains("export interface Date2 {"));
+  assert!(!content.contains("export type "));
+}
+
+#[test]
+fn json_schema() {
+  assert_snapshot(
+    "jsonSchema",
+    "user.schema.json",
+    include_str!("snapshots/user.schema.json.snap"),
+  );
+}
+
+#[test]
+fn json_schema_round_trips() {
+  let target: Target = serde_json::from_value(json!({ "lang": "jsonSchema" })).unwrap();
+  let schema = generate(&user_graph(), &target).remove(0).content;
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "user",
+    "kind": "jsonSchema",
+    "samples": [schema],
+  }]))
+  .unwrap();
+  let lowered = infer::infer(&sources, &Options::default()).unwrap();
+  assert_eq!(generate(&lowered, &target)[0].content, schema);
+}
+
+#[test]
+fn go() {
+  assert_snapshot("go", "user.go", include_str!("snapshots/user.go.snap"));
+}
+
+#[test]
+fn go_tags_keep_the_keys() {
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "account",
+    "samples": [
+      r#"{ "user_id": 1, "api-url": "a", "2fa": true, "html": "<p>", "owner": { "ip": "::1" } }"#,
+      r#"{ "user_id": 2, "api-url": "b", "2fa": false, "html": "<b>" }"#,
+    ],
+  }]))
+  .unwrap();
+  let graph = infer::infer(&sources, &Options::default()).unwrap();
+  let target: Target = serde_json::from_value(json!({ "lang": "go" })).unwrap();
+  assert!(generate(&graph, &target)[0].content.contains(
+    "\
+type Account struct {
+\tUserID   int64  `json:\"user_id\"`
+\tAPIURL   string `json:\"api-url\"`
+\tField2fa bool   `json:\"2fa\"`
+\tHTML     string `json:\"html\"`
+\tOwner    *Owner `json:\"owner,omitempty\"`
+}"
+  ));
+}
+
+#[test]
+fn go_optional_fields_can_be_omit_empty() {
+  let target: Target =
+    serde_json::from_value(json!({ "lang": "go", "optionalFields": "omitEmpty" })).unwrap();
+  let content = &generate(&user_graph(), &target)[0].content;
+  assert!(content.contains("\tEmail     string    `json:\"email,omitempty\"`\n"));
+  assert!(content.contains("\tManager   string    `json:\"manager\"`\n"));
+  assert!(content.contains("\tAddress   Address   `json:\"address\"`\n"));
+}
+
+#[test]
+fn go_top_levels_named_alike() {
+  let sources: Vec<Source> = serde_json::from_value(json!([
+    { "name": "user_id", "samples": [r#"{ "a": 1 }"#] },
+    { "name": "userID", "samples": [r#"{ "b": true }"#] },
+  ]))
+  .unwrap();
+  let graph = infer::infer(&sources, &Options::default()).unwrap();
+  let target: Target = serde_json::from_value(json!({ "lang": "go" })).unwrap();
+  let content = &generate(&graph, &target)[0].content;
+  assert!(content.contains("//\tvar model UserID\n"));
+  assert!(content.contains("type UserID struct {"));
+  assert!(content.contains("type UserID2 struct {"));
+  assert!(!content.contains(" = UserID"));
+}
+
+#[test]
+fn python() {
+  assert_snapshot("python", "user.py", include_str!("snapshots/user.py.snap"));
+}
+
+#[test]
+fn python_pydantic() {
+  assert_snapshot_of(
+    &user_graph(),
+    json!({ "lang": "python", "style": "pydantic" }),
+    "user.py",
+    include_str!("snapshots/user.pydantic.py.snap"),
+  );
+}
+
+#[test]
+fn python_typed_dict() {
+  assert_snapshot_of(
+    &user_graph(),
+    json!({ "lang": "python", "style": "typedDict" }),
+    "user.py",
+    include_str!("snapshots/user.typed_dict.py.snap"),
+  );
+}
+
+// Keys that aren't identifiers, some of them optional.
+fn profile_graph() -> TypeGraph {
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "profile",
+    "samples": [
+      r#"{ "first-name": "Ann", "class": "a", "2fa": true, "id": 1, "tags": ["x"] }"#,
+      r#"{ "first-name": "Bob", "class": "b", "tags": [] }"#,
+    ],
+  }]))
+  .unwrap();
+  infer::infer(&sources, &Options::default()).unwrap()
+}
+
+#[test]
+fn python_typed_dict_with_keys_that_are_not_identifiers() {
+  assert_snapshot_of(
+    &profile_graph(),
+    json!({ "lang": "python", "style": "typedDict" }),
+    "profile.py",
+    include_str!("snapshots/profile.typed_dict.py.snap"),
+  );
+}
+
+#[test]
+fn python_fields_keep_the_keys() {
+  let content = |style: &str| {
+    let target: Target =
+      serde_json::from_value(json!({ "lang": "python", "style": style })).unwrap();
+    generate(&profile_graph(), &target).remove(0).content
+  };
+  let dataclass = content("dataclass");
+  assert!(dataclass.contains("            class_=obj[\"class\"],\n"));
+  assert!(dataclass.contains("            field_2fa=obj.get(\"2fa\"),\n"));
+  assert!(dataclass.contains("        if self.field_2fa is not None:\n            result[\"2fa\"]"));
+  let pydantic = content("pydantic");
+  assert!(pydantic.contains("    first_name: str = Field(alias=\"first-name\")\n"));
+  assert!(pydantic.contains("    field_2fa: Optional[bool] = Field(default=None, alias=\"2fa\")\n"));
+}
+
+#[test]
+fn python_top_level_clashing_with_an_import() {
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "field",
+    "samples": [r#"{ "ok": true }"#],
+  }]))
+  .unwrap();
+  let graph = infer::infer(&sources, &Options::default()).unwrap();
+  let target: Target =
+    serde_json::from_value(json!({ "lang": "python", "style": "pydantic" })).unwrap();
+  let content = &generate(&graph, &target)[0].content;
+  assert!(content.contains("#     model = Field2.model_validate_json(text)\n"));
+  assert!(content.contains("class Field2(BaseModel):"));
+  assert!(!content.contains("Field3"));
+}
@@ -0,0 +1,464 @@
+use super::{
+  naming::{Case, Namer, TYPESCRIPT},
+  SourceFile,
+};
+use crate::app::infer::{ClassId, EnumId, Format, TopLevel, Type, TypeGraph};
+use serde::Deserialize;
+use std::{collections::HashMap, fmt::Write};
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Options {
+  pub declaration: Declaration,
+  /// Marks every property `readonly`.
+  pub readonly: bool,
+  /// How values of unknown type are typed.
+  pub untyped: Untyped,
+  /// Code that checks parsed data against the types at runtime.
+  pub runtime: Runtime,
+}
+
+/// `export interface User { ... }` or `export type User = { ... };`
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Declaration {
+  #[default]
+  Interface,
+  Type,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Untyped {
+  #[default]
+  Unknown,
+  Any,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Runtime {
+  #[default]
+  None,
+  /// A `zod` schema per type, e.g. `UserSchema.parse(json)`.
+  Zod,
+  /// A type guard function per type, e.g. `isUser(json)`.
+  Guards,
+}
+
+// Global types a declaration would shadow.
+const GLOBALS: &[&str] = &[
+  "Array", "Boolean", "Date", "Error", "Function", "Map", "Number", "Object", "Promise", "Record",
+  "RegExp", "Set", "String", "Symbol",
+];
+
+struct Generator<'a> {
+  graph: &'a TypeGraph,
+  options: &'a Options,
+  class_names: HashMap<ClassId, String>,
+  enum_names: HashMap<EnumId, String>,
+}
+
+pub fn generate(graph: &TypeGraph, options: &Options) -> Vec<SourceFile> {
+  let order = super::class_order(graph);
+  let mut namer = Namer::new(GLOBALS.iter().copied());
+  let class_names = order
+    .iter()
+    .map(|&id| {
+      (
+        id,
+        namer.unique(TYPESCRIPT.type_name(&graph.class(id).name)),
+      )
+    })
+    .collect();
+  let enums = super::enum_order(graph, &order);
+  let enum_names = enums
+    .iter()
+    .map(|&id| {
+      (
+        id,
+        namer.unique(TYPESCRIPT.type_name(&graph.enum_(id).name)),
+      )
+    })
+    .collect();
+  let gen = Generator {
+    graph,
+    options,
+    class_names,
+    enum_names,
+  };
+  // top levels that aren't a class or enum of the same name get an alias
+  let mut top_names = vec![];
+  let mut aliases: Vec<(String, &Type)> = vec![];
+  for top in &graph.top_levels {
+    let name = gen.declared_name(top).unwrap_or_else(|| {
+      let name = namer.unique(TYPESCRIPT.type_name(&top.name));
+      aliases.push((name.clone(), &top.ty));
+      name
+    });
+    top_names.push(name);
+  }
+
+  let mut out = String::new();
+  if let Some(name) = top_names.first() {
+    let example = match options.runtime {
+      Runtime::None => format!("const model = JSON.parse(json) as {};", name),
+      Runtime::Zod => format!("const model = {}Schema.parse(JSON.parse(json));", name),
+      Runtime::Guards => format!(
+        "const model: unknown = JSON.parse(json);\n// if (is{}(model)) {{ ... }}",
+        name
+      ),
+    };
+    writeln!(
+      out,
+      "// Example code that parses the model:\n//\n// {}\n",
+      example
+    )
+    .unwrap();
+  }
+  if options.runtime == Runtime::Zod {
+    out.push_str("import { z } from \"zod\";\n\n");
+  }
+
+  for (name, ty) in &aliases {
+    writeln!(out, "export type {} = {};\n", name, gen.ts_type(ty)).unwrap();
+  }
+  for &id in &enums {
+    writeln!(
+      out,
+      "export type {} = {};\n",
+      gen.enum_names[&id],
+      gen.literals(id, " | ")
+    )
+    .unwrap();
+  }
+  for &id in &order {
+    gen.write_declaration(&mut out, id);
+  }
+
+  match options.runtime {
+    Runtime::None => {}
+    Runtime::Zod => {
+      // leaves first, so most schemas are defined before they are used
+      for &id in &enums {
+        let name = &gen.enum_names[&id];
+        writeln!(
+          out,
+          "export const {}Schema = z.enum([{}]);\n",
+          name,
+          gen.literals(id, ", ")
+        )
+        .unwrap();
+      }
+      let mut defined = vec![];
+      for &id in order.iter().rev() {
+        gen.write_zod_class(&mut out, id, &defined);
+        defined.push(id);
+      }
+      for (name, ty) in &aliases {
+        let schema = gen.zod(ty, &defined);
+        writeln!(
+          out,
+          "export const {}Schema: z.ZodType<{}> = {};\n",
+          name, name, schema
+        )
+        .unwrap();
+      }
+    }
+    Runtime::Guards => {
+      out.push_str("function isRecord(value: unknown): value is Record<string, unknown> {\n");
+      out.push_str(
+        "  return typeof value === \"object\" && value !== null && !Array.isArray(value);\n}\n\n",
+      );
+      for &id in &enums {
+        let name = &gen.enum_names[&id];
+        writeln!(
+          out,
+          "export function is{}(value: unknown): value is {} {{\n  return typeof value === \"string\" && [{}].includes(value);\n}}\n",
+          name,
+          name,
+          gen.literals(id, ", ")
+        )
+        .unwrap();
+      }
+      for (name, ty) in &aliases {
+        writeln!(
+          out,
+          "export function is{}(value: unknown): value is {} {{\n  return {};\n}}\n",
+          name,
+          name,
+          gen.guard(ty, "value", 0)
+        )
+        .unwrap();
+      }
+      for &id in &order {
+        gen.write_guard(&mut out, id);
+      }
+    }
+  }
+
+  let file = graph
+    .top_levels
+    .first()
+    .map_or("models".into(), |t| Case::Kebab.apply(&t.name));
+  vec![SourceFile {
+    name: format!("{}.ts", if file.is_empty() { "models" } else { &file }),
+    content: format!("{}\n", out.trim_end()),
+  }]
+}
+
+impl Generator<'_> {
+  // Top levels that are a class or enum of the same name need no alias. They go by the name it
+  // was declared under, which has a suffix when it clashed with another.
+  fn declared_name(&self, top: &TopLevel) -> Option<String> {
+    let (declared, unique) = match &top.ty {
+      Type::Class { id } => (&self.graph.class(*id).name, &self.class_names[id]),
+      Type::Enum { id } => (&self.graph.enum_(*id).name, &self.enum_names[id]),
+      _ => return None,
+    };
+    (TYPESCRIPT.type_name(declared) == TYPESCRIPT.type_name(&top.name)).then(|| unique.clone())
+  }
+
+  fn ts_type(&self, ty: &Type) -> String {
+    match ty {
+      Type::Any => match self.options.untyped {
+        Untyped::Unknown => "unknown".into(),
+        Untyped::Any => "any".into(),
+      },
+      Type::Null => "null".into(),
+      Type::Bool => "boolean".into(),
+      Type::Integer | Type::Double => "number".into(),
+      Type::String | Type::Format { .. } => "string".into(),
+      Type::Enum { id } => self.enum_names[id].clone(),
+      Type::Array { items } => {
+        let items = self.ts_type(items);
+        if has_top_level_union(&items) {
+          format!("({})[]", items)
+        } else {
+          format!("{}[]", items)
+        }
+      }
+      Type::Map { values } => format!("Record<string, {}>", self.ts_type(values)),
+      Type::Class { id } => self.class_names[id].clone(),
+      Type::Union { members } => members
+        .iter()
+        .map(|m| self.ts_type(m))
+        .collect::<Vec<_>>()
+        .join(" | "),
+      Type::Nullable { inner } => format!("{} | null", self.ts_type(inner)),
+    }
+  }
+
+  // The values of an enum as string literals: `"a" | "b"`.
+  fn literals(&self, id: EnumId, separator: &str) -> String {
+    self
+      .graph
+      .enum_(id)
+      .values
+      .iter()
+      .map(|v| serde_json::Value::from(v.as_str()).to_string())
+      .collect::<Vec<_>>()
+      .join(separator)
+  }
+
+  fn write_declaration(&self, out: &mut String, id: ClassId) {
+    let class = self.graph.class(id);
+    let name = &self.class_names[&id];
+    match self.options.declaration {
+      Declaration::Interface => writeln!(out, "export interface {} {{", name),
+      Declaration::Type => writeln!(out, "export type {} = {{", name),
+    }
+    .unwrap();
+    for property in &class.properties {
+      writeln!(
+        out,
+        "  {}{}{}: {};",
+        if self.options.readonly {
+          "readonly "
+        } else {
+          ""
+        },
+        property_key(&property.name),
+        if property.optional { "?" } else { "" },
+        self.ts_type(&property.ty)
+      )
+      .unwrap();
+    }
+    match self.options.declaration {
+      Declaration::Interface => out.push_str("}\n\n"),
+      Declaration::Type => out.push_str("};\n\n"),
+    }
+  }
+
+  // `defined` are the classes whose schema is already declared, others are referenced lazily.
+  fn zod(&self, ty: &Type, defined: &[ClassId]) -> String {
+    match ty {
+      Type::Any => match self.options.untyped {
+        Untyped::Unknown => "z.unknown()".into(),
+        Untyped::Any => "z.any()".into(),
+      },
+      Type::Null => "z.null()".into(),
+      Type::Bool => "z.boolean()".into(),
+      Type::Integer => "z.number().int()".into(),
+      Type::Double => "z.number()".into(),
+      Type::String => "z.string()".into(),
+      Type::Format { format } => match format {
+        Format::DateTime => "z.string().datetime({ offset: true })".into(),
+        Format::Date => "z.string().date()".into(),
+        Format::Time => "z.string().time()".into(),
+        Format::Uuid => "z.string().uuid()".into(),
+        Format::Uri => "z.string().url()".into(),
+        Format::Email => "z.string().email()".into(),
+        Format::IntegerString => "z.string().regex(/^-?(0|[1-9]\\d*)$/)".into(),
+        Format::BoolString => "z.enum([\"true\", \"false\"])".into(),
+      },
+      Type::Enum { id } => format!("{}Schema", self.enum_names[id]),
+      Type::Array { items } => format!("z.array({})", self.zod(items, defined)),
+      Type::Map { values } => format!("z.record({})", self.zod(values, defined)),
+      Type::Class { id } if defined.contains(id) => format!("{}Schema", self.class_names[id]),
+      Type::Class { id } => format!("z.lazy(() => {}Schema)", self.class_names[id]),
+      Type::Union { members } => format!(
+        "z.union([{}])",
+        members
+          .iter()
+          .map(|m| self.zod(m, defined))
+          .collect::<Vec<_>>()
+          .join(", ")
+      ),
+      Type::Nullable { inner } => format!("{}.nullable()", self.zod(inner, defined)),
+    }
+  }
+
+  fn write_zod_class(&self, out: &mut String, id: ClassId, defined: &[ClassId]) {
+    let name = &self.class_names[&id];
+    writeln!(
+      out,
+      "export const {}Schema: z.ZodType<{}> = z.object({{",
+      name, name
+    )
+    .unwrap();
+    for property in &self.graph.class(id).properties {
+      writeln!(
+        out,
+        "  {}: {}{},",
+        property_key(&property.name),
+        self.zod(&property.ty, defined),
+        if property.optional { ".optional()" } else { "" }
+      )
+      .unwrap();
+    }
+    out.push_str("});\n\n");
+  }
+
+  // A boolean expression checking that `expr` holds a `ty`. `depth` keeps the names of nested
+  // callback parameters apart.
+  fn guard(&self, ty: &Type, expr: &str, depth: usize) -> String {
+    match ty {
+      Type::Any => "true".into(),
+      Type::Null => format!("{} === null", expr),
+      Type::Bool => format!("typeof {} === \"boolean\"", expr),
+      Type::Integer => format!("Number.isInteger({})", expr),
+      Type::Double => format!("typeof {} === \"number\"", expr),
+      Type::String | Type::Format { .. } => format!("typeof {} === \"string\"", expr),
+      Type::Enum { id } => format!("is{}({})", self.enum_names[id], expr),
+      Type::Array { items } => {
+        let x = format!("x{}", depth);
+        format!(
+          "Array.isArray({}) && {}.every(({}: unknown) => {})",
+          expr,
+          expr,
+          x,
+          self.guard(items, &x, depth + 1)
+        )
+      }
+      Type::Map { values } => {
+        let x = format!("x{}", depth);
+        format!(
+          "isRecord({}) && Object.values({}).every(({}) => {})",
+          expr,
+          expr,
+          x,
+          self.guard(values, &x, depth + 1)
+        )
+      }
+      Type::Class { id } => format!("is{}({})", self.class_names[id], expr),
+      Type::Union { members } => format!(
+        "({})",
+        members
+          .iter()
+          .map(|m| self.guard(m, expr, depth))
+          .collect::<Vec<_>>()
+          .join(" || ")
+      ),
+      Type::Nullable { inner } => {
+        format!("({} === null || {})", expr, self.guard(inner, expr, depth))
+      }
+    }
+  }
+
+  fn write_guard(&self, out: &mut String, id: ClassId) {
+    let name = &self.class_names[&id];
+    writeln!(
+      out,
+      "export function is{}(value: unknown): value is {} {{",
+      name, name
+    )
+    .unwrap();
+    let checks: Vec<String> = self
+      .graph
+      .class(id)
+      .properties
+      .iter()
+      .map(|property| {
+        let expr = format!("value[{}]", serde_json::Value::from(property.name.as_str()));
+        let check = self.guard(&property.ty, &expr, 0);
+        if property.optional {
+          format!("({} === undefined || {})", expr, check)
+        } else {
+          check
+        }
+      })
+      .collect();
+    out.push_str("  if (!isRecord(value)) return false;\n");
+    if checks.is_empty() {
+      out.push_str("  return true;\n}\n\n");
+    } else {
+      writeln!(
+        out,
+        "  return (\n    {}\n  );\n}}\n",
+        checks.join(" &&\n    ")
+      )
+      .unwrap();
+    }
+  }
+}
+
+// `A | B` needs parentheses before `[]`, `Record<string, A | B>` doesn't.
+fn has_top_level_union(ts: &str) -> bool {
+  let mut depth = 0;
+  for c in ts.chars() {
+    match c {
+      '(' | '[' | '<' | '{' => depth += 1,
+      ')' | ']' | '>' | '}' => depth -= 1,
+      '|' if depth == 0 => return true,
+      _ => {}
+    }
+  }
+  false
+}
+
+/// `name`, or `"first-name"` for keys that aren't identifiers.
+fn property_key(key: &str) -> String {
+  let is_ident = key
+    .chars()
+    .next()
+    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+    && key
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+  if is_ident {
+    key.into()
+  } else {
+    serde_json::Value::from(key).to_string()
+  }
+}
@@ -0,0 +1,187 @@
+//! Recognizes strings in well known formats, so they can be typed more precisely than a string.
+//! Every detector can be switched off or tuned through `Detectors`.
+
+#[cfg(test)]
+mod tests;
+
+use super::{
+  shape::{Object, Shape, Strings},
+  Format,
+};
+use regex::Regex;
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+const DATE: &str = r"\d{4}-(0[1-9]|1[0-2])-(0[1-9]|[12]\d|3[01])";
+const TIME: &str = r"([01]\d|2[0-3]):[0-5]\d:([0-5]\d|60)(\.\d+)?";
+const UUID: &str = "[0-9a-fA-F]{8}-([0-9a-fA-F]{4}-){3}[0-9a-fA-F]{12}";
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Detectors {
+  /// `date-time`, `date` and `time` strings.
+  pub date_time: Detector,
+  pub uuid: Detector,
+  pub uri: Detector,
+  pub email: Detector,
+  /// Integers in a string, e.g. `"42"`.
+  pub integer_string: Detector,
+  /// `"true"` and `"false"`.
+  pub bool_string: Detector,
+  pub enums: EnumDetector,
+  /// Objects keyed by IDs, e.g. `{ "u123": {...}, "u456": {...} }`, typed as maps.
+  pub maps: MapDetector,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Detector {
+  pub enabled: bool,
+  /// Fewer strings than this at one place are typed as plain strings.
+  pub min_samples: usize,
+}
+
+impl Default for Detector {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      min_samples: 1,
+    }
+  }
+}
+
+/// Strings with a few values that keep repeating, e.g. `"status": "active"`. Every value has to
+/// be seen at least twice on average.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct EnumDetector {
+  pub enabled: bool,
+  pub min_samples: usize,
+  /// At most `shape::MAX_VALUES`.
+  pub max_values: usize,
+}
+
+impl Default for EnumDetector {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      min_samples: 2,
+      max_values: 10,
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MapDetector {
+  pub enabled: bool,
+  /// Objects with fewer keys stay classes.
+  pub min_keys: usize,
+}
+
+impl Default for MapDetector {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      min_keys: 2,
+    }
+  }
+}
+
+impl Detectors {
+  fn detector(&self, format: Format) -> &Detector {
+    match format {
+      Format::DateTime | Format::Date | Format::Time => &self.date_time,
+      Format::Uuid => &self.uuid,
+      Format::Uri => &self.uri,
+      Format::Email => &self.email,
+      Format::IntegerString => &self.integer_string,
+      Format::BoolString => &self.bool_string,
+    }
+  }
+
+  /// The first format every string was in, if its detector is on.
+  pub fn format(&self, strings: &Strings) -> Option<Format> {
+    strings.formats.iter().copied().find(|&format| {
+      let detector = self.detector(format);
+      detector.enabled && strings.count >= detector.min_samples
+    })
+  }
+
+  /// The values of an enum, if the strings look like one.
+  pub fn enum_values<'a>(&self, strings: &'a Strings) -> Option<&'a [String]> {
+    let detector = &self.enums;
+    let values = strings.values.as_deref()?;
+    let is_enum = detector.enabled
+      && strings.count >= detector.min_samples
+      && values.len() <= detector.max_values
+      && strings.count >= 2 * values.len();
+    is_enum.then_some(values)
+  }
+
+  /// Whether the keys of an object look like IDs and its values all have one shape.
+  pub fn is_map(&self, object: &Object) -> bool {
+    let detector = &self.maps;
+    detector.enabled
+      && object.fields.len() >= detector.min_keys
+      && object.fields.iter().all(|f| is_id(&f.key))
+      && object
+        .fields
+        .iter()
+        .fold(Shape::Unknown, |acc, f| acc.unify(f.shape.clone()))
+        .is_uniform()
+  }
+}
+
+// `123`, `u123`, `item-7`, a UUID, a date or a hex hash. Without a separator it takes three digits,
+// so numbered fields like `line1` and `line2` stay properties.
+fn is_id(key: &str) -> bool {
+  static PATTERN: OnceLock<Regex> = OnceLock::new();
+  let pattern = PATTERN.get_or_init(|| {
+    Regex::new(&format!(
+      r"^(-?\d+|[A-Za-z]{{1,4}}[-_]\d+|[A-Za-z]{{1,4}}\d{{3,}}|{}|{})$",
+      UUID, DATE
+    ))
+    .unwrap()
+  });
+  let is_hash = key.len() >= 8
+    && key.chars().all(|c| c.is_ascii_hexdigit())
+    && key.chars().any(|c| c.is_ascii_digit());
+  is_hash || pattern.is_match(key)
+}
+
+fn patterns() -> &'static [(Format, Regex)] {
+  static PATTERNS: OnceLock<Vec<(Format, Regex)>> = OnceLock::new();
+  PATTERNS.get_or_init(|| {
+    [
+      (
+        Format::DateTime,
+        format!(r"^{}[Tt]{}([Zz]|[+-]([01]\d|2[0-3]):[0-5]\d)$", DATE, TIME),
+      ),
+      (Format::Date, format!("^{}$", DATE)),
+      (Format::Time, format!("^{}$", TIME)),
+      (Format::Uuid, format!("^{}$", UUID)),
+      // only absolute URLs, `a:b` is more likely something else
+      (
+        Format::Uri,
+        r"^[a-zA-Z][a-zA-Z0-9+.-]*://[^\s/?#]+\S*$".into(),
+      ),
+      (Format::Email, r"^[^\s@]+@[^\s@.]+(\.[^\s@.]+)+$".into()),
+      // no leading zeros, `"007"` is a code rather than a number
+      (Format::IntegerString, r"^-?(0|[1-9]\d*)$".into()),
+      (Format::BoolString, "^(true|false)$".into()),
+    ]
+    .into_iter()
+    .map(|(format, pattern)| (format, Regex::new(&pattern).unwrap()))
+    .collect()
+  })
+}
+
+/// The formats `s` is in, at most one.
+pub fn formats(s: &str) -> Vec<Format> {
+  patterns()
+    .iter()
+    .filter(|(_, re)| re.is_match(s))
+    .map(|(format, _)| *format)
+    .collect()
+}
@@ -0,0 +1,180 @@
+use super::{formats, Detectors};
+use crate::app::infer::{
+  shape::{Shape, Strings},
+  Format,
+};
+use serde_json::{json, Value};
+
+fn detectors(options: Value) -> Detectors {
+  serde_json::from_value(options).unwrap()
+}
+
+// What is seen at one place holding each of `values`.
+fn strings<S: AsRef<str>>(values: &[S]) -> Strings {
+  let shape = values.iter().fold(Shape::Unknown, |acc, v| {
+    acc.unify(Shape::of(&json!(v.as_ref())))
+  });
+  match shape {
+    Shape::String(strings) => strings,
+    shape => panic!("{:?}", shape),
+  }
+}
+
+#[test]
+fn formats_are_recognized() {
+  let cases = [
+    ("2024-01-02T03:04:05Z", Some(Format::DateTime)),
+    ("2024-01-02t03:04:05.5+01:00", Some(Format::DateTime)),
+    ("2024-01-02", Some(Format::Date)),
+    ("23:59:60", Some(Format::Time)),
+    ("123e4567-e89b-12d3-a456-426614174000", Some(Format::Uuid)),
+    ("https://example.com/a?b", Some(Format::Uri)),
+    ("ann@example.com", Some(Format::Email)),
+    ("-42", Some(Format::IntegerString)),
+    ("true", Some(Format::BoolString)),
+    ("2024-13-02", None),
+    ("a:b", None),
+    ("007", None),
+    ("True", None),
+  ];
+  for (s, format) in cases {
+    assert_eq!(formats(s), format.into_iter().collect::<Vec<_>>(), "{}", s);
+  }
+}
+
+#[test]
+fn formats_need_one_sample_by_default() {
+  let detectors = Detectors::default();
+  assert_eq!(
+    detectors.format(&strings(&["2024-01-02"])),
+    Some(Format::Date)
+  );
+  // every string has to be in the format
+  assert_eq!(detectors.format(&strings(&["2024-01-02", "soon"])), None);
+}
+
+#[test]
+fn enums_need_repeated_values_by_default() {
+  let detectors = Detectors::default();
+  let values = |v: &[&str]| detectors.enum_values(&strings(v)).map(<[String]>::to_vec);
+
+  // two samples
+  assert_eq!(values(&["open"]), None);
+  assert_eq!(values(&["open", "open"]), Some(vec!["open".to_string()]));
+  // each value seen twice on average
+  assert_eq!(values(&["open", "closed"]), None);
+  assert_eq!(values(&["open", "closed", "open"]), None);
+  assert_eq!(
+    values(&["open", "closed", "open", "closed"]),
+    Some(vec!["open".to_string(), "closed".to_string()])
+  );
+  // at most ten values, each seen twice
+  let twice = |n: usize| -> Vec<String> { (0..2 * n).map(|i| format!("v{}", i % n)).collect() };
+  let ten = twice(10);
+  let eleven = twice(11);
+  assert!(detectors.enum_values(&strings(&ten)).is_some());
+  assert!(detectors.enum_values(&strings(&eleven)).is_none());
+}
+
+#[test]
+fn disabled_detectors_find_nothing() {
+  let detectors = detectors(json!({
+    "dateTime": { "enabled": false },
+    "enums": { "enabled": false },
+  }));
+  assert_eq!(detectors.format(&strings(&["2024-01-02"])), None);
+  assert_eq!(
+    detectors.format(&strings(&["ann@example.com"])),
+    Some(Format::Email)
+  );
+  assert_eq!(detectors.enum_values(&strings(&["open", "open"])), None);
+}
+
+#[test]
+fn detectors_can_be_tuned() {
+  let detectors = detectors(json!({
+    "uuid": { "minSamples": 3 },
+    "enums": { "minSamples": 5, "maxValues": 2 },
+  }));
+  let uuid = "123e4567-e89b-12d3-a456-426614174000";
+  assert_eq!(detectors.format(&strings(&[uuid, uuid])), None);
+  assert_eq!(
+    detectors.format(&strings(&[uuid, uuid, uuid])),
+    Some(Format::Uuid)
+  );
+
+  assert!(detectors
+    .enum_values(&strings(&["a", "b", "a", "b"]))
+    .is_none());
+  assert!(detectors
+    .enum_values(&strings(&["a", "b", "a", "b", "a"]))
+    .is_some());
+  assert!(detectors
+    .enum_values(&strings(&["a", "b", "c", "a", "b", "c"]))
+    .is_none());
+}
+
+fn is_map(detectors: &Detectors, object: Value) -> bool {
+  match Shape::of(&object) {
+    Shape::Object(object) => detectors.is_map(&object),
+    shape => panic!("{:?}", shape),
+  }
+}
+
+#[test]
+fn objects_keyed_by_ids_are_maps() {
+  let detectors = Detectors::default();
+  let user = json!({ "name": "Ann" });
+  for keys in [
+    ["1", "2"],
+    ["u123", "u456"],
+    ["item-7", "item-8"],
+    ["usr_1", "usr_2"],
+    ["2024-01-02", "2024-01-03"],
+    [
+      "123e4567-e89b-12d3-a456-426614174000",
+      "123e4567-e89b-12d3-a456-426614174001",
+    ],
+    ["9f86d081", "4e07408562bedb8b"],
+  ] {
+    let object: serde_json::Map<_, _> =
+      keys.iter().map(|k| (k.to_string(), user.clone())).collect();
+    assert!(is_map(&detectors, Value::Object(object)), "{:?}", keys);
+  }
+}
+
+#[test]
+fn numbered_fields_are_not_ids() {
+  let detectors = Detectors::default();
+  assert!(!is_map(&detectors, json!({ "line1": "a", "line2": "b" })));
+  assert!(!is_map(
+    &detectors,
+    json!({ "img1": "a.png", "img2": "b.png" })
+  ));
+  assert!(!is_map(
+    &detectors,
+    json!({ "deadbeef": "a", "cafebabe": "b" })
+  ));
+  assert!(!is_map(&detectors, json!({ "name": "a", "title": "b" })));
+}
+
+#[test]
+fn maps_need_enough_keys_of_one_shape() {
+  let defaults = Detectors::default();
+  assert!(!is_map(&defaults, json!({ "u123": 1 })));
+  assert!(!is_map(&defaults, json!({ "u123": 1, "u456": "a" })));
+  // objects are one kind whatever their keys, missing ones become optional
+  assert!(is_map(
+    &defaults,
+    json!({ "u123": { "a": 1 }, "u456": { "b": 1 } })
+  ));
+  assert!(!is_map(
+    &defaults,
+    json!({ "u123": { "a": 1 }, "u456": [1] })
+  ));
+
+  let one_key = detectors(json!({ "maps": { "minKeys": 1 } }));
+  assert!(is_map(&one_key, json!({ "u123": 1 })));
+  let disabled = detectors(json!({ "maps": { "enabled": false } }));
+  assert!(!is_map(&disabled, json!({ "u123": 1, "u456": 2 })));
+}
@@ -0,0 +1,360 @@
+use super::{child_path, class_name};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Index of a class in `TypeGraph::classes`.
+pub type ClassId = usize;
+/// Index of an enum in `TypeGraph::enums`.
+pub type EnumId = usize;
+
+/// Language independent description of the inferred types, the input of every code generator.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeGraph {
+  pub classes: Vec<Class>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub enums: Vec<Enum>,
+  pub top_levels: Vec<TopLevel>,
+  /// How often each key was present, when inferred from samples.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub stats: Vec<FieldStats>,
+}
+
+/// `$.users[*].email` was present in `present` of the `total` objects found at `$.users[*]`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldStats {
+  pub path: String,
+  pub present: usize,
+  pub total: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TopLevel {
+  pub name: String,
+  #[serde(rename = "type")]
+  pub ty: Type,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Class {
+  pub name: String,
+  /// Where the class was first found, e.g. `$.users[*]`.
+  pub path: String,
+  pub properties: Vec<Property>,
+}
+
+/// A class with the properties of `members` followed by its own, e.g. from `allOf` or `extends`.
+#[derive(Debug, Clone)]
+pub struct Merge {
+  pub id: ClassId,
+  pub members: Vec<ClassId>,
+  pub own: Vec<Property>,
+}
+
+/// A string that only takes one of `values`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Enum {
+  pub name: String,
+  pub path: String,
+  pub values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Property {
+  /// The key as it appears in the JSON.
+  pub name: String,
+  #[serde(rename = "type")]
+  pub ty: Type,
+  /// Missing from some of the objects.
+  pub optional: bool,
+  /// What to name the field instead of the key, from `Options::renames`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub rename: Option<String>,
+}
+
+/// `{ "kind": "array", "items": { "kind": "class", "id": 0 } }`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Type {
+  /// No value was seen, e.g. the items of an empty array.
+  Any,
+  Null,
+  Bool,
+  Integer,
+  Double,
+  String,
+  /// A string in a well known format.
+  Format {
+    format: Format,
+  },
+  Enum {
+    id: EnumId,
+  },
+  Array {
+    items: Box<Type>,
+  },
+  /// An object used as a dictionary, all values have the same type.
+  Map {
+    values: Box<Type>,
+  },
+  Class {
+    id: ClassId,
+  },
+  /// Two or more non-null types, never nested.
+  Union {
+    members: Vec<Type>,
+  },
+  /// `inner` or `null`, `inner` is never `Null` or `Nullable` itself.
+  Nullable {
+    inner: Box<Type>,
+  },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+  DateTime,
+  Date,
+  Time,
+  Uuid,
+  Uri,
+  Email,
+  /// An integer in a string, e.g. `"42"`.
+  IntegerString,
+  /// `"true"` or `"false"`.
+  BoolString,
+}
+
+impl Format {
+  /// The name of the format in JSON Schema, which has none for strings holding other types.
+  pub fn as_str(self) -> Option<&'static str> {
+    Some(match self {
+      Self::DateTime => "date-time",
+      Self::Date => "date",
+      Self::Time => "time",
+      Self::Uuid => "uuid",
+      Self::Uri => "uri",
+      Self::Email => "email",
+      Self::IntegerString | Self::BoolString => return None,
+    })
+  }
+}
+
+impl Type {
+  pub fn array(items: Type) -> Self {
+    Self::Array {
+      items: Box::new(items),
+    }
+  }
+
+  pub fn nullable(inner: Type) -> Self {
+    match inner {
+      Self::Null | Self::Nullable { .. } | Self::Any => inner,
+      inner => Self::Nullable {
+        inner: Box::new(inner),
+      },
+    }
+  }
+
+  /// Builds the simplest type accepting any of `members`: nested unions are flattened, duplicates
+  /// dropped, and `Null` turns the result nullable.
+  pub fn union(members: Vec<Type>) -> Self {
+    let mut nullable = false;
+    let mut flat: Vec<Type> = vec![];
+    fn push(ty: Type, flat: &mut Vec<Type>) {
+      if !flat.contains(&ty) {
+        flat.push(ty);
+      }
+    }
+    for member in members {
+      match member {
+        Self::Null => nullable = true,
+        Self::Nullable { inner } => {
+          nullable = true;
+          match *inner {
+            Self::Union { members } => members.into_iter().for_each(|m| push(m, &mut flat)),
+            inner => push(inner, &mut flat),
+          }
+        }
+        Self::Union { members } => members.into_iter().for_each(|m| push(m, &mut flat)),
+        ty => push(ty, &mut flat),
+      }
+    }
+    // anything absorbs the rest
+    if flat.contains(&Self::Any) {
+      return Self::Any;
+    }
+    let ty = match flat.len() {
+      0 => return Self::Null,
+      1 => flat.pop().unwrap(),
+      _ => Self::Union { members: flat },
+    };
+    if nullable {
+      Self::nullable(ty)
+    } else {
+      ty
+    }
+  }
+
+  /// Whether the class `id` appears anywhere in the type.
+  pub fn refers_to(&self, id: ClassId) -> bool {
+    match self {
+      Self::Class { id: class } => *class == id,
+      Self::Array { items } => items.refers_to(id),
+      Self::Map { values } => values.refers_to(id),
+      Self::Union { members } => members.iter().any(|m| m.refers_to(id)),
+      Self::Nullable { inner } => inner.refers_to(id),
+      _ => false,
+    }
+  }
+
+  /// Moves the ids of the classes after `removed` down by one, once it was taken out of the graph.
+  pub fn class_removed(&mut self, removed: ClassId) {
+    match self {
+      Self::Class { id } if *id > removed => *id -= 1,
+      Self::Array { items } => items.class_removed(removed),
+      Self::Map { values } => values.class_removed(removed),
+      Self::Union { members } => members.iter_mut().for_each(|m| m.class_removed(removed)),
+      Self::Nullable { inner } => inner.class_removed(removed),
+      _ => {}
+    }
+  }
+
+  /// The type without its `Nullable` wrapper, and whether there was one.
+  pub fn non_null(&self) -> (&Type, bool) {
+    match self {
+      Self::Nullable { inner } => (inner, true),
+      ty => (ty, false),
+    }
+  }
+}
+
+impl TypeGraph {
+  pub fn class(&self, id: ClassId) -> &Class {
+    &self.classes[id]
+  }
+
+  pub fn enum_(&self, id: EnumId) -> &Enum {
+    &self.enums[id]
+  }
+
+  /// Adds a class named after `hint`. A class with the same properties is reused instead, keeping
+  /// the name of the first place it was found.
+  pub fn add_class(&mut self, hint: &str, path: &str, properties: Vec<Property>) -> ClassId {
+    if let Some(id) = self.classes.iter().position(|c| c.properties == properties) {
+      return id;
+    }
+    let id = self.reserve_class(hint, path);
+    self.classes[id].properties = properties;
+    id
+  }
+
+  /// Adds a class without properties yet, so they can refer back to it.
+  pub fn reserve_class(&mut self, hint: &str, path: &str) -> ClassId {
+    let name = self.unique_name(hint);
+    self.classes.push(Class {
+      name,
+      path: path.into(),
+      properties: vec![],
+    });
+    self.classes.len() - 1
+  }
+
+  /// Takes out a class nothing refers to anymore, the classes after it move down by one.
+  pub fn remove_class(&mut self, id: ClassId) {
+    self.classes.remove(id);
+    let types = self
+      .classes
+      .iter_mut()
+      .flat_map(|c| c.properties.iter_mut().map(|p| &mut p.ty))
+      .chain(self.top_levels.iter_mut().map(|t| &mut t.ty));
+    for ty in types {
+      ty.class_removed(id);
+    }
+  }
+
+  /// Adds an enum named after `hint`, or reuses one with the same values.
+  pub fn add_enum(&mut self, hint: &str, path: &str, values: Vec<String>) -> EnumId {
+    if let Some(id) = self.enums.iter().position(|e| e.values == values) {
+      return id;
+    }
+    let name = self.unique_name(hint);
+    self.enums.push(Enum {
+      name,
+      path: path.into(),
+      values,
+    });
+    self.enums.len() - 1
+  }
+
+  /// Fills in merged classes. Repeated until nothing changes, as a merged class can be a member
+  /// of another.
+  pub fn merge(&mut self, merges: &[Merge]) {
+    for _ in 0..=merges.len() {
+      let mut changed = false;
+      for merge in merges {
+        let mut properties: Vec<Property> = vec![];
+        let inherited = merge
+          .members
+          .iter()
+          .flat_map(|&member| self.classes[member].properties.clone());
+        for property in inherited.chain(merge.own.iter().cloned()) {
+          match properties.iter_mut().find(|p| p.name == property.name) {
+            // required by any member means required
+            Some(p) => {
+              p.optional &= property.optional;
+              p.ty = property.ty;
+            }
+            None => properties.push(property),
+          }
+        }
+        if self.classes[merge.id].properties != properties {
+          self.classes[merge.id].properties = properties;
+          changed = true;
+        }
+      }
+      if !changed {
+        break;
+      }
+    }
+  }
+
+  /// Applies names chosen by the user, keyed by the path of a class, enum or property.
+  pub fn rename(&mut self, renames: &HashMap<String, String>) {
+    for class in &mut self.classes {
+      if let Some(name) = renames.get(&class.path) {
+        class.name = name.clone();
+      }
+      for property in &mut class.properties {
+        property.rename = renames
+          .get(&child_path(&class.path, &property.name))
+          .cloned();
+      }
+    }
+    for e in &mut self.enums {
+      if let Some(name) = renames.get(&e.path) {
+        e.name = name.clone();
+      }
+    }
+  }
+
+  // Classes and enums share one namespace: `Item`, `Item2`.
+  fn unique_name(&self, hint: &str) -> String {
+    let base = class_name(hint);
+    let taken = |name: &str| {
+      self.classes.iter().any(|c| c.name == name) || self.enums.iter().any(|e| e.name == name)
+    };
+    let mut name = base.clone();
+    let mut n = 1;
+    while taken(&name) {
+      n += 1;
+      name = format!("{}{}", base, n);
+    }
+    name
+  }
+}
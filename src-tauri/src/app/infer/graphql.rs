@@ -0,0 +1,1216 @@
+//! GraphQL input. The operations of a source are typed against its schema, giving one top level
+//! per operation: the type of `data` in its response, with aliases, fragments, nullability, lists
+//! and enums the way the server returns them.
+//!
+//! Samples and files are GraphQL documents, SDL and operations can be mixed, or introspection
+//! results in JSON. Variables aren't typed, custom scalars are `Any` unless `Source::scalars`
+//! says what they hold.
+//!
+//! A field of a union or interface type with fragments on some of its possible types is a union
+//! of one class per possible type, each with only the fragments that apply to it. Selecting
+//! `__typename` tells them apart: it is an enum with the one name in each class.
+
+use super::{child_path, class_name, read_file, singular, Error, Result, Source};
+use super::{Format, Property, TopLevel, Type, TypeGraph};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// What the values of a custom scalar are, e.g. `"date-time"` for a `DateTime` scalar.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scalar {
+  String,
+  Integer,
+  Number,
+  Boolean,
+  DateTime,
+  Date,
+  Time,
+  Uuid,
+  Uri,
+  Email,
+  Any,
+}
+
+impl Scalar {
+  fn ty(self) -> Type {
+    let format = match self {
+      Self::String => return Type::String,
+      Self::Integer => return Type::Integer,
+      Self::Number => return Type::Double,
+      Self::Boolean => return Type::Bool,
+      Self::Any => return Type::Any,
+      Self::DateTime => Format::DateTime,
+      Self::Date => Format::Date,
+      Self::Time => Format::Time,
+      Self::Uuid => Format::Uuid,
+      Self::Uri => Format::Uri,
+      Self::Email => Format::Email,
+    };
+    Type::Format { format }
+  }
+}
+
+// Line and column, from 1.
+type Pos = (usize, usize);
+type ParseResult<T> = std::result::Result<T, (Pos, String)>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Name(String),
+  Str(String),
+  Num(String),
+  Punct(char),
+  /// `...`
+  Spread,
+}
+
+#[derive(Debug, Clone)]
+enum TypeRef {
+  Named(String),
+  List(Box<TypeRef>),
+  NonNull(Box<TypeRef>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+  Scalar,
+  Object,
+  Interface,
+  Union,
+  Enum,
+  Input,
+}
+
+#[derive(Debug, Clone)]
+struct TypeDef {
+  kind: Kind,
+  fields: Vec<(String, TypeRef)>,
+  /// Interfaces implemented by an object or interface.
+  interfaces: Vec<String>,
+  /// Members of a union.
+  members: Vec<String>,
+  /// Values of an enum.
+  values: Vec<String>,
+}
+
+impl TypeDef {
+  fn new(kind: Kind) -> Self {
+    Self {
+      kind,
+      fields: vec![],
+      interfaces: vec![],
+      members: vec![],
+      values: vec![],
+    }
+  }
+
+  // `extend type`, and the same type in SDL and introspection.
+  fn extend(&mut self, other: TypeDef) {
+    for (name, ty) in other.fields {
+      if !self.fields.iter().any(|(n, _)| *n == name) {
+        self.fields.push((name, ty));
+      }
+    }
+    self.interfaces.extend(other.interfaces);
+    self.members.extend(other.members);
+    self.values.extend(other.values);
+  }
+}
+
+#[derive(Debug, Clone)]
+enum Selection {
+  Field {
+    alias: Option<String>,
+    name: String,
+    /// Behind `@include` or `@skip`.
+    conditional: bool,
+    selections: Vec<Selection>,
+    text: usize,
+    pos: Pos,
+  },
+  /// `...Fragment`
+  Spread {
+    name: String,
+    conditional: bool,
+    text: usize,
+    pos: Pos,
+  },
+  /// `... on Type { }`, or `... { }` without a type condition.
+  Inline {
+    on: Option<String>,
+    conditional: bool,
+    selections: Vec<Selection>,
+    text: usize,
+    pos: Pos,
+  },
+}
+
+struct Operation {
+  /// `query`, `mutation` or `subscription`.
+  kind: String,
+  name: Option<String>,
+  selections: Vec<Selection>,
+  text: usize,
+  pos: Pos,
+}
+
+struct Fragment {
+  on: String,
+  selections: Vec<Selection>,
+  text: usize,
+  pos: Pos,
+}
+
+#[derive(Default)]
+struct Document {
+  types: Vec<(String, TypeDef)>,
+  /// `schema { query: Root }`
+  roots: Vec<(String, String)>,
+  operations: Vec<Operation>,
+  fragments: Vec<(String, Fragment)>,
+}
+
+/// Lowers the operations of `source` into `graph`, one top level per operation.
+pub fn lower(source: &Source, graph: &mut TypeGraph) -> Result<()> {
+  let mut texts: Vec<(String, String)> = source
+    .samples
+    .iter()
+    .enumerate()
+    .map(|(i, text)| (format!("sample {}", i + 1), text.clone()))
+    .collect();
+  for path in source.files()? {
+    texts.push((path.display().to_string(), read_file(&path)?));
+  }
+
+  let mut lowerer = Lowerer {
+    source,
+    graph,
+    labels: texts.iter().map(|(label, _)| label.clone()).collect(),
+    types: HashMap::new(),
+    fragments: HashMap::new(),
+  };
+  let mut roots = HashMap::new();
+  let mut operations = vec![];
+  for (text, (_, content)) in texts.iter().enumerate() {
+    // a query is never valid JSON, `{ user { id } }` included
+    let document = match serde_json::from_str::<Value>(content) {
+      Ok(value) => introspection(&value).map_err(|message| Error::Schema {
+        name: source.name.clone(),
+        location: lowerer.labels[text].clone(),
+        message,
+      })?,
+      Err(_) => tokenize(content)
+        .and_then(|tokens| {
+          Parser {
+            tokens,
+            at: 0,
+            text,
+          }
+          .document()
+        })
+        .map_err(|(pos, message)| lowerer.error(text, pos, message))?,
+    };
+    for (name, def) in document.types {
+      match lowerer.types.get_mut(&name) {
+        Some(existing) => existing.extend(def),
+        None => {
+          lowerer.types.insert(name, def);
+        }
+      }
+    }
+    roots.extend(document.roots);
+    operations.extend(document.operations);
+    for (name, fragment) in document.fragments {
+      if lowerer.fragments.contains_key(&name) {
+        let message = format!("fragment `{}` is defined twice", name);
+        return Err(lowerer.error(fragment.text, fragment.pos, message));
+      }
+      lowerer.fragments.insert(name, fragment);
+    }
+  }
+  if operations.is_empty() {
+    return Err(Error::NoSamples(source.name.clone()));
+  }
+  lowerer.check_cycles()?;
+
+  for operation in &operations {
+    let root = match roots.get(&operation.kind) {
+      Some(root) => root.clone(),
+      None => {
+        let mut chars = operation.kind.chars();
+        let first = chars.next().unwrap().to_uppercase();
+        first.chain(chars).collect()
+      }
+    };
+    if lowerer.kind(&root) != Some(Kind::Object) {
+      let message = format!("the schema has no {} type `{}`", operation.kind, root);
+      return Err(lowerer.error(operation.text, operation.pos, message));
+    }
+    let name = operation.name.as_deref().unwrap_or(&source.name);
+    let ty = lowerer.lower_selections(&root, &operation.selections, None, name, "$")?;
+    lowerer.graph.top_levels.push(TopLevel {
+      name: class_name(name),
+      ty,
+    });
+  }
+  Ok(())
+}
+
+struct Lowerer<'a> {
+  source: &'a Source,
+  graph: &'a mut TypeGraph,
+  labels: Vec<String>,
+  types: HashMap<String, TypeDef>,
+  fragments: HashMap<String, Fragment>,
+}
+
+/// A field of a selection set, with every selection of the same response key merged into it.
+struct Collected {
+  key: String,
+  name: String,
+  /// The type the field is looked up on, narrower than the selection set's in fragments.
+  on: String,
+  /// Only in some responses: behind `@include`/`@skip`, or in a fragment on a narrower type.
+  optional: bool,
+  selections: Vec<Selection>,
+  text: usize,
+  pos: Pos,
+}
+
+impl Lowerer<'_> {
+  fn error(&self, text: usize, (line, column): Pos, message: String) -> Error {
+    Error::Parse {
+      name: self.source.name.clone(),
+      location: format!("{}:{}:{}", self.labels[text], line, column),
+      message,
+    }
+  }
+
+  fn kind(&self, name: &str) -> Option<Kind> {
+    match name {
+      "Int" | "Float" | "String" | "Boolean" | "ID" => Some(Kind::Scalar),
+      name => self.types.get(name).map(|def| def.kind),
+    }
+  }
+
+  // Whether every value of `ty` is also a `condition`, so a fragment on it always applies.
+  fn applies(&self, condition: &str, ty: &str) -> bool {
+    condition == ty
+      || self
+        .types
+        .get(ty)
+        .is_some_and(|def| def.interfaces.iter().any(|i| i == condition))
+      || self
+        .types
+        .get(condition)
+        .is_some_and(|def| def.kind == Kind::Union && def.members.iter().any(|m| m == ty))
+  }
+
+  // A class with the fields `selections` picks from `on`. `typename` is the object type of the
+  // values when `on` is abstract and they are split by it, fragments on other types are left out.
+  fn lower_selections(
+    &mut self,
+    on: &str,
+    selections: &[Selection],
+    typename: Option<&str>,
+    hint: &str,
+    path: &str,
+  ) -> Result<Type> {
+    let mut fields = vec![];
+    let ty = typename.unwrap_or(on);
+    self.collect(ty, on, selections, false, &mut fields, &mut vec![])?;
+    let mut properties = vec![];
+    for field in &fields {
+      let field_path = child_path(path, &field.key);
+      properties.push(Property {
+        name: field.key.clone(),
+        ty: self.lower_field(field, typename, &field_path)?,
+        optional: field.optional,
+        rename: None,
+      });
+    }
+    Ok(Type::Class {
+      id: self.graph.add_class(hint, path, properties),
+    })
+  }
+
+  // Flattens fragments into `out`, merging fields by response key. `ty` is the type of the
+  // selection set being built, `on` the type `selections` are written against. Fragments that
+  // don't apply to an object `ty` are dropped, they never match.
+  fn collect(
+    &self,
+    ty: &str,
+    on: &str,
+    selections: &[Selection],
+    optional: bool,
+    out: &mut Vec<Collected>,
+    spreads: &mut Vec<String>,
+  ) -> Result<()> {
+    for selection in selections {
+      match selection {
+        Selection::Field {
+          alias,
+          name,
+          conditional,
+          selections,
+          text,
+          pos,
+        } => {
+          let key = alias.as_ref().unwrap_or(name).clone();
+          let optional = optional || *conditional;
+          match out.iter_mut().find(|field| field.key == key) {
+            Some(field) => {
+              field.optional &= optional;
+              field.selections.extend(selections.iter().cloned());
+            }
+            None => out.push(Collected {
+              key,
+              name: name.clone(),
+              on: on.into(),
+              optional,
+              selections: selections.clone(),
+              text: *text,
+              pos: *pos,
+            }),
+          }
+        }
+        Selection::Spread {
+          name,
+          conditional,
+          text,
+          pos,
+        } => {
+          let fragment = match self.fragments.get(name) {
+            Some(fragment) => fragment,
+            None => return Err(self.error(*text, *pos, format!("unknown fragment `{}`", name))),
+          };
+          if spreads.contains(name) {
+            let message = format!("fragment `{}` spreads itself", name);
+            return Err(self.error(*text, *pos, message));
+          }
+          self.check_condition(&fragment.on, fragment.text, fragment.pos)?;
+          let applies = self.applies(&fragment.on, ty);
+          if !applies && self.kind(ty) == Some(Kind::Object) {
+            continue;
+          }
+          spreads.push(name.clone());
+          let optional = optional || *conditional || !applies;
+          self.collect(
+            ty,
+            &fragment.on,
+            &fragment.selections,
+            optional,
+            out,
+            spreads,
+          )?;
+          spreads.pop();
+        }
+        Selection::Inline {
+          on: condition,
+          conditional,
+          selections,
+          text,
+          pos,
+        } => {
+          let condition = condition.as_deref().unwrap_or(on);
+          self.check_condition(condition, *text, *pos)?;
+          let applies = self.applies(condition, ty);
+          if !applies && self.kind(ty) == Some(Kind::Object) {
+            continue;
+          }
+          let optional = optional || *conditional || !applies;
+          self.collect(ty, condition, selections, optional, out, spreads)?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  // A fragment spreading itself, even in the selection set of one of its fields, would give an
+  // infinitely deep response.
+  fn check_cycles(&self) -> Result<()> {
+    let mut names: Vec<&String> = self.fragments.keys().collect();
+    names.sort();
+    for name in names {
+      self.check_spreads(&self.fragments[name].selections, &mut vec![name.clone()])?;
+    }
+    Ok(())
+  }
+
+  fn check_spreads(&self, selections: &[Selection], spreads: &mut Vec<String>) -> Result<()> {
+    for selection in selections {
+      match selection {
+        Selection::Field { selections, .. } | Selection::Inline { selections, .. } => {
+          self.check_spreads(selections, spreads)?
+        }
+        Selection::Spread {
+          name, text, pos, ..
+        } => {
+          if spreads.contains(name) {
+            let message = format!("fragment `{}` spreads itself", name);
+            return Err(self.error(*text, *pos, message));
+          }
+          // unknown ones are reported by `collect`
+          if let Some(fragment) = self.fragments.get(name) {
+            spreads.push(name.clone());
+            self.check_spreads(&fragment.selections, spreads)?;
+            spreads.pop();
+          }
+        }
+      }
+    }
+    Ok(())
+  }
+
+  // Whether some fragment of `selections` only applies to some of the values of `ty`.
+  fn narrows(&self, ty: &str, selections: &[Selection], spreads: &mut Vec<String>) -> bool {
+    selections.iter().any(|selection| match selection {
+      Selection::Field { .. } => false,
+      Selection::Spread { name, .. } => match self.fragments.get(name) {
+        Some(fragment) if !spreads.contains(name) => {
+          spreads.push(name.clone());
+          let narrows =
+            !self.applies(&fragment.on, ty) || self.narrows(ty, &fragment.selections, spreads);
+          spreads.pop();
+          narrows
+        }
+        // unknown or spreading itself, reported by `collect`
+        _ => false,
+      },
+      Selection::Inline { on, selections, .. } => {
+        on.as_deref().is_some_and(|on| !self.applies(on, ty))
+          || self.narrows(ty, selections, spreads)
+      }
+    })
+  }
+
+  // The object types a value of the union or interface `ty` can be, by name.
+  fn possible_types(&self, ty: &str) -> Vec<String> {
+    let mut types: Vec<String> = match self.types[ty].kind {
+      Kind::Union => self.types[ty].members.clone(),
+      _ => self
+        .types
+        .iter()
+        .filter(|(_, def)| def.kind == Kind::Object && def.interfaces.iter().any(|i| i == ty))
+        .map(|(name, _)| name.clone())
+        .collect(),
+    };
+    types.sort();
+    types.dedup();
+    types
+  }
+
+  fn check_condition(&self, condition: &str, text: usize, pos: Pos) -> Result<()> {
+    match self.kind(condition) {
+      Some(Kind::Object | Kind::Interface | Kind::Union) => Ok(()),
+      Some(_) => {
+        let message = format!("fragments can't be on `{}`, it has no fields", condition);
+        Err(self.error(text, pos, message))
+      }
+      None => Err(self.error(text, pos, format!("unknown type `{}`", condition))),
+    }
+  }
+
+  fn lower_field(&mut self, field: &Collected, typename: Option<&str>, path: &str) -> Result<Type> {
+    if field.name == "__typename" {
+      return Ok(match typename {
+        Some(typename) => Type::Enum {
+          id: self.graph.add_enum(
+            &format!("{} typename", typename),
+            path,
+            vec![typename.into()],
+          ),
+        },
+        None => Type::String,
+      });
+    }
+    // `__schema` and `__type`
+    if field.name.starts_with("__") {
+      return Ok(Type::Any);
+    }
+    let ty = self.types.get(&field.on).and_then(|def| {
+      def
+        .fields
+        .iter()
+        .find(|(name, _)| *name == field.name)
+        .map(|(_, ty)| ty.clone())
+    });
+    match ty {
+      Some(ty) => self.lower_ref(&ty, field, &field.key, path),
+      None => {
+        let message = format!("`{}` has no field `{}`", field.on, field.name);
+        Err(self.error(field.text, field.pos, message))
+      }
+    }
+  }
+
+  // Fields are nullable unless their type ends in `!`.
+  fn lower_ref(&mut self, ty: &TypeRef, field: &Collected, hint: &str, path: &str) -> Result<Type> {
+    let (ty, non_null) = match ty {
+      TypeRef::NonNull(inner) => (&**inner, true),
+      ty => (ty, false),
+    };
+    let lowered = match ty {
+      TypeRef::List(items) => {
+        Type::array(self.lower_ref(items, field, &singular(hint), &format!("{}[*]", path))?)
+      }
+      TypeRef::Named(name) => self.lower_named(name, field, hint, path)?,
+      // `T!!` doesn't parse
+      TypeRef::NonNull(inner) => self.lower_ref(inner, field, hint, path)?,
+    };
+    Ok(if non_null {
+      lowered
+    } else {
+      Type::nullable(lowered)
+    })
+  }
+
+  fn lower_named(&mut self, name: &str, field: &Collected, hint: &str, path: &str) -> Result<Type> {
+    let kind = self.kind(name);
+    let composite = matches!(kind, Some(Kind::Object | Kind::Interface | Kind::Union));
+    if composite && field.selections.is_empty() {
+      let message = format!("`{}` is a `{}` and needs a selection set", field.key, name);
+      return Err(self.error(field.text, field.pos, message));
+    }
+    if !composite && !field.selections.is_empty() {
+      let message = format!(
+        "`{}` is a `{}` and can't have a selection set",
+        field.key, name
+      );
+      return Err(self.error(field.text, field.pos, message));
+    }
+    Ok(match (name, kind) {
+      ("Int", _) => Type::Integer,
+      ("Float", _) => Type::Double,
+      ("String" | "ID", _) => Type::String,
+      ("Boolean", _) => Type::Bool,
+      (_, Some(Kind::Scalar)) => self.source.scalars.get(name).map_or(Type::Any, |s| s.ty()),
+      (_, Some(Kind::Enum)) => Type::Enum {
+        id: self
+          .graph
+          .add_enum(name, path, self.types[name].values.clone()),
+      },
+      (_, Some(Kind::Object)) => {
+        let selections = field.selections.clone();
+        self.lower_selections(name, &selections, None, hint, path)?
+      }
+      (_, Some(Kind::Interface | Kind::Union)) => {
+        let selections = field.selections.clone();
+        let types = self.possible_types(name);
+        if types.is_empty() || !self.narrows(name, &selections, &mut vec![]) {
+          return self.lower_selections(name, &selections, None, hint, path);
+        }
+        let mut members = vec![];
+        for typename in types {
+          let path = format!(
+            "{}[?(@.__typename=={})]",
+            path,
+            Value::from(typename.as_str())
+          );
+          let member =
+            self.lower_selections(name, &selections, Some(&typename), &typename, &path)?;
+          members.push(member);
+        }
+        Type::union(members)
+      }
+      (_, Some(Kind::Input) | None) => {
+        let message = format!("unknown output type `{}`", name);
+        return Err(self.error(field.text, field.pos, message));
+      }
+    })
+  }
+}
+
+// The types of an introspection result, `{ "__schema": ... }` or the whole response.
+fn introspection(value: &Value) -> std::result::Result<Document, String> {
+  let schema = value
+    .pointer("/data/__schema")
+    .or_else(|| value.get("__schema"))
+    .ok_or("not an introspection result, `__schema` is missing")?;
+  let names = |value: Option<&Value>| -> Vec<String> {
+    value
+      .and_then(Value::as_array)
+      .map(|items| {
+        items
+          .iter()
+          .filter_map(|item| item.get("name").and_then(Value::as_str))
+          .map(String::from)
+          .collect()
+      })
+      .unwrap_or_default()
+  };
+
+  let mut document = Document::default();
+  for (kind, key) in [
+    ("query", "queryType"),
+    ("mutation", "mutationType"),
+    ("subscription", "subscriptionType"),
+  ] {
+    if let Some(name) = schema
+      .pointer(&format!("/{}/name", key))
+      .and_then(Value::as_str)
+    {
+      document.roots.push((kind.into(), name.into()));
+    }
+  }
+  let types = schema
+    .get("types")
+    .and_then(Value::as_array)
+    .ok_or("`__schema.types` is missing")?;
+  for ty in types {
+    let name = ty
+      .get("name")
+      .and_then(Value::as_str)
+      .ok_or("a type has no name")?;
+    let kind = match ty.get("kind").and_then(Value::as_str) {
+      Some("SCALAR") => Kind::Scalar,
+      Some("OBJECT") => Kind::Object,
+      Some("INTERFACE") => Kind::Interface,
+      Some("UNION") => Kind::Union,
+      Some("ENUM") => Kind::Enum,
+      Some("INPUT_OBJECT") => Kind::Input,
+      _ => return Err(format!("`{}` has an unknown kind", name)),
+    };
+    let mut def = TypeDef::new(kind);
+    for field in ty
+      .get("fields")
+      .and_then(Value::as_array)
+      .into_iter()
+      .flatten()
+    {
+      let field_name = field
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+      let field_ty = field
+        .get("type")
+        .ok_or(())
+        .and_then(type_ref)
+        .map_err(|_| format!("`{}.{}` has an invalid type", name, field_name))?;
+      def.fields.push((field_name.into(), field_ty));
+    }
+    def.interfaces = names(ty.get("interfaces"));
+    def.members = names(ty.get("possibleTypes"));
+    def.values = names(ty.get("enumValues"));
+    document.types.push((name.into(), def));
+  }
+  Ok(document)
+}
+
+// `{ "kind": "NON_NULL", "ofType": { "kind": "SCALAR", "name": "ID" } }`
+fn type_ref(value: &Value) -> std::result::Result<TypeRef, ()> {
+  let of_type = || value.get("ofType").ok_or(()).and_then(type_ref);
+  match value.get("kind").and_then(Value::as_str) {
+    Some("NON_NULL") => Ok(TypeRef::NonNull(Box::new(of_type()?))),
+    Some("LIST") => Ok(TypeRef::List(Box::new(of_type()?))),
+    _ => match value.get("name").and_then(Value::as_str) {
+      Some(name) => Ok(TypeRef::Named(name.into())),
+      None => Err(()),
+    },
+  }
+}
+
+fn tokenize(text: &str) -> ParseResult<Vec<(Token, Pos)>> {
+  let chars: Vec<char> = text.chars().collect();
+  let mut tokens = vec![];
+  let (mut i, mut line, mut column) = (0, 1, 1);
+  // moves past `n` characters, keeping track of the position
+  let advance = |i: &mut usize, line: &mut usize, column: &mut usize, n: usize| {
+    for _ in 0..n {
+      if chars.get(*i) == Some(&'\n') {
+        *line += 1;
+        *column = 1;
+      } else {
+        *column += 1;
+      }
+      *i += 1;
+    }
+  };
+
+  while i < chars.len() {
+    let c = chars[i];
+    let pos = (line, column);
+    let rest: String = chars[i..chars.len().min(i + 3)].iter().collect();
+    // commas are insignificant, like whitespace
+    let len = if c.is_whitespace() || c == ',' || c == '\u{feff}' {
+      1
+    } else if c == '#' {
+      chars[i..]
+        .iter()
+        .position(|&c| c == '\n' || c == '\r')
+        .unwrap_or(chars.len() - i)
+    } else if c.is_ascii_alphabetic() || c == '_' {
+      let len = chars[i..]
+        .iter()
+        .position(|&c| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(chars.len() - i);
+      tokens.push((Token::Name(chars[i..i + len].iter().collect()), pos));
+      len
+    } else if c.is_ascii_digit() || c == '-' {
+      let len = 1
+        + chars[i + 1..]
+          .iter()
+          .position(|&c| !(c.is_ascii_alphanumeric() || c == '.' || c == '+' || c == '-'))
+          .unwrap_or(chars.len() - i - 1);
+      tokens.push((Token::Num(chars[i..i + len].iter().collect()), pos));
+      len
+    } else if rest == "\"\"\"" {
+      let end = (i + 3..chars.len().saturating_sub(2))
+        .find(|&j| chars[j..j + 3] == ['"'; 3] && chars[j - 1] != '\\');
+      match end {
+        Some(end) => {
+          tokens.push((Token::Str(chars[i + 3..end].iter().collect()), pos));
+          end + 3 - i
+        }
+        None => return Err((pos, "unterminated block string".into())),
+      }
+    } else if c == '"' {
+      let (value, len) = string(&chars[i..]).map_err(|message| (pos, message))?;
+      tokens.push((Token::Str(value), pos));
+      len
+    } else if rest == "..." {
+      tokens.push((Token::Spread, pos));
+      3
+    } else if "!$&():=@[]{}|".contains(c) {
+      tokens.push((Token::Punct(c), pos));
+      1
+    } else {
+      return Err((pos, format!("unexpected `{}`", c)));
+    };
+    advance(&mut i, &mut line, &mut column, len);
+  }
+  Ok(tokens)
+}
+
+// A string at the start of `chars`, and how many characters it takes.
+fn string(chars: &[char]) -> std::result::Result<(String, usize), String> {
+  let mut value = String::new();
+  let mut i = 1;
+  while let Some(&c) = chars.get(i) {
+    match c {
+      '"' => return Ok((value, i + 1)),
+      '\\' => {
+        let escaped = chars.get(i + 1).copied().unwrap_or('\\');
+        i += 2;
+        match escaped {
+          'n' => value.push('\n'),
+          't' => value.push('\t'),
+          'r' => value.push('\r'),
+          'b' => value.push('\u{8}'),
+          'f' => value.push('\u{c}'),
+          'u' => {
+            let hex: String = chars.iter().skip(i).take(4).collect();
+            let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+            value.push(c.ok_or("invalid `\\u` escape")?);
+            i += 4;
+          }
+          c => value.push(c),
+        }
+        continue;
+      }
+      '\n' => break,
+      c => value.push(c),
+    }
+    i += 1;
+  }
+  Err("unterminated string".into())
+}
+
+struct Parser {
+  tokens: Vec<(Token, Pos)>,
+  at: usize,
+  /// Index into the labels of the parsed texts, for errors.
+  text: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.at).map(|(token, _)| token)
+  }
+
+  fn pos(&self) -> Pos {
+    match self.tokens.get(self.at).or(self.tokens.last()) {
+      Some((_, pos)) => *pos,
+      None => (1, 1),
+    }
+  }
+
+  fn is(&self, c: char) -> bool {
+    self.peek() == Some(&Token::Punct(c))
+  }
+
+  fn is_name(&self, name: &str) -> bool {
+    matches!(self.peek(), Some(Token::Name(n)) if n == name)
+  }
+
+  // Consumes `c` if it's next.
+  fn eat(&mut self, c: char) -> bool {
+    let is = self.is(c);
+    if is {
+      self.at += 1;
+    }
+    is
+  }
+
+  fn expect(&mut self, c: char) -> ParseResult<()> {
+    if self.eat(c) {
+      Ok(())
+    } else {
+      Err(self.unexpected(&format!("`{}`", c)))
+    }
+  }
+
+  fn unexpected(&self, expected: &str) -> (Pos, String) {
+    let found = match self.peek() {
+      Some(Token::Name(s)) | Some(Token::Num(s)) => format!("`{}`", s),
+      Some(Token::Str(s)) => format!("{:?}", s),
+      Some(Token::Punct(c)) => format!("`{}`", c),
+      Some(Token::Spread) => "`...`".into(),
+      None => "the end".into(),
+    };
+    (
+      self.pos(),
+      format!("expected {}, found {}", expected, found),
+    )
+  }
+
+  fn name(&mut self) -> ParseResult<String> {
+    match self.peek() {
+      Some(Token::Name(name)) => {
+        let name = name.clone();
+        self.at += 1;
+        Ok(name)
+      }
+      _ => Err(self.unexpected("a name")),
+    }
+  }
+
+  // Descriptions document the schema, they are skipped.
+  fn description(&mut self) {
+    if matches!(self.peek(), Some(Token::Str(_))) {
+      self.at += 1;
+    }
+  }
+
+  fn document(mut self) -> ParseResult<Document> {
+    let mut document = Document::default();
+    while self.peek().is_some() {
+      self.description();
+      let pos = self.pos();
+      if self.is('{') {
+        let selections = self.selection_set()?;
+        document.operations.push(Operation {
+          kind: "query".into(),
+          name: None,
+          selections,
+          text: self.text,
+          pos,
+        });
+        continue;
+      }
+      let keyword = self.name()?;
+      match keyword.as_str() {
+        "query" | "mutation" | "subscription" => {
+          let name = match self.peek() {
+            Some(Token::Name(_)) => Some(self.name()?),
+            _ => None,
+          };
+          if self.eat('(') {
+            while !self.eat(')') {
+              self.expect('$')?;
+              self.name()?;
+              self.expect(':')?;
+              self.type_ref()?;
+              if self.eat('=') {
+                self.value()?;
+              }
+              self.directives()?;
+            }
+          }
+          self.directives()?;
+          let selections = self.selection_set()?;
+          document.operations.push(Operation {
+            kind: keyword,
+            name,
+            selections,
+            text: self.text,
+            pos,
+          });
+        }
+        "fragment" => {
+          let name = self.name()?;
+          if !self.is_name("on") {
+            return Err(self.unexpected("`on`"));
+          }
+          self.at += 1;
+          let on = self.name()?;
+          self.directives()?;
+          let selections = self.selection_set()?;
+          let fragment = Fragment {
+            on,
+            selections,
+            text: self.text,
+            pos,
+          };
+          document.fragments.push((name, fragment));
+        }
+        "schema" => {
+          self.directives()?;
+          self.expect('{')?;
+          while !self.eat('}') {
+            let kind = self.name()?;
+            self.expect(':')?;
+            document.roots.push((kind, self.name()?));
+          }
+        }
+        "extend" => {
+          if self.is_name("schema") {
+            self.at += 1;
+            self.directives()?;
+            if self.eat('{') {
+              while !self.eat('}') {
+                let kind = self.name()?;
+                self.expect(':')?;
+                document.roots.push((kind, self.name()?));
+              }
+            }
+          } else {
+            let keyword = self.name()?;
+            if let Some(def) = self.type_definition(&keyword)? {
+              document.types.push(def);
+            }
+          }
+        }
+        "directive" => {
+          self.expect('@')?;
+          self.name()?;
+          if self.eat('(') {
+            self.input_values(')')?;
+          }
+          if self.is_name("repeatable") {
+            self.at += 1;
+          }
+          if !self.is_name("on") {
+            return Err(self.unexpected("`on`"));
+          }
+          self.at += 1;
+          self.eat('|');
+          self.name()?;
+          while self.eat('|') {
+            self.name()?;
+          }
+        }
+        keyword => match self.type_definition(keyword)? {
+          Some(def) => document.types.push(def),
+          None => {
+            self.at -= 1;
+            return Err(self.unexpected("a definition"));
+          }
+        },
+      }
+    }
+    Ok(document)
+  }
+
+  // The rest of `type`, `interface`, `union`, `enum`, `input` and `scalar` definitions.
+  fn type_definition(&mut self, keyword: &str) -> ParseResult<Option<(String, TypeDef)>> {
+    let kind = match keyword {
+      "scalar" => Kind::Scalar,
+      "type" => Kind::Object,
+      "interface" => Kind::Interface,
+      "union" => Kind::Union,
+      "enum" => Kind::Enum,
+      "input" => Kind::Input,
+      _ => return Ok(None),
+    };
+    let name = self.name()?;
+    let mut def = TypeDef::new(kind);
+    if self.is_name("implements") {
+      self.at += 1;
+      self.eat('&');
+      def.interfaces.push(self.name()?);
+      while self.eat('&') {
+        def.interfaces.push(self.name()?);
+      }
+    }
+    self.directives()?;
+    match kind {
+      Kind::Object | Kind::Interface if self.eat('{') => {
+        while !self.eat('}') {
+          self.description();
+          let field = self.name()?;
+          if self.eat('(') {
+            self.input_values(')')?;
+          }
+          self.expect(':')?;
+          let ty = self.type_ref()?;
+          self.directives()?;
+          def.fields.push((field, ty));
+        }
+      }
+      Kind::Union if self.eat('=') => {
+        self.eat('|');
+        def.members.push(self.name()?);
+        while self.eat('|') {
+          def.members.push(self.name()?);
+        }
+      }
+      Kind::Enum if self.eat('{') => {
+        while !self.eat('}') {
+          self.description();
+          def.values.push(self.name()?);
+          self.directives()?;
+        }
+      }
+      Kind::Input if self.eat('{') => self.input_values('}')?,
+      _ => {}
+    }
+    Ok(Some((name, def)))
+  }
+
+  // Arguments or input fields with their types and defaults, up to `end`.
+  fn input_values(&mut self, end: char) -> ParseResult<()> {
+    while !self.eat(end) {
+      self.description();
+      self.name()?;
+      self.expect(':')?;
+      self.type_ref()?;
+      if self.eat('=') {
+        self.value()?;
+      }
+      self.directives()?;
+    }
+    Ok(())
+  }
+
+  fn type_ref(&mut self) -> ParseResult<TypeRef> {
+    let ty = if self.eat('[') {
+      let items = self.type_ref()?;
+      self.expect(']')?;
+      TypeRef::List(Box::new(items))
+    } else {
+      TypeRef::Named(self.name()?)
+    };
+    Ok(if self.eat('!') {
+      TypeRef::NonNull(Box::new(ty))
+    } else {
+      ty
+    })
+  }
+
+  // Values don't change the shape of a response, they are only checked for syntax.
+  fn value(&mut self) -> ParseResult<()> {
+    match self.peek() {
+      Some(Token::Punct('$')) => {
+        self.at += 1;
+        self.name()?;
+      }
+      Some(Token::Name(_) | Token::Num(_) | Token::Str(_)) => self.at += 1,
+      Some(Token::Punct('[')) => {
+        self.at += 1;
+        while !self.eat(']') {
+          self.value()?;
+        }
+      }
+      Some(Token::Punct('{')) => {
+        self.at += 1;
+        while !self.eat('}') {
+          self.name()?;
+          self.expect(':')?;
+          self.value()?;
+        }
+      }
+      _ => return Err(self.unexpected("a value")),
+    }
+    Ok(())
+  }
+
+  fn arguments(&mut self) -> ParseResult<()> {
+    if self.eat('(') {
+      while !self.eat(')') {
+        self.name()?;
+        self.expect(':')?;
+        self.value()?;
+      }
+    }
+    Ok(())
+  }
+
+  // Whether there was an `@include` or `@skip`.
+  fn directives(&mut self) -> ParseResult<bool> {
+    let mut conditional = false;
+    while self.eat('@') {
+      let name = self.name()?;
+      conditional |= name == "include" || name == "skip";
+      self.arguments()?;
+    }
+    Ok(conditional)
+  }
+
+  fn selection_set(&mut self) -> ParseResult<Vec<Selection>> {
+    self.expect('{')?;
+    let mut selections = vec![];
+    while !self.eat('}') {
+      let pos = self.pos();
+      if self.peek() == Some(&Token::Spread) {
+        self.at += 1;
+        let on = if self.is_name("on") {
+          self.at += 1;
+          Some(self.name()?)
+        } else {
+          None
+        };
+        match self.peek() {
+          Some(Token::Name(_)) if on.is_none() => {
+            let name = self.name()?;
+            let conditional = self.directives()?;
+            selections.push(Selection::Spread {
+              name,
+              conditional,
+              text: self.text,
+              pos,
+            });
+          }
+          _ => {
+            let conditional = self.directives()?;
+            selections.push(Selection::Inline {
+              on,
+              conditional,
+              selections: self.selection_set()?,
+              text: self.text,
+              pos,
+            });
+          }
+        }
+        continue;
+      }
+      let mut name = self.name()?;
+      let mut alias = None;
+      if self.eat(':') {
+        alias = Some(name);
+        name = self.name()?;
+      }
+      self.arguments()?;
+      let conditional = self.directives()?;
+      let selections_of_field = if self.is('{') {
+        self.selection_set()?
+      } else {
+        vec![]
+      };
+      selections.push(Selection::Field {
+        alias,
+        name,
+        conditional,
+        selections: selections_of_field,
+        text: self.text,
+        pos,
+      });
+    }
+    if selections.is_empty() {
+      return Err((self.pos(), "empty selection set".into()));
+    }
+    Ok(selections)
+  }
+}
@@ -0,0 +1,394 @@
+//! Offline type inference: turns JSON samples into a `TypeGraph` that the code generators work on,
+//! so models can be generated without reaching `conf::APP_URL`.
+
+pub mod detect;
+pub mod graph;
+pub mod graphql;
+pub mod sample;
+pub mod schema;
+pub mod shape;
+pub mod typescript;
+
+#[cfg(test)]
+mod tests;
+
+use crate::utils;
+use serde::{ser::Serializer, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+pub use graph::{ClassId, EnumId, FieldStats, Format, Merge, Property, TopLevel, Type, TypeGraph};
+use sample::{CsvOptions, SampleFormat};
+use shape::{Shape, Strings};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+  #[error("`{name}` {sample}: {source}")]
+  Json {
+    name: String,
+    sample: String,
+    source: serde_json::Error,
+  },
+  #[error("`{name}` {sample}: {source}")]
+  Yaml {
+    name: String,
+    sample: String,
+    source: serde_yaml::Error,
+  },
+  #[error("`{name}` {sample}: {source}")]
+  Toml {
+    name: String,
+    sample: String,
+    // boxed, it is several times the size of the other errors
+    source: Box<toml::de::Error>,
+  },
+  #[error("`{name}` {sample}: {message}")]
+  Csv {
+    name: String,
+    sample: String,
+    message: String,
+  },
+  #[error("{}: {source}", .path.display())]
+  Io {
+    path: PathBuf,
+    source: std::io::Error,
+  },
+  #[error("`{0}` has no samples")]
+  NoSamples(String),
+  #[error("`{name}` {location}: {message}")]
+  Schema {
+    name: String,
+    location: String,
+    message: String,
+  },
+  #[error("`{name}` {location}: {message}")]
+  Parse {
+    name: String,
+    location: String,
+    message: String,
+  },
+}
+
+impl Serialize for Error {
+  fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    serializer.serialize_str(self.to_string().as_ref())
+  }
+}
+
+/// One top-level type and the JSON, YAML, TOML or CSV documents it is inferred from. All samples
+/// are merged into one model: keys missing from some of them become optional, conflicting types
+/// become unions.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Source {
+  pub name: String,
+  #[serde(default)]
+  pub kind: SourceKind,
+  #[serde(default)]
+  pub samples: Vec<String>,
+  /// How `samples` are written, files under `dir` are read by their extension.
+  #[serde(default)]
+  pub format: SampleFormat,
+  #[serde(default)]
+  pub csv: CsvOptions,
+  /// What the custom scalars of a GraphQL schema hold, e.g. `{ "DateTime": "date-time" }`. The
+  /// others are `Any`.
+  #[serde(default)]
+  pub scalars: HashMap<String, graphql::Scalar>,
+  /// Every file under it with an extension of `kind`, e.g. `.json`, is a sample too. Relative to
+  /// `utils::app_root()`.
+  #[serde(default)]
+  pub dir: Option<PathBuf>,
+}
+
+/// What the samples of a source are.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SourceKind {
+  /// JSON documents to infer a model from.
+  #[default]
+  Json,
+  /// JSON Schema (draft-07 or 2020-12) describing the model, see `schema`.
+  JsonSchema,
+  /// TypeScript interfaces and type aliases, see `typescript`.
+  #[serde(rename = "typescript")]
+  TypeScript,
+  /// GraphQL operations and the schema they run against, as SDL or introspection JSON, see
+  /// `graphql`.
+  #[serde(rename = "graphql")]
+  GraphQl,
+}
+
+impl SourceKind {
+  fn extensions(self) -> &'static [&'static str] {
+    match self {
+      Self::Json => SampleFormat::EXTENSIONS,
+      Self::JsonSchema => &["json"],
+      Self::TypeScript => &["ts"],
+      Self::GraphQl => &["graphql", "gql", "json"],
+    }
+  }
+}
+
+impl Source {
+  fn read_samples(&self) -> Result<Vec<Value>> {
+    let mut samples = vec![];
+    for (i, text) in self.samples.iter().enumerate() {
+      let label = format!("sample {}", i + 1);
+      samples.extend(self.format.parse(self, &label, text)?);
+    }
+
+    for path in self.files()? {
+      let format = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(SampleFormat::from_extension)
+        .unwrap_or_default();
+      let label = path.display().to_string();
+      samples.extend(format.parse(self, &label, &read_file(&path)?)?);
+    }
+
+    if samples.is_empty() {
+      return Err(Error::NoSamples(self.name.clone()));
+    }
+    Ok(samples)
+  }
+
+  /// The files under `dir` with an extension of `kind`, sorted, skipping hidden ones.
+  fn files(&self) -> Result<Vec<PathBuf>> {
+    let dir = match &self.dir {
+      Some(v) => utils::app_root().join(v),
+      None => return Ok(vec![]),
+    };
+    if !dir.is_dir() {
+      return Err(Error::Io {
+        path: dir,
+        source: std::io::ErrorKind::NotFound.into(),
+      });
+    }
+    Ok(
+      WalkDir::new(&dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !utils::is_hidden(e))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|path| {
+          let ext = path.extension().and_then(|ext| ext.to_str());
+          ext.is_some_and(|ext| self.kind.extensions().contains(&ext))
+        })
+        .collect(),
+    )
+  }
+}
+
+fn read_file(path: &Path) -> Result<String> {
+  std::fs::read_to_string(path).map_err(|source| Error::Io {
+    path: path.to_path_buf(),
+    source,
+  })
+}
+
+/// How samples are interpreted.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Options {
+  pub detectors: detect::Detectors,
+  /// Objects at these paths, e.g. `$.users` or `$.teams[*].members`, are typed as a map or a class
+  /// whatever `detect::Detectors::maps` would decide.
+  pub objects: HashMap<String, ObjectKind>,
+  /// Names for the classes, enums and properties at these paths, e.g. `$.users[*]` or
+  /// `$.users[*]["2fa"]`. They are still cased the way each language wants. A class found at
+  /// several paths is renamed by the first one.
+  pub renames: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ObjectKind {
+  Map,
+  Class,
+}
+
+/// Infers one top-level type per source, classes found in several sources are shared.
+pub fn infer(sources: &[Source], options: &Options) -> Result<TypeGraph> {
+  let mut graph = TypeGraph::default();
+  for source in sources {
+    match source.kind {
+      SourceKind::Json => {}
+      SourceKind::JsonSchema => {
+        schema::lower(source, &mut graph)?;
+        continue;
+      }
+      SourceKind::TypeScript => {
+        typescript::lower(source, &mut graph)?;
+        continue;
+      }
+      SourceKind::GraphQl => {
+        graphql::lower(source, &mut graph)?;
+        continue;
+      }
+    }
+
+    let mut shape = Shape::Unknown;
+    for value in source.read_samples()? {
+      shape = shape.unify(Shape::of(&value));
+    }
+    let ty = Builder {
+      graph: &mut graph,
+      options,
+    }
+    .lower(&shape, &source.name, "$");
+    graph.top_levels.push(TopLevel {
+      name: class_name(&source.name),
+      ty,
+    });
+  }
+  graph.rename(&options.renames);
+  Ok(graph)
+}
+
+struct Builder<'a> {
+  graph: &'a mut TypeGraph,
+  options: &'a Options,
+}
+
+impl Builder<'_> {
+  // `name` is the key or top-level name the shape was found under, used to name its classes.
+  fn lower(&mut self, shape: &Shape, name: &str, path: &str) -> Type {
+    match shape {
+      Shape::Unknown => Type::Any,
+      Shape::Null => Type::Null,
+      Shape::Bool => Type::Bool,
+      Shape::Integer => Type::Integer,
+      Shape::Double => Type::Double,
+      Shape::String(strings) => self.lower_string(strings, name, path),
+      Shape::Array(items) => {
+        Type::array(self.lower(items, &singular(name), &format!("{}[*]", path)))
+      }
+      Shape::Union(members) => Type::union(
+        members
+          .iter()
+          .map(|member| self.lower(member, name, path))
+          .collect(),
+      ),
+      Shape::Object(object) => {
+        let is_map = match self.options.objects.get(path) {
+          Some(kind) => *kind == ObjectKind::Map,
+          None => self.options.detectors.is_map(object),
+        };
+        if is_map {
+          let values = object
+            .fields
+            .iter()
+            .fold(Shape::Unknown, |acc, field| acc.unify(field.shape.clone()));
+          return Type::Map {
+            values: Box::new(self.lower(&values, &singular(name), &format!("{}.*", path))),
+          };
+        }
+
+        let properties = object
+          .fields
+          .iter()
+          .map(|field| {
+            let field_path = child_path(path, &field.key);
+            let ty = self.lower(&field.shape, &field.key, &field_path);
+            self.graph.stats.push(FieldStats {
+              path: field_path,
+              present: field.count,
+              total: object.count,
+            });
+            Property {
+              name: field.key.clone(),
+              ty,
+              optional: field.count < object.count,
+              rename: None,
+            }
+          })
+          .collect();
+        Type::Class {
+          id: self.graph.add_class(name, path, properties),
+        }
+      }
+    }
+  }
+
+  // A format all strings are in, or an enum when a few values keep repeating.
+  fn lower_string(&mut self, strings: &Strings, name: &str, path: &str) -> Type {
+    let detectors = &self.options.detectors;
+    if let Some(format) = detectors.format(strings) {
+      return Type::Format { format };
+    }
+    match detectors.enum_values(strings) {
+      Some(values) => Type::Enum {
+        id: self.graph.add_enum(name, path, values.to_vec()),
+      },
+      None => Type::String,
+    }
+  }
+}
+
+/// `$.users`, or `$["first name"]` for keys that aren't identifiers.
+pub fn child_path(path: &str, key: &str) -> String {
+  let is_ident = key
+    .chars()
+    .next()
+    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+    && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+  if is_ident {
+    format!("{}.{}", path, key)
+  } else {
+    format!("{}[{}]", path, Value::from(key))
+  }
+}
+
+/// `user_id` -> `UserId`. Letters outside ASCII are left for the generators to transliterate.
+pub fn class_name(hint: &str) -> String {
+  let name: String = hint
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .map(|word| {
+      let mut chars = word.chars();
+      let first = chars.next().unwrap().to_uppercase();
+      first.chain(chars).collect::<String>()
+    })
+    .collect();
+  match name.chars().next() {
+    None => "Class".into(),
+    Some(c) if c.is_ascii_digit() => format!("T{}", name),
+    Some(_) => name,
+  }
+}
+
+/// Items of `users` are named `User`.
+pub fn singular(name: &str) -> String {
+  if let Some(stem) = name.strip_suffix("ies") {
+    format!("{}y", stem)
+  } else if name.ends_with('s') && !name.ends_with("ss") && name.len() > 1 {
+    name[..name.len() - 1].into()
+  } else {
+    format!("{}Item", name)
+  }
+}
+
+pub mod cmd {
+  use super::{Options, Result, Source, TypeGraph};
+  use tauri::command;
+
+  /// Runs on the async runtime, large samples would otherwise block the UI thread.
+  #[command]
+  pub async fn infer_types(sources: Vec<Source>, options: Option<Options>) -> Result<TypeGraph> {
+    super::infer(&sources, &options.unwrap_or_default())
+  }
+}
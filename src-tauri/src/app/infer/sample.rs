@@ -0,0 +1,330 @@
+//! Sample formats besides JSON. Every sample is read into a `serde_json::Value`, inference doesn't
+//! know where it came from.
+
+use super::{Error, Result, Source};
+use serde::Deserialize;
+use serde_json::{Map, Number, Value};
+
+/// How a sample is written. Files are read by their extension.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SampleFormat {
+  #[default]
+  Json,
+  /// One JSON document per line, also known as JSON Lines. Every line is a sample.
+  Ndjson,
+  /// Every document of a multi-document stream is a sample.
+  Yaml,
+  Toml,
+  /// Every row is a sample, read with `Source::csv`.
+  Csv,
+  /// CSV with tabs between cells.
+  Tsv,
+}
+
+/// How CSV and TSV samples are read.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CsvOptions {
+  /// `,` for CSV and a tab for TSV when not set.
+  pub delimiter: Option<char>,
+  /// `null` turns quoting off.
+  pub quote: Option<char>,
+  /// Whether the first row names the columns, guessed when not set: it does when its cells are
+  /// distinct text and some column below it holds numbers or booleans, so a CSV of only text
+  /// needs it set. Columns without a name are called `column1`, `column2` and so on.
+  pub header: Option<bool>,
+}
+
+impl Default for CsvOptions {
+  fn default() -> Self {
+    Self {
+      delimiter: None,
+      quote: Some('"'),
+      header: None,
+    }
+  }
+}
+
+impl SampleFormat {
+  pub const EXTENSIONS: &'static [&'static str] = &[
+    "json", "ndjson", "jsonl", "yaml", "yml", "toml", "csv", "tsv",
+  ];
+
+  pub fn from_extension(ext: &str) -> Option<Self> {
+    match ext {
+      "json" => Some(Self::Json),
+      "ndjson" | "jsonl" => Some(Self::Ndjson),
+      "yaml" | "yml" => Some(Self::Yaml),
+      "toml" => Some(Self::Toml),
+      "csv" => Some(Self::Csv),
+      "tsv" => Some(Self::Tsv),
+      _ => None,
+    }
+  }
+
+  /// The samples in `text`, `label` names it in errors.
+  pub fn parse(self, source: &Source, label: &str, text: &str) -> Result<Vec<Value>> {
+    let name = &source.name;
+    match self {
+      Self::Json => {
+        let value = serde_json::from_str(text).map_err(|source| Error::Json {
+          name: name.into(),
+          sample: label.into(),
+          source,
+        })?;
+        Ok(vec![value])
+      }
+      Self::Ndjson => text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+          serde_json::from_str(line).map_err(|source| Error::Json {
+            name: name.into(),
+            sample: format!("{}:{}", label, i + 1),
+            source,
+          })
+        })
+        .collect(),
+      Self::Yaml => {
+        let error = |source| Error::Yaml {
+          name: name.into(),
+          sample: label.into(),
+          source,
+        };
+        let mut samples = vec![];
+        for document in serde_yaml::Deserializer::from_str(text) {
+          let mut value = serde_yaml::Value::deserialize(document).map_err(error)?;
+          // `<<: *defaults`
+          value.apply_merge().map_err(error)?;
+          samples.push(from_yaml(value));
+        }
+        Ok(samples)
+      }
+      Self::Toml => {
+        let table: toml::Table = toml::from_str(text).map_err(|source| Error::Toml {
+          name: name.into(),
+          sample: label.into(),
+          source: Box::new(source),
+        })?;
+        Ok(vec![from_toml(toml::Value::Table(table))])
+      }
+      Self::Csv => parse_csv(text, ',', &source.csv).map_err(|message| Error::Csv {
+        name: name.into(),
+        sample: label.into(),
+        message,
+      }),
+      Self::Tsv => parse_csv(text, '\t', &source.csv).map_err(|message| Error::Csv {
+        name: name.into(),
+        sample: label.into(),
+        message,
+      }),
+    }
+  }
+}
+
+// NaN and infinity can't be written in JSON, they are typed like `null`.
+fn from_f64(f: f64) -> Value {
+  Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+fn from_yaml(value: serde_yaml::Value) -> Value {
+  use serde_yaml::Value as Yaml;
+  match value {
+    Yaml::Null => Value::Null,
+    Yaml::Bool(b) => b.into(),
+    Yaml::Number(n) => match (n.as_i64(), n.as_u64()) {
+      (Some(i), _) => i.into(),
+      (_, Some(u)) => u.into(),
+      _ => from_f64(n.as_f64().unwrap_or(f64::NAN)),
+    },
+    Yaml::String(s) => s.into(),
+    Yaml::Sequence(items) => items.into_iter().map(from_yaml).collect(),
+    Yaml::Mapping(mapping) => mapping
+      .into_iter()
+      .map(|(key, value)| (yaml_key(key), from_yaml(value)))
+      .collect::<Map<_, _>>()
+      .into(),
+    // `!custom value` is read as its value
+    Yaml::Tagged(tagged) => from_yaml(tagged.value),
+  }
+}
+
+// JSON keys are strings, `1: a` has the key `"1"`.
+fn yaml_key(key: serde_yaml::Value) -> String {
+  match from_yaml(key) {
+    Value::String(s) => s,
+    key => key.to_string(),
+  }
+}
+
+// Dates and times become RFC 3339 strings, which the `date-time` detector picks up.
+fn from_toml(value: toml::Value) -> Value {
+  use toml::Value as Toml;
+  match value {
+    Toml::String(s) => s.into(),
+    Toml::Integer(i) => i.into(),
+    Toml::Float(f) => from_f64(f),
+    Toml::Boolean(b) => b.into(),
+    Toml::Datetime(datetime) => datetime.to_string().into(),
+    Toml::Array(items) => items.into_iter().map(from_toml).collect(),
+    Toml::Table(table) => table
+      .into_iter()
+      .map(|(key, value)| (key, from_toml(value)))
+      .collect::<Map<_, _>>()
+      .into(),
+  }
+}
+
+// What all non-empty cells of a column are, from the narrowest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Column {
+  Integer,
+  Double,
+  Bool,
+  String,
+}
+
+impl Column {
+  fn of(cell: &str) -> Self {
+    let digits = cell.strip_prefix('-').unwrap_or(cell);
+    // `007` is a code rather than a number
+    let leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+    if leading_zero {
+      Self::String
+    } else if cell.parse::<i64>().is_ok() {
+      Self::Integer
+    } else if cell.parse::<f64>().is_ok() && digits.starts_with(|c: char| c.is_ascii_digit()) {
+      Self::Double
+    } else if cell.eq_ignore_ascii_case("true") || cell.eq_ignore_ascii_case("false") {
+      Self::Bool
+    } else {
+      Self::String
+    }
+  }
+
+  fn unify(self, other: Self) -> Self {
+    match (self, other) {
+      (a, b) if a == b => a,
+      (Self::Integer | Self::Double, Self::Integer | Self::Double) => Self::Double,
+      _ => Self::String,
+    }
+  }
+
+  // Empty cells are `null`, the column turns nullable.
+  fn value(self, cell: &str) -> Value {
+    let trimmed = cell.trim();
+    if trimmed.is_empty() {
+      return Value::Null;
+    }
+    match self {
+      Self::Integer => trimmed.parse::<i64>().map_or(Value::Null, Value::from),
+      Self::Double => trimmed.parse::<f64>().map_or(Value::Null, from_f64),
+      Self::Bool => trimmed.eq_ignore_ascii_case("true").into(),
+      Self::String => cell.into(),
+    }
+  }
+}
+
+// One object per row, keyed by the column names. Dates and enums are left to the detectors.
+fn parse_csv(
+  text: &str,
+  delimiter: char,
+  options: &CsvOptions,
+) -> std::result::Result<Vec<Value>, String> {
+  let ascii = |c: char, what: &str| match u8::try_from(c) {
+    Ok(b) if b.is_ascii() => Ok(b),
+    _ => Err(format!("the {} must be an ASCII character", what)),
+  };
+  let mut builder = csv::ReaderBuilder::new();
+  builder
+    .has_headers(false)
+    .flexible(true)
+    .delimiter(ascii(options.delimiter.unwrap_or(delimiter), "delimiter")?);
+  match options.quote {
+    Some(quote) => builder.quote(ascii(quote, "quote")?),
+    None => builder.quoting(false),
+  };
+  let mut rows = vec![];
+  for record in builder.from_reader(text.as_bytes()).records() {
+    let record = record.map_err(|err| err.to_string())?;
+    rows.push(record.iter().map(String::from).collect::<Vec<_>>());
+  }
+
+  let header = match options.header {
+    Some(header) => header,
+    None => looks_like_header(&rows),
+  };
+  let header = if header && !rows.is_empty() {
+    rows.remove(0)
+  } else {
+    vec![]
+  };
+  let width = rows
+    .iter()
+    .map(Vec::len)
+    .max()
+    .unwrap_or(0)
+    .max(header.len());
+  let mut names: Vec<String> = vec![];
+  for i in 0..width {
+    let name = match header.get(i).map(|name| name.trim()) {
+      Some(name) if !name.is_empty() => name.to_string(),
+      _ => format!("column{}", i + 1),
+    };
+    // repeated names are numbered, `name`, `name2`
+    let mut unique = name.clone();
+    let mut n = 1;
+    while names.contains(&unique) {
+      n += 1;
+      unique = format!("{}{}", name, n);
+    }
+    names.push(unique);
+  }
+
+  let columns: Vec<Column> = (0..width).map(|i| column(&rows, i)).collect();
+  // cells missing from short rows make their columns optional
+  Ok(
+    rows
+      .iter()
+      .map(|row| {
+        row
+          .iter()
+          .zip(&names)
+          .zip(&columns)
+          .map(|((cell, name), column)| (name.clone(), column.value(cell)))
+          .collect::<Map<_, _>>()
+          .into()
+      })
+      .collect(),
+  )
+}
+
+// The type of the non-empty cells of column `i`, `String` when there are none.
+fn column(rows: &[Vec<String>], i: usize) -> Column {
+  rows
+    .iter()
+    .filter_map(|row| row.get(i).map(|cell| cell.trim()))
+    .filter(|cell| !cell.is_empty())
+    .map(Column::of)
+    .reduce(Column::unify)
+    .unwrap_or(Column::String)
+}
+
+// A first row of distinct names that aren't numbers or booleans, above a column that is. Like
+// csv-sniffer, the types of the first row are compared with those of the rest: when every column
+// is text, the first row can't be told apart from data and is kept.
+fn looks_like_header(rows: &[Vec<String>]) -> bool {
+  let (first, rest) = match rows.split_first() {
+    Some(split) => split,
+    None => return false,
+  };
+  let names = first.iter().enumerate().all(|(i, cell)| {
+    let cell = cell.trim();
+    !cell.is_empty()
+      && Column::of(cell) == Column::String
+      && !first[..i].iter().any(|other| other.trim() == cell)
+  });
+  names && (0..first.len()).any(|i| column(rest, i) != Column::String)
+}
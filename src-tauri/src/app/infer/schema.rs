@@ -0,0 +1,676 @@
+//! JSON Schema input. Schemas (draft-07 or 2020-12) are lowered into the same `TypeGraph` that
+//! sample inference builds, so every generator accepts them.
+//!
+//! `$ref`s are resolved within a document, across the `.json` files of `Source::dir` (by relative
+//! path or `$id`) and by `$anchor`. Files outside of `Source::dir` can't be referenced. Objects
+//! referring back to themselves become recursive classes, any other reference cycle is an error.
+//! Keywords next to a `$ref` are merged with its target like `allOf` in documents whose `$schema`
+//! is 2019-09 or later, as draft-07 ignores them.
+
+use super::{child_path, class_name, read_file, singular, Error, Result, Source};
+use super::{ClassId, Format, Merge, Property, TopLevel, Type, TypeGraph};
+use serde_json::{Map, Value};
+use std::{
+  collections::HashMap,
+  path::{Component, Path, PathBuf},
+};
+
+struct Doc {
+  /// Where the document was read from, `None` for inline schemas.
+  file: Option<PathBuf>,
+  /// The `$id` of the document, refs are resolved against it.
+  id: Option<String>,
+  /// Whether keywords next to a `$ref` apply, they do since 2019-09.
+  ref_siblings: bool,
+  root: Value,
+}
+
+impl Doc {
+  fn new(file: Option<PathBuf>, root: Value) -> Self {
+    let id = root
+      .get("$id")
+      .and_then(Value::as_str)
+      .map(|id| id.trim_end_matches('#').to_string());
+    let ref_siblings = root
+      .get("$schema")
+      .and_then(Value::as_str)
+      .is_some_and(|uri| uri.contains("/2019-09/") || uri.contains("/2020-12/"));
+    Self {
+      file,
+      id,
+      ref_siblings,
+      root,
+    }
+  }
+
+  fn label(&self) -> String {
+    match &self.file {
+      Some(path) => path.display().to_string(),
+      None => "schema".into(),
+    }
+  }
+}
+
+// A schema node: a document and a JSON pointer into it.
+type Location = (usize, String);
+
+struct Lowerer<'a> {
+  source: &'a Source,
+  graph: &'a mut TypeGraph,
+  docs: Vec<Doc>,
+  /// Relative `$ref`s of inline schemas are resolved against it.
+  dir: Option<PathBuf>,
+  done: HashMap<Location, Type>,
+  /// Nodes being lowered, a `$ref` back to one of them is a cycle.
+  pending: Vec<Location>,
+  /// Classes made from `allOf`, filled in once their members are complete.
+  merges: Vec<Merge>,
+}
+
+/// Lowers the schemas of `source` into `graph`, adding their top-level types.
+pub fn lower(source: &Source, graph: &mut TypeGraph) -> Result<()> {
+  let mut lowerer = Lowerer {
+    source,
+    graph,
+    docs: vec![],
+    dir: None,
+    done: HashMap::new(),
+    pending: vec![],
+    merges: vec![],
+  };
+
+  // every file of the directory can be referenced, by path or by `$id`
+  let files = source.files()?;
+  if let Some(dir) = files.first().and(source.dir.as_ref()) {
+    lowerer.dir = Some(normalize(&crate::utils::app_root().join(dir)));
+  }
+  for path in &files {
+    lowerer.load(path)?;
+  }
+
+  let mut roots = vec![];
+  for (i, text) in source.samples.iter().enumerate() {
+    let root = serde_json::from_str(text).map_err(|err| Error::Json {
+      name: source.name.clone(),
+      sample: format!("sample {}", i + 1),
+      source: err,
+    })?;
+    lowerer.docs.push(Doc::new(None, root));
+    roots.push((lowerer.docs.len() - 1, source.name.clone()));
+  }
+  // without inline schemas, each file is a top level of its own
+  if roots.is_empty() {
+    roots = (0..lowerer.docs.len())
+      .map(|doc| {
+        let stem = lowerer.docs[doc]
+          .file
+          .as_ref()
+          .and_then(|p| p.file_stem())
+          .map(|s| s.to_string_lossy().trim_end_matches(".schema").to_string())
+          .unwrap_or_else(|| source.name.clone());
+        (doc, stem)
+      })
+      .collect();
+  }
+  if roots.is_empty() {
+    return Err(Error::NoSamples(source.name.clone()));
+  }
+
+  for (doc, name) in roots {
+    let hint = title(&lowerer.docs[doc].root).unwrap_or(name);
+    let ty = lowerer.lower_at((doc, String::new()), &hint, "$")?;
+    let definitions = ["$defs", "definitions"]
+      .iter()
+      .find_map(|key| Some((*key, lowerer.docs[doc].root.get(key)?.as_object()?.clone())));
+    match (ty, definitions) {
+      // a file that only holds definitions contributes each of them
+      (Type::Any, Some((key, definitions))) => {
+        for name in definitions.keys() {
+          let pointer = format!("/{}/{}", key, escape(name));
+          let ty = lowerer.lower_at((doc, pointer), name, "$")?;
+          lowerer.graph.top_levels.push(TopLevel {
+            name: class_name(name),
+            ty,
+          });
+        }
+      }
+      (ty, _) => lowerer.graph.top_levels.push(TopLevel {
+        name: class_name(&hint),
+        ty,
+      }),
+    }
+  }
+  lowerer.graph.merge(&lowerer.merges);
+  Ok(())
+}
+
+fn title(schema: &Value) -> Option<String> {
+  schema.get("title")?.as_str().map(String::from)
+}
+
+/// `~` and `/` escaped for a JSON pointer.
+fn escape(key: &str) -> String {
+  key.replace('~', "~0").replace('/', "~1")
+}
+
+impl Lowerer<'_> {
+  fn load(&mut self, path: &Path) -> Result<usize> {
+    let path = normalize(path);
+    if let Some(doc) = self
+      .docs
+      .iter()
+      .position(|d| d.file.as_deref() == Some(&path))
+    {
+      return Ok(doc);
+    }
+    let root = serde_json::from_str(&read_file(&path)?).map_err(|err| Error::Json {
+      name: self.source.name.clone(),
+      sample: path.display().to_string(),
+      source: err,
+    })?;
+    self.docs.push(Doc::new(Some(path), root));
+    Ok(self.docs.len() - 1)
+  }
+
+  fn error(&self, (doc, pointer): &Location, message: String) -> Error {
+    Error::Schema {
+      name: self.source.name.clone(),
+      location: format!("{}#{}", self.docs[*doc].label(), pointer),
+      message,
+    }
+  }
+
+  // Only classes can be referred to before they are complete.
+  fn cycle_error(&self, loc: &Location) -> Error {
+    let schema = self.docs[loc.0].root.pointer(&loc.1);
+    let message = match ["oneOf", "anyOf", "allOf"]
+      .into_iter()
+      .find(|key| schema.and_then(|s| s.get(key)).is_some())
+    {
+      Some(key) => format!(
+        "reference cycle through `{}`, only objects can refer back to themselves",
+        key
+      ),
+      None => "reference cycle that doesn't go through an object".into(),
+    };
+    self.error(loc, message)
+  }
+
+  // `hint` names the classes and enums found at `loc`, `path` is where its values are in the data.
+  fn lower_at(&mut self, loc: Location, hint: &str, path: &str) -> Result<Type> {
+    if let Some(ty) = self.done.get(&loc) {
+      return Ok(ty.clone());
+    }
+    if self.pending.contains(&loc) {
+      return Err(self.cycle_error(&loc));
+    }
+    self.pending.push(loc.clone());
+    let ty = self.lower_node(&loc, hint, path);
+    self.pending.pop();
+    let ty = ty?;
+    self.done.insert(loc, ty.clone());
+    Ok(ty)
+  }
+
+  fn lower_node(&mut self, loc: &Location, hint: &str, path: &str) -> Result<Type> {
+    let schema = match self.docs[loc.0].root.pointer(&loc.1) {
+      Some(Value::Object(v)) => v.clone(),
+      // `true` accepts anything, `false` nothing
+      Some(Value::Bool(_)) => return Ok(Type::Any),
+      _ => return Err(self.error(loc, "not a schema".into())),
+    };
+    let title = schema.get("title").and_then(Value::as_str);
+    let hint = title.unwrap_or(hint);
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+      // since 2019-09 the keywords next to a `$ref` apply as well, they are merged like `allOf`
+      if self.docs[loc.0].ref_siblings
+        && schema
+          .keys()
+          .any(|key| !REF_SIBLINGS.contains(&key.as_str()))
+      {
+        let merged = with_ref_in_all_of(schema.clone());
+        let count = merged["allOf"].as_array().map_or(0, Vec::len);
+        if let Some(node) = self.docs[loc.0].root.pointer_mut(&loc.1) {
+          *node = Value::Object(merged.clone());
+        }
+        return self.lower_all_of(loc, &merged, count, hint, path);
+      }
+      let target = self.resolve(loc, reference)?;
+      let name = target.1.rsplit('/').next().filter(|s| !s.is_empty());
+      let name = name.map(|s| s.replace("~1", "/").replace("~0", "~"));
+      return self.lower_at(target, name.as_deref().unwrap_or(hint), path);
+    }
+    if let Some(value) = schema.get("const") {
+      return Ok(self.lower_enum(std::slice::from_ref(value), hint, path));
+    }
+    if let Some(Value::Array(values)) = schema.get("enum") {
+      return Ok(self.lower_enum(values, hint, path));
+    }
+    if let Some(Value::Array(members)) = schema.get("allOf") {
+      return self.lower_all_of(loc, &schema, members.len(), hint, path);
+    }
+    for key in ["oneOf", "anyOf"] {
+      if let Some(Value::Array(members)) = schema.get(key) {
+        let members = (0..members.len())
+          .map(|i| self.lower_at((loc.0, format!("{}/{}/{}", loc.1, key, i)), hint, path))
+          .collect::<Result<Vec<_>>>()?;
+        // members that only add constraints, e.g. `{ "required": ["id"] }`, leave the type as is
+        if members.iter().any(|m| *m != Type::Any) {
+          return Ok(Type::union(members));
+        }
+      }
+    }
+
+    match schema.get("type") {
+      Some(Value::String(ty)) => self.lower_typed(loc, &schema, ty, hint, path),
+      Some(Value::Array(types)) => {
+        let mut members = vec![];
+        for ty in types {
+          let ty = ty.as_str().unwrap_or_default();
+          members.push(self.lower_typed(loc, &schema, ty, hint, path)?);
+        }
+        Ok(Type::union(members))
+      }
+      _ if ["properties", "additionalProperties", "patternProperties"]
+        .iter()
+        .any(|k| schema.contains_key(*k)) =>
+      {
+        self.lower_typed(loc, &schema, "object", hint, path)
+      }
+      _ if schema.contains_key("items") || schema.contains_key("prefixItems") => {
+        self.lower_typed(loc, &schema, "array", hint, path)
+      }
+      _ => Ok(Type::Any),
+    }
+  }
+
+  fn lower_typed(
+    &mut self,
+    loc: &Location,
+    schema: &Map<String, Value>,
+    ty: &str,
+    hint: &str,
+    path: &str,
+  ) -> Result<Type> {
+    Ok(match ty {
+      "null" => Type::Null,
+      "boolean" => Type::Bool,
+      "integer" => Type::Integer,
+      "number" => Type::Double,
+      "string" => match schema
+        .get("format")
+        .and_then(Value::as_str)
+        .and_then(format)
+      {
+        Some(format) => Type::Format { format },
+        None => Type::String,
+      },
+      "array" => {
+        let items_hint = singular(hint);
+        let items_path = format!("{}[*]", path);
+        let mut members = vec![];
+        let mut lower_item = |this: &mut Self, pointer: String| {
+          this
+            .lower_at((loc.0, pointer), &items_hint, &items_path)
+            .map(|ty| members.push(ty))
+        };
+        // tuples are typed as arrays of any of their item types
+        for key in ["prefixItems", "items"] {
+          match schema.get(key) {
+            Some(Value::Array(items)) => {
+              for i in 0..items.len() {
+                lower_item(self, format!("{}/{}/{}", loc.1, key, i))?;
+              }
+            }
+            Some(_) => lower_item(self, format!("{}/{}", loc.1, key))?,
+            None => {}
+          }
+        }
+        Type::array(if members.is_empty() {
+          Type::Any
+        } else {
+          Type::union(members)
+        })
+      }
+      "object" => {
+        let has_properties = schema
+          .get("properties")
+          .and_then(Value::as_object)
+          .is_some_and(|p| !p.is_empty());
+        let closed = schema.get("additionalProperties") == Some(&Value::Bool(false));
+        if has_properties || closed {
+          let id = self.graph.reserve_class(hint, path);
+          // lets properties refer back to the class
+          self.done.insert(loc.clone(), Type::Class { id });
+          let properties = self.lower_properties(loc, schema, path)?;
+          self.graph.classes[id].properties = properties;
+          Type::Class { id }
+        } else {
+          self.lower_map(loc, schema, hint, path)?
+        }
+      }
+      ty => return Err(self.error(loc, format!("unknown type `{}`", ty))),
+    })
+  }
+
+  fn lower_properties(
+    &mut self,
+    loc: &Location,
+    schema: &Map<String, Value>,
+    path: &str,
+  ) -> Result<Vec<Property>> {
+    let required: Vec<&str> = match schema.get("required") {
+      Some(Value::Array(keys)) => keys.iter().filter_map(Value::as_str).collect(),
+      _ => vec![],
+    };
+    let keys: Vec<String> = match schema.get("properties") {
+      Some(Value::Object(properties)) => properties.keys().cloned().collect(),
+      _ => vec![],
+    };
+    keys
+      .into_iter()
+      .map(|key| {
+        let pointer = format!("{}/properties/{}", loc.1, escape(&key));
+        let ty = self.lower_at((loc.0, pointer), &key, &child_path(path, &key))?;
+        Ok(Property {
+          optional: !required.contains(&key.as_str()),
+          name: key,
+          ty,
+          rename: None,
+        })
+      })
+      .collect()
+  }
+
+  // An object without known properties is a dictionary.
+  fn lower_map(
+    &mut self,
+    loc: &Location,
+    schema: &Map<String, Value>,
+    hint: &str,
+    path: &str,
+  ) -> Result<Type> {
+    let values_path = format!("{}.*", path);
+    let mut members = vec![];
+    if let Some(Value::Object(patterns)) = schema.get("patternProperties") {
+      for pattern in patterns.keys() {
+        let pointer = format!("{}/patternProperties/{}", loc.1, escape(pattern));
+        members.push(self.lower_at((loc.0, pointer), hint, &values_path)?);
+      }
+    }
+    if let Some(Value::Object(_)) = schema.get("additionalProperties") {
+      let pointer = format!("{}/additionalProperties", loc.1);
+      members.push(self.lower_at((loc.0, pointer), hint, &values_path)?);
+    }
+    Ok(Type::Map {
+      values: Box::new(if members.is_empty() {
+        Type::Any
+      } else {
+        Type::union(members)
+      }),
+    })
+  }
+
+  // Objects are merged into one class, for anything else the first member with a type wins.
+  fn lower_all_of(
+    &mut self,
+    loc: &Location,
+    schema: &Map<String, Value>,
+    count: usize,
+    hint: &str,
+    path: &str,
+  ) -> Result<Type> {
+    // reserved up front, so members can refer back to it and it is named before them
+    let id = self.graph.reserve_class(hint, path);
+    self.done.insert(loc.clone(), Type::Class { id });
+
+    let mut members = vec![];
+    for i in 0..count {
+      let pointer = format!("{}/allOf/{}", loc.1, i);
+      members.push(self.lower_at((loc.0, pointer), hint, path)?);
+    }
+    let classes: Vec<_> = members
+      .iter()
+      .filter(|m| **m != Type::Any)
+      .map(|m| match m {
+        Type::Class { id } => Some(*id),
+        _ => None,
+      })
+      .collect();
+
+    if classes.iter().all(Option::is_some) {
+      let own = self.lower_properties(loc, schema, path)?;
+      // members may still be incomplete when they refer back to this class
+      self.merges.push(Merge {
+        id,
+        members: classes.into_iter().flatten().collect(),
+        own,
+      });
+      return Ok(Type::Class { id });
+    }
+    // not objects after all, so the reserved class goes again unless a member refers to it
+    self.done.remove(loc);
+    let referenced = self.done.values().any(|ty| ty.refers_to(id))
+      || self
+        .merges
+        .iter()
+        .any(|merge| merge.members.contains(&id) || merge.own.iter().any(|p| p.ty.refers_to(id)));
+    if referenced {
+      return Err(self.cycle_error(loc));
+    }
+    self.remove_class(id);
+    let mut ty = members
+      .into_iter()
+      .find(|m| *m != Type::Any)
+      .unwrap_or(Type::Any);
+    ty.class_removed(id);
+    Ok(ty)
+  }
+
+  // Takes a class out of the graph and out of the types and merges built so far.
+  fn remove_class(&mut self, id: ClassId) {
+    self.graph.remove_class(id);
+    self.done.values_mut().for_each(|ty| ty.class_removed(id));
+    let shift = |class: &mut ClassId| {
+      if *class > id {
+        *class -= 1;
+      }
+    };
+    for merge in &mut self.merges {
+      shift(&mut merge.id);
+      merge.members.iter_mut().for_each(shift);
+      merge.own.iter_mut().for_each(|p| p.ty.class_removed(id));
+    }
+  }
+
+  fn lower_enum(&mut self, values: &[Value], hint: &str, path: &str) -> Type {
+    let strings: Vec<String> = values
+      .iter()
+      .filter_map(|v| v.as_str().map(String::from))
+      .collect();
+    let mut members: Vec<Type> = values
+      .iter()
+      .filter(|v| !v.is_string())
+      .map(|v| match v {
+        Value::Null => Type::Null,
+        Value::Bool(_) => Type::Bool,
+        Value::Number(n) if n.is_f64() => Type::Double,
+        Value::Number(_) => Type::Integer,
+        _ => Type::Any,
+      })
+      .collect();
+    if !strings.is_empty() {
+      members.push(Type::Enum {
+        id: self.graph.add_enum(hint, path, strings),
+      });
+    }
+    Type::union(members)
+  }
+
+  fn resolve(&mut self, from: &Location, reference: &str) -> Result<Location> {
+    let (uri, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+    let doc = if uri.is_empty() {
+      from.0
+    } else if let Some(doc) = self.find_by_id(from.0, uri) {
+      doc
+    } else {
+      match self.find_file(from, uri)? {
+        Some(path) => self.load(&path)?,
+        None => return Err(self.error(from, format!("can't resolve `{}`", reference))),
+      }
+    };
+
+    let pointer = if fragment.is_empty() || fragment.starts_with('/') {
+      percent_decode(fragment)
+    } else {
+      find_anchor(&self.docs[doc].root, fragment, String::new())
+        .ok_or_else(|| self.error(from, format!("no `$anchor` named `{}`", fragment)))?
+    };
+    if self.docs[doc].root.pointer(&pointer).is_none() {
+      return Err(self.error(from, format!("`{}` points at nothing", reference)));
+    }
+    Ok((doc, pointer))
+  }
+
+  fn find_by_id(&self, from: usize, uri: &str) -> Option<usize> {
+    let absolute = match &self.docs[from].id {
+      Some(base) if !uri.contains(':') => match base.rfind('/') {
+        Some(i) => format!("{}/{}", &base[..i], uri),
+        None => uri.to_string(),
+      },
+      _ => uri.to_string(),
+    };
+    self
+      .docs
+      .iter()
+      .position(|d| d.id.as_deref() == Some(absolute.as_str()))
+  }
+
+  // Only files under `Source::dir`, remote schemas aren't fetched.
+  fn find_file(&self, from: &Location, uri: &str) -> Result<Option<PathBuf>> {
+    let root = match &self.dir {
+      Some(dir) if !uri.contains("://") => dir,
+      _ => return Ok(None),
+    };
+    let base = match &self.docs[from.0].file {
+      Some(file) => file.parent().unwrap_or(root),
+      None => root,
+    };
+    let path = base.join(percent_decode(uri));
+    if !path.is_file() {
+      return Ok(None);
+    }
+    // symlinks are followed before checking, they can't lead out either
+    let path = normalize(&path);
+    if !path.starts_with(root) {
+      return Err(self.error(from, format!("`{}` is outside of {}", uri, root.display())));
+    }
+    Ok(Some(path))
+  }
+}
+
+// Keywords that don't change the type, a `$ref` next to only these is followed as it is.
+const REF_SIBLINGS: &[&str] = &[
+  "$ref",
+  "$schema",
+  "$id",
+  "$anchor",
+  "$comment",
+  "$defs",
+  "definitions",
+  "title",
+  "description",
+  "default",
+  "examples",
+  "deprecated",
+  "readOnly",
+  "writeOnly",
+];
+
+// `{ "$ref": r, ... }` -> `{ "allOf": [..., { "$ref": r }], ... }`. The other keywords stay where
+// they are, so pointers into them still resolve.
+fn with_ref_in_all_of(mut schema: Map<String, Value>) -> Map<String, Value> {
+  let reference = schema.remove("$ref").unwrap_or_default();
+  let mut all_of = match schema.remove("allOf") {
+    Some(Value::Array(members)) => members,
+    _ => vec![],
+  };
+  all_of.push(serde_json::json!({ "$ref": reference }));
+  schema.insert("allOf".into(), Value::Array(all_of));
+  schema
+}
+
+fn format(name: &str) -> Option<Format> {
+  Some(match name {
+    "date-time" => Format::DateTime,
+    "date" => Format::Date,
+    "time" => Format::Time,
+    "uuid" => Format::Uuid,
+    "uri" | "iri" | "uri-reference" | "iri-reference" => Format::Uri,
+    "email" | "idn-email" => Format::Email,
+    _ => return None,
+  })
+}
+
+// Pointer to the subschema with `"$anchor": name`, or the draft-07 form `"$id": "#name"`.
+fn find_anchor(value: &Value, name: &str, pointer: String) -> Option<String> {
+  match value {
+    Value::Object(map) => {
+      let is_anchor = map.get("$anchor").and_then(Value::as_str) == Some(name)
+        || map.get("$id").and_then(Value::as_str) == Some(&format!("#{}", name));
+      if is_anchor {
+        return Some(pointer);
+      }
+      map
+        .iter()
+        .find_map(|(key, v)| find_anchor(v, name, format!("{}/{}", pointer, escape(key))))
+    }
+    Value::Array(items) => items
+      .iter()
+      .enumerate()
+      .find_map(|(i, v)| find_anchor(v, name, format!("{}/{}", pointer, i))),
+    _ => None,
+  }
+}
+
+fn percent_decode(s: &str) -> String {
+  let bytes = s.as_bytes();
+  let mut out = vec![];
+  let mut i = 0;
+  while i < bytes.len() {
+    let hex = bytes
+      .get(i + 1..i + 3)
+      .and_then(|h| std::str::from_utf8(h).ok())
+      .and_then(|h| u8::from_str_radix(h, 16).ok());
+    match (bytes[i], hex) {
+      (b'%', Some(byte)) => {
+        out.push(byte);
+        i += 3;
+      }
+      (byte, _) => {
+        out.push(byte);
+        i += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&out).into_owned()
+}
+
+// `a/./b/../c.json` -> `a/c.json`, so one file is loaded once however it is referenced.
+fn normalize(path: &Path) -> PathBuf {
+  if let Ok(path) = path.canonicalize() {
+    return path;
+  }
+  let mut out = PathBuf::new();
+  for component in path.components() {
+    match component {
+      Component::CurDir => {}
+      Component::ParentDir => {
+        out.pop();
+      }
+      c => out.push(c),
+    }
+  }
+  out
+}
@@ -0,0 +1,186 @@
+use super::{detect, Format};
+use serde_json::Value;
+
+/// Distinct strings kept per place, more than this can't be an enum.
+pub const MAX_VALUES: usize = 256;
+
+/// What has been seen at one place across all samples, before it is turned into a `TypeGraph`.
+/// Objects stay inline, so shapes can be unified structurally.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+  /// Nothing seen yet, e.g. the items of `[]`.
+  Unknown,
+  Null,
+  Bool,
+  Integer,
+  Double,
+  String(Strings),
+  Array(Box<Shape>),
+  Object(Object),
+  /// At most one member of each `Kind`, never `Unknown` or another union.
+  Union(Vec<Shape>),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Object {
+  pub fields: Vec<Field>,
+  /// Number of objects merged into this one.
+  pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Strings {
+  /// Number of strings merged into this one.
+  pub count: usize,
+  /// The distinct strings in the order they were seen, `None` once there are more than
+  /// `MAX_VALUES`.
+  pub values: Option<Vec<String>>,
+  /// The formats every string was in.
+  pub formats: Vec<Format>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+  pub key: String,
+  pub shape: Shape,
+  /// Number of objects that had the key.
+  pub count: usize,
+}
+
+// Shapes of the same kind are merged instead of forming a union.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+  Null,
+  Bool,
+  Number,
+  String,
+  Array,
+  Object,
+}
+
+impl Shape {
+  pub fn of(value: &Value) -> Self {
+    match value {
+      Value::Null => Self::Null,
+      Value::Bool(_) => Self::Bool,
+      Value::Number(n) if n.is_f64() => Self::Double,
+      Value::Number(_) => Self::Integer,
+      Value::String(s) => Self::String(Strings {
+        count: 1,
+        values: Some(vec![s.clone()]),
+        formats: detect::formats(s),
+      }),
+      Value::Array(items) => Self::Array(Box::new(
+        items
+          .iter()
+          .fold(Self::Unknown, |acc, item| acc.unify(Self::of(item))),
+      )),
+      Value::Object(map) => Self::Object(Object {
+        fields: map
+          .iter()
+          .map(|(key, value)| Field {
+            key: key.clone(),
+            shape: Self::of(value),
+            count: 1,
+          })
+          .collect(),
+        count: 1,
+      }),
+    }
+  }
+
+  fn kind(&self) -> Option<Kind> {
+    Some(match self {
+      Self::Unknown | Self::Union(_) => return None,
+      Self::Null => Kind::Null,
+      Self::Bool => Kind::Bool,
+      Self::Integer | Self::Double => Kind::Number,
+      Self::String(_) => Kind::String,
+      Self::Array(_) => Kind::Array,
+      Self::Object(_) => Kind::Object,
+    })
+  }
+
+  /// Whether every value seen here was of one kind, or null.
+  pub fn is_uniform(&self) -> bool {
+    match self {
+      Self::Union(members) => members.iter().filter(|m| **m != Self::Null).count() <= 1,
+      _ => true,
+    }
+  }
+
+  /// The shape of a place that has held either value.
+  pub fn unify(self, other: Shape) -> Shape {
+    match (self, other) {
+      (Self::Unknown, shape) | (shape, Self::Unknown) => shape,
+      (Self::Union(members), Self::Union(others)) => {
+        Self::Union(others.into_iter().fold(members, add_member))
+      }
+      (Self::Union(members), shape) | (shape, Self::Union(members)) => {
+        Self::Union(add_member(members, shape))
+      }
+      (a, b) if a.kind() == b.kind() => merge(a, b),
+      (a, b) => Self::Union(vec![a, b]),
+    }
+  }
+}
+
+fn add_member(mut members: Vec<Shape>, shape: Shape) -> Vec<Shape> {
+  match members.iter().position(|m| m.kind() == shape.kind()) {
+    Some(i) => {
+      let member = std::mem::replace(&mut members[i], Shape::Unknown);
+      members[i] = merge(member, shape);
+    }
+    None => members.push(shape),
+  }
+  members
+}
+
+// Both shapes are of the same kind.
+fn merge(a: Shape, b: Shape) -> Shape {
+  match (a, b) {
+    (Shape::Array(a), Shape::Array(b)) => Shape::Array(Box::new(a.unify(*b))),
+    (Shape::Object(a), Shape::Object(b)) => Shape::Object(a.merge(b)),
+    (Shape::String(a), Shape::String(b)) => Shape::String(a.merge(b)),
+    (Shape::Integer, Shape::Integer) => Shape::Integer,
+    (Shape::Integer | Shape::Double, Shape::Integer | Shape::Double) => Shape::Double,
+    (a, _) => a,
+  }
+}
+
+impl Object {
+  /// Keys keep the order they were first seen in.
+  pub fn merge(mut self, other: Object) -> Object {
+    for field in other.fields {
+      match self.fields.iter_mut().find(|f| f.key == field.key) {
+        Some(f) => {
+          let shape = std::mem::replace(&mut f.shape, Shape::Unknown);
+          f.shape = shape.unify(field.shape);
+          f.count += field.count;
+        }
+        None => self.fields.push(field),
+      }
+    }
+    self.count += other.count;
+    self
+  }
+}
+
+impl Strings {
+  pub fn merge(mut self, other: Strings) -> Strings {
+    self.count += other.count;
+    self.values = match (self.values, other.values) {
+      (Some(mut values), Some(others)) => {
+        for value in others {
+          if !values.contains(&value) {
+            values.push(value);
+          }
+        }
+        Some(values).filter(|v| v.len() <= MAX_VALUES)
+      }
+      _ => None,
+    };
+    self.formats.retain(|f| other.formats.contains(f));
+    self
+  }
+}
@@ -0,0 +1,838 @@
+use super::{infer, Error, Options, Source, Type, TypeGraph};
+use crate::cli;
+use serde_json::{json, Value};
+use std::{fs, path::Path};
+
+fn graph_of(sources: Value) -> TypeGraph {
+  let sources: Vec<Source> = serde_json::from_value(sources).unwrap();
+  infer(&sources, &Options::default()).unwrap()
+}
+
+// One line per top level, class and enum: `User { id: integer, email?: string? }`.
+fn describe(graph: &TypeGraph) -> String {
+  let mut lines: Vec<String> = graph
+    .top_levels
+    .iter()
+    .map(|top| format!("{} = {}", top.name, type_name(graph, &top.ty)))
+    .collect();
+  for class in &graph.classes {
+    let properties: Vec<String> = class
+      .properties
+      .iter()
+      .map(|p| {
+        let optional = if p.optional { "?" } else { "" };
+        format!("{}{}: {}", p.name, optional, type_name(graph, &p.ty))
+      })
+      .collect();
+    lines.push(format!("{} {{ {} }}", class.name, properties.join(", ")));
+  }
+  for e in &graph.enums {
+    lines.push(format!("enum {} {{ {} }}", e.name, e.values.join(", ")));
+  }
+  lines.join("\n")
+}
+
+fn type_name(graph: &TypeGraph, ty: &Type) -> String {
+  match ty {
+    Type::Any => "any".into(),
+    Type::Null => "null".into(),
+    Type::Bool => "bool".into(),
+    Type::Integer => "integer".into(),
+    Type::Double => "number".into(),
+    Type::String => "string".into(),
+    Type::Format { format } => format!("{:?}", format),
+    Type::Enum { id } => graph.enum_(*id).name.clone(),
+    Type::Array { items } => format!("[{}]", type_name(graph, items)),
+    Type::Map { values } => format!("{{{}}}", type_name(graph, values)),
+    Type::Class { id } => graph.class(*id).name.clone(),
+    Type::Union { members } => {
+      let members: Vec<String> = members.iter().map(|m| type_name(graph, m)).collect();
+      format!("({})", members.join(" | "))
+    }
+    Type::Nullable { inner } => format!("{}?", type_name(graph, inner)),
+  }
+}
+
+#[test]
+fn sample_is_inferred() {
+  let graph = graph_of(json!([{
+    "name": "user",
+    "samples": [r#"{
+      "id": 1, "name": "Ann", "score": 9.5, "admin": false, "manager": null,
+      "tags": ["a", "b"], "address": { "city": "Paris" }, "links": []
+    }"#],
+  }]));
+  assert_eq!(
+    describe(&graph),
+    "\
+User = User
+Address { city: string }
+User { id: integer, name: string, score: number, admin: bool, manager: null, tags: [string], \
+address: Address, links: [any] }"
+  );
+}
+
+#[test]
+fn samples_are_unified() {
+  let graph = graph_of(json!([{
+    "name": "user",
+    "samples": [
+      r#"{ "id": 1, "name": "Ann", "nickname": null, "ref": 7, "pets": [{ "name": "Rex" }] }"#,
+      r#"{ "id": 2, "name": "Bob", "nickname": "B", "ref": "x-1", "pets": [] }"#,
+      r#"{ "id": 3, "nickname": null, "ref": 9.5, "pets": [{ "name": "Tom", "age": 3 }] }"#,
+    ],
+  }]));
+  assert_eq!(
+    describe(&graph),
+    "\
+User = User
+Pet { name: string, age?: integer }
+User { id: integer, name?: string, nickname: string?, ref: (number | string), pets: [Pet] }"
+  );
+}
+
+#[test]
+fn samples_keep_their_stats() {
+  let graph = graph_of(json!([{
+    "name": "user",
+    "samples": [r#"{ "id": 1, "name": "Ann" }"#, r#"{ "id": 2 }"#],
+  }]));
+  let name = graph
+    .stats
+    .iter()
+    .find(|stats| stats.path == "$.name")
+    .unwrap();
+  assert_eq!((name.present, name.total), (1, 2));
+}
+
+fn write(dir: &Path, name: &str, contents: &str) {
+  let path = dir.join(name);
+  fs::create_dir_all(path.parent().unwrap()).unwrap();
+  fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn dir_files_are_sorted_and_filtered() {
+  let (_home, home) = cli::test_home();
+  let dir = home.join("samples");
+  for name in [
+    "b.json",
+    "a.json",
+    "nested/c.json",
+    "notes.txt",
+    ".draft.json",
+    ".git/d.json",
+  ] {
+    write(&dir, name, "{}");
+  }
+  write(&dir, "models.ts", "interface A {}");
+
+  let source: Source = serde_json::from_value(json!({ "name": "a", "dir": "samples" })).unwrap();
+  let files: Vec<_> = source
+    .files()
+    .unwrap()
+    .into_iter()
+    .map(|path| path.strip_prefix(&dir).unwrap().to_path_buf())
+    .collect();
+  assert_eq!(
+    files,
+    [
+      Path::new("a.json"),
+      Path::new("b.json"),
+      Path::new("nested/c.json")
+    ]
+  );
+
+  let source: Source =
+    serde_json::from_value(json!({ "name": "a", "kind": "typescript", "dir": "samples" })).unwrap();
+  assert_eq!(source.files().unwrap(), [dir.join("models.ts")]);
+}
+
+#[test]
+fn dir_samples_are_unified_with_inline_ones() {
+  let (_home, home) = cli::test_home();
+  write(
+    &home.join("users"),
+    "1.json",
+    r#"{ "id": 1, "name": "Ann" }"#,
+  );
+  write(&home.join("users"), "2.json", r#"{ "id": 2 }"#);
+
+  let graph = graph_of(json!([{
+    "name": "user",
+    "samples": [r#"{ "id": 3, "name": "Bob" }"#],
+    "dir": "users",
+  }]));
+  assert_eq!(
+    describe(&graph),
+    "User = User\nUser { id: integer, name?: string }"
+  );
+  let name = graph
+    .stats
+    .iter()
+    .find(|stats| stats.path == "$.name")
+    .unwrap();
+  assert_eq!((name.present, name.total), (2, 3));
+}
+
+#[test]
+fn missing_dir_is_an_error() {
+  let (_home, home) = cli::test_home();
+  let source: Source = serde_json::from_value(json!({ "name": "a", "dir": "missing" })).unwrap();
+  match source.files() {
+    Err(Error::Io { path, source }) => {
+      assert_eq!(path, home.join("missing"));
+      assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+    }
+    res => panic!("{:?}", res),
+  }
+}
+
+#[test]
+fn json_schema_is_lowered() {
+  let schema = json!({
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "title": "Order",
+    "type": "object",
+    "required": ["id", "status", "items"],
+    "properties": {
+      "id": { "type": "string", "format": "uuid" },
+      "status": { "enum": ["open", "shipped"] },
+      "note": { "type": ["string", "null"] },
+      "items": { "type": "array", "items": { "$ref": "#/$defs/item" } },
+      "meta": { "type": "object", "additionalProperties": { "type": "integer" } },
+    },
+    "$defs": {
+      "item": {
+        "type": "object",
+        "required": ["sku"],
+        "properties": {
+          "sku": { "type": "string" },
+          "quantity": { "type": "integer" },
+        },
+      },
+    },
+  });
+  let graph = graph_of(json!([{
+    "name": "order",
+    "kind": "jsonSchema",
+    "samples": [schema.to_string()],
+  }]));
+  assert_eq!(
+    describe(&graph),
+    "\
+Order = Order
+Order { id: Uuid, status: Status, note?: string?, items: [Item], meta?: {integer} }
+Item { sku: string, quantity?: integer }
+enum Status { open, shipped }"
+  );
+}
+
+// One source named `root` of this kind.
+fn lowered(kind: &str, text: &str) -> super::Result<TypeGraph> {
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "root",
+    "kind": kind,
+    "samples": [text],
+  }]))
+  .unwrap();
+  infer(&sources, &Options::default())
+}
+
+fn schema_graph(schema: Value) -> super::Result<TypeGraph> {
+  lowered("jsonSchema", &schema.to_string())
+}
+
+#[test]
+fn json_schema_all_of_is_merged() {
+  let graph = schema_graph(json!({
+    "$defs": {
+      "entity": {
+        "type": "object",
+        "required": ["id"],
+        "properties": { "id": { "type": "integer" } },
+      },
+      "node": {
+        "allOf": [
+          { "$ref": "#/$defs/entity" },
+          {
+            "properties": {
+              "children": { "type": "array", "items": { "$ref": "#/$defs/node" } },
+            },
+          },
+        ],
+      },
+    },
+  }))
+  .unwrap();
+  // the second member is a class of its own, which nothing refers to
+  assert_eq!(
+    describe(&graph),
+    "\
+Entity = Entity
+Node = Node
+Entity { id: integer }
+Node { id: integer, children?: [Node] }
+Node2 { children?: [Node] }"
+  );
+}
+
+#[test]
+fn json_schema_all_of_without_objects_is_not_a_class() {
+  let graph = schema_graph(json!({
+    "title": "Order",
+    "type": "object",
+    "properties": {
+      "name": { "allOf": [{ "type": "string" }, { "maxLength": 10 }] },
+      "names": {
+        "type": "array",
+        "items": { "type": "object", "properties": { "x": { "type": "integer" } } },
+      },
+    },
+  }))
+  .unwrap();
+  assert_eq!(
+    describe(&graph),
+    "Order = Order\nOrder { name?: string, names?: [Name] }\nName { x?: integer }"
+  );
+}
+
+#[test]
+fn json_schema_cycle_through_one_of_is_reported() {
+  let err = schema_graph(json!({
+    "$ref": "#/$defs/list",
+    "$defs": {
+      "list": {
+        "oneOf": [
+          { "type": "object", "properties": { "next": { "$ref": "#/$defs/list" } } },
+          { "type": "null" },
+        ],
+      },
+    },
+  }))
+  .unwrap_err();
+  assert_eq!(
+    err.to_string(),
+    "`root` schema#/$defs/list: reference cycle through `oneOf`, only objects can refer back to \
+themselves"
+  );
+}
+
+#[test]
+fn json_schema_ref_siblings_depend_on_the_draft() {
+  let schema = |draft: &str| {
+    json!({
+      "$schema": format!("https://json-schema.org/{}/schema", draft),
+      "title": "Order",
+      "type": "object",
+      "properties": {
+        "item": {
+          "$ref": "#/definitions/item",
+          "properties": { "note": { "type": "string" } },
+        },
+      },
+      "definitions": {
+        "item": { "type": "object", "properties": { "sku": { "type": "string" } } },
+      },
+    })
+  };
+  let graph = schema_graph(schema("draft/2020-12")).unwrap();
+  assert_eq!(
+    describe(&graph),
+    "\
+Order = Order
+Order { item?: Item }
+Item { sku?: string, note?: string }
+Item2 { sku?: string }"
+  );
+  let graph = schema_graph(schema("draft-07")).unwrap();
+  assert_eq!(
+    describe(&graph),
+    "Order = Order\nOrder { item?: Item }\nItem { sku?: string }"
+  );
+}
+
+#[test]
+fn objects_keyed_by_ids_are_maps() {
+  let graph = graph_of(json!([{
+    "name": "team",
+    "samples": [r#"{
+      "members": { "u123": { "name": "Ann" }, "u456": { "name": "Bob" } },
+      "lines": { "line1": "Main St", "line2": "Apt 4" }
+    }"#],
+  }]));
+  assert_eq!(
+    describe(&graph),
+    "\
+Team = Team
+Member { name: string }
+Lines { line1: string, line2: string }
+Team { members: {Member}, lines: Lines }"
+  );
+}
+
+#[test]
+fn objects_can_be_forced_to_maps_or_classes() {
+  let infer_with = |sample: &str, objects: Value| {
+    let sources: Vec<Source> =
+      serde_json::from_value(json!([{ "name": "root", "samples": [sample] }])).unwrap();
+    let options: Options = serde_json::from_value(json!({ "objects": objects })).unwrap();
+    describe(&infer(&sources, &options).unwrap())
+  };
+  assert_eq!(
+    infer_with(
+      r#"{ "a": { "u123": 1, "u456": 2 } }"#,
+      json!({ "$.a": "class" })
+    ),
+    "Root = Root\nA { u123: integer, u456: integer }\nRoot { a: A }"
+  );
+  assert_eq!(
+    infer_with(
+      r#"{ "a": [{ "b": { "name": "Ann", "title": "Dr" } }] }"#,
+      json!({ "$.a[*].b": "map" })
+    ),
+    "Root = Root\nAItem { b: {string} }\nRoot { a: [AItem] }"
+  );
+}
+
+#[test]
+fn classes_and_properties_can_be_renamed() {
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "root",
+    "samples": [r#"{ "users": [{ "2fa": true, "id": 1 }], "admin": { "2fa": false, "id": 2 } }"#],
+  }]))
+  .unwrap();
+  let options: Options = serde_json::from_value(json!({
+    "renames": {
+      "$.users[*]": "Account",
+      r#"$.users[*]["2fa"]"#: "two factor",
+      "$.missing": "Ignored",
+    },
+  }))
+  .unwrap();
+  let graph = infer(&sources, &options).unwrap();
+  assert_eq!(
+    describe(&graph),
+    "Root = Root\nAccount { 2fa: bool, id: integer }\nRoot { users: [Account], admin: Account }"
+  );
+  let renamed: Vec<_> = graph.classes[0]
+    .properties
+    .iter()
+    .map(|p| p.rename.as_deref())
+    .collect();
+  assert_eq!(renamed, [Some("two factor"), None]);
+}
+
+#[test]
+fn typescript_is_lowered() {
+  let source = r#"
+    type Id = string | number;
+    export interface Entity { id: Id }
+    export interface User extends Entity {
+      name?: string;
+      role: "admin" | "member";
+      friends: User[];
+      pair: [string, number];
+      extra: Record<string, boolean> | null;
+    }
+  "#;
+  let graph = graph_of(json!([{ "name": "models", "kind": "typescript", "samples": [source] }]));
+  assert_eq!(
+    describe(&graph),
+    "\
+Id = (string | number)
+Entity = Entity
+User = User
+Entity { id: (string | number) }
+User { id: (string | number), name?: string, role: Role, friends: [User], \
+pair: [(string | number)], extra: {bool}? }
+enum Role { admin, member }"
+  );
+  assert_eq!(graph.classes[1].path, "$.User");
+}
+
+#[test]
+fn typescript_interfaces_and_intersections_are_merged() {
+  let graph = lowered(
+    "typescript",
+    r#"
+      interface Base { id: number }
+      interface Base { created: Date }
+      type Tagged = { tags: { [key: string]: string } };
+      type Post = Base & Tagged & { title: string; draft: boolean | undefined; rating: 1 | 2.5 };
+    "#,
+  )
+  .unwrap();
+  assert_eq!(
+    describe(&graph),
+    "\
+Base = Base
+Tagged = Tagged
+Post = Post
+Base { id: number, created: DateTime }
+Tagged { tags: {string} }
+Post { id: number, created: DateTime, tags: {string}, title: string, draft?: bool, \
+rating: (integer | number) }
+Post2 { title: string, draft?: bool, rating: (integer | number) }"
+  );
+  // the object literal was merged into `Post`, nothing refers to it and generators skip it
+  let literal = graph
+    .classes
+    .iter()
+    .position(|c| c.name == "Post2")
+    .unwrap();
+  let properties = graph.classes.iter().flat_map(|c| &c.properties);
+  assert!(!properties
+    .map(|p| &p.ty)
+    .chain(graph.top_levels.iter().map(|t| &t.ty))
+    .any(|ty| ty.refers_to(literal)));
+}
+
+#[test]
+fn typescript_errors_point_at_the_declaration() {
+  let error = |source: &str| lowered("typescript", source).unwrap_err().to_string();
+  assert_eq!(
+    error("interface A {\n  b: Missing\n}"),
+    "`root` sample 1:2:6: unknown type `Missing`"
+  );
+  assert_eq!(
+    error("type List = List[] | null;"),
+    "`root` sample 1:1:1: `List` refers to itself outside of an object"
+  );
+  assert_eq!(
+    error("interface Box<T> { value: T }"),
+    "`root` sample 1:1:14: generic declarations aren't supported"
+  );
+  assert_eq!(
+    error("type A = string;\nfunction f() {}"),
+    "`root` sample 1:2:1: expected `interface` or `type`, found `function`"
+  );
+  assert_eq!(error("// nothing"), "`root` has no samples");
+}
+
+#[test]
+fn graphql_is_lowered() {
+  let source = r#"
+    scalar DateTime
+    interface Node { id: ID! }
+    type User implements Node { id: ID! name: String! joined: DateTime }
+    type Post implements Node { id: ID! title: String! author: User! }
+    union SearchResult = User | Post
+    type Query { search(text: String!): [SearchResult!]! node(id: ID!): Node }
+
+    query Search {
+      results: search(text: "a") {
+        __typename
+        ... on User { name joined }
+        ... on Post { title author { name } }
+      }
+      node(id: "1") { id }
+    }
+  "#;
+  let graph = graph_of(json!([{
+    "name": "api",
+    "kind": "graphql",
+    "scalars": { "DateTime": "date-time" },
+    "samples": [source],
+  }]));
+  assert_eq!(
+    describe(&graph),
+    "\
+Search = Search
+Author { name: string }
+Post { __typename: PostTypename, title: string, author: Author }
+User { __typename: UserTypename, name: string, joined: DateTime? }
+Node { id: string }
+Search { results: [(Post | User)], node: Node? }
+enum PostTypename { Post }
+enum UserTypename { User }"
+  );
+}
+
+#[test]
+fn graphql_fragments_enums_and_mutations_are_lowered() {
+  let graph = lowered(
+    "graphql",
+    r#"
+      enum Status { ACTIVE BANNED }
+      type User { id: ID! status: Status! scores: [Float] friends: [User!]! }
+      type Query { me: User }
+      type Mutation { ban(id: ID!): User! }
+
+      fragment Basics on User { id status }
+      query CurrentUser { me { ...Basics friends { ...Basics } scores } }
+      mutation BanUser($id: ID!) { ban(id: $id) { status } }
+    "#,
+  )
+  .unwrap();
+  assert_eq!(
+    describe(&graph),
+    "\
+CurrentUser = CurrentUser
+BanUser = BanUser
+Friend { id: string, status: Status }
+Me { id: string, status: Status, friends: [Friend], scores: [number?]? }
+CurrentUser { me: Me? }
+Ban { status: Status }
+BanUser { ban: Ban }
+enum Status { ACTIVE, BANNED }"
+  );
+}
+
+#[test]
+fn graphql_introspection_is_a_schema() {
+  let schema = json!({ "data": { "__schema": {
+    "queryType": { "name": "Query" },
+    "types": [
+      { "kind": "OBJECT", "name": "Query", "fields": [{
+        "name": "count",
+        "type": { "kind": "NON_NULL", "ofType": { "kind": "SCALAR", "name": "Int" } },
+      }] },
+      { "kind": "SCALAR", "name": "Int" },
+    ],
+  } } });
+  let graph = graph_of(json!([{
+    "name": "api",
+    "kind": "graphql",
+    "samples": [schema.to_string(), "{ count }"],
+  }]));
+  assert_eq!(describe(&graph), "Api = Api\nApi { count: integer }");
+}
+
+#[test]
+fn graphql_errors_point_at_the_selection() {
+  let error = |operation: &str| {
+    let schema = "type User { id: ID! friend: User } type Query { user: User }";
+    lowered("graphql", &format!("{}\n{}", schema, operation))
+      .unwrap_err()
+      .to_string()
+  };
+  assert_eq!(
+    error("{ user { name } }"),
+    "`root` sample 1:2:10: `User` has no field `name`"
+  );
+  assert_eq!(
+    error("{ user { friend } }"),
+    "`root` sample 1:2:10: `friend` is a `User` and needs a selection set"
+  );
+  assert_eq!(
+    error("{ user { id { x } } }"),
+    "`root` sample 1:2:10: `id` is a `ID` and can't have a selection set"
+  );
+  assert_eq!(
+    error("{ user { ...Missing } }"),
+    "`root` sample 1:2:10: unknown fragment `Missing`"
+  );
+  assert_eq!(
+    error("fragment F on User { friend { ...F } } { user { ...F } }"),
+    "`root` sample 1:2:31: fragment `F` spreads itself"
+  );
+  assert_eq!(
+    error("mutation { user { id } }"),
+    "`root` sample 1:2:1: the schema has no mutation type `Mutation`"
+  );
+}
+
+fn formatted(format: &str, text: &str) -> super::Result<TypeGraph> {
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "root",
+    "format": format,
+    "samples": [text],
+  }]))
+  .unwrap();
+  infer(&sources, &Options::default())
+}
+
+#[test]
+fn yaml_documents_are_samples() {
+  let graph = formatted(
+    "yaml",
+    "\
+id: 1
+name: Ann
+tags: [a, b]
+---
+defaults: &defaults
+  active: true
+id: 2
+settings:
+  <<: *defaults
+  theme: dark
+",
+  )
+  .unwrap();
+  assert_eq!(describe(&graph), "Root = Root\nDefaults { active: bool }\nSettings { theme: string, active: bool }\nRoot { id: integer, name?: string, tags?: [string], defaults?: Defaults, settings?: Settings }");
+  assert_eq!(
+    formatted("yaml", "a: [1, 2").unwrap_err().to_string(),
+    "`root` sample 1: did not find expected ',' or ']' at line 2 column 1, while parsing a flow \
+sequence at line 1 column 4"
+  );
+}
+
+#[test]
+fn toml_is_a_sample() {
+  let graph = formatted(
+    "toml",
+    r#"
+title = "Example"
+released = 2024-01-02T03:04:05Z
+ratio = 0.5
+
+[owner]
+name = "Ann"
+
+[[servers]]
+ip = "10.0.0.1"
+ports = [80, 443]
+
+[[servers]]
+ip = "10.0.0.2"
+"#,
+  )
+  .unwrap();
+  assert_eq!(describe(&graph), "Root = Root\nOwner { name: string }\nServer { ip: string, ports?: [integer] }\nRoot { title: string, released: DateTime, ratio: number, owner: Owner, servers: [Server] }");
+  let error = formatted("toml", "a = ").unwrap_err().to_string();
+  assert!(
+    error.starts_with("`root` sample 1: TOML parse error at line 1, column 5"),
+    "{}",
+    error
+  );
+}
+
+#[test]
+fn ndjson_lines_are_samples() {
+  let graph = formatted(
+    "ndjson",
+    "{ \"id\": 1 }\n\n{ \"id\": 2, \"name\": \"Ann\" }\n",
+  )
+  .unwrap();
+  assert_eq!(
+    describe(&graph),
+    "Root = Root\nRoot { id: integer, name?: string }"
+  );
+  // errors count the blank lines
+  assert_eq!(
+    formatted("ndjson", "{}\n\n{ \"id\": }")
+      .unwrap_err()
+      .to_string(),
+    "`root` sample 1:3: expected value at line 1 column 9"
+  );
+}
+
+#[test]
+fn dir_files_are_read_by_their_extension() {
+  let (_home, home) = cli::test_home();
+  let dir = home.join("mixed");
+  write(&dir, "a.yml", "id: 1\n---\nid: 2\n");
+  write(&dir, "b.jsonl", "{ \"id\": 3 }\n{ \"id\": 4 }\n");
+  write(&dir, "c.toml", "id = 5\nname = \"Ann\"\n");
+  write(&dir, "d.txt", "not a sample");
+
+  let graph = graph_of(json!([{ "name": "root", "dir": "mixed" }]));
+  assert_eq!(
+    describe(&graph),
+    "Root = Root\nRoot { id: integer, name?: string }"
+  );
+  let id = graph
+    .stats
+    .iter()
+    .find(|stats| stats.path == "$.id")
+    .unwrap();
+  assert_eq!(id.total, 5);
+}
+
+fn csv_graph(format: &str, csv: Value, text: &str) -> String {
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "row",
+    "format": format,
+    "csv": csv,
+    "samples": [text],
+  }]))
+  .unwrap();
+  describe(&infer(&sources, &Options::default()).unwrap())
+}
+
+#[test]
+fn csv_rows_are_samples() {
+  // empty cells are `null`, missing ones make their column optional
+  assert_eq!(
+    csv_graph(
+      "csv",
+      json!({ "header": true }),
+      "id,name,score,zip,name\n1,Ann,9.5,007,a\n2,,10,123,b,extra\n3,Cy\n"
+    ),
+    "Row = Row\nRow { id: integer, name: string?, score?: number, zip?: string, name2?: string, \
+column6?: string }"
+  );
+}
+
+#[test]
+fn csv_header_is_guessed() {
+  // a row of text above numbers names the columns
+  assert_eq!(
+    csv_graph("csv", json!({}), "id,admin\n1,true\n"),
+    "Row = Row\nRow { id: integer, admin: bool }"
+  );
+  // text above text can't be told apart from data
+  assert_eq!(
+    csv_graph("csv", json!({}), "name,city\nAnn,Paris\n"),
+    "Row = Row\nRow { column1: string, column2: string }"
+  );
+  assert_eq!(
+    csv_graph("csv", json!({ "header": true }), "name,city\nAnn,Paris\n"),
+    "Row = Row\nRow { name: string, city: string }"
+  );
+  // repeated names aren't a header
+  assert_eq!(
+    csv_graph("csv", json!({}), "a,a\n1,2\n"),
+    "Row = Row\nRow { column1: string, column2: string }"
+  );
+  assert_eq!(
+    csv_graph("csv", json!({ "header": false }), "id,admin\n1,true\n"),
+    "Row = Row\nRow { column1: string, column2: string }"
+  );
+}
+
+#[test]
+fn csv_delimiter_and_quote_can_be_set() {
+  assert_eq!(
+    csv_graph(
+      "csv",
+      json!({ "delimiter": ";", "quote": "'" }),
+      "name;note\n'Ann;B';1\n"
+    ),
+    "Row = Row\nRow { name: string, note: integer }"
+  );
+  assert_eq!(
+    csv_graph("csv", json!({ "quote": null }), "name,note\n\"Ann,1\n"),
+    "Row = Row\nRow { name: string, note: integer }"
+  );
+  assert_eq!(
+    csv_graph("tsv", json!({}), "id\tname\n1\tAnn, B\n"),
+    "Row = Row\nRow { id: integer, name: string }"
+  );
+  let sources: Vec<Source> = serde_json::from_value(json!([{
+    "name": "row",
+    "format": "csv",
+    "csv": { "delimiter": "→" },
+    "samples": ["a"],
+  }]))
+  .unwrap();
+  assert_eq!(
+    infer(&sources, &Options::default())
+      .unwrap_err()
+      .to_string(),
+    "`row` sample 1: the delimiter must be an ASCII character"
+  );
+}
+
+#[test]
+fn tsv_files_are_read_by_their_extension() {
+  let (_home, home) = cli::test_home();
+  write(&home.join("rows"), "a.tsv", "id\tname\n1\tAnn, B\n");
+  write(&home.join("rows"), "b.csv", "id,name\n2,\n");
+  assert_eq!(
+    describe(&graph_of(json!([{ "name": "row", "dir": "rows" }]))),
+    "Row = Row\nRow { id: integer, name: string? }"
+  );
+}
@@ -0,0 +1,773 @@
+//! TypeScript input. A subset of TypeScript declarations is lowered into the same `TypeGraph` that
+//! sample inference builds: interfaces (with `extends`), type aliases, unions, intersections,
+//! literal types, arrays, tuples, optional properties, index signatures and `Record`.
+//!
+//! Every interface and alias becomes a top level. Generics, functions, `enum`s and conditional
+//! types aren't supported.
+
+use super::{child_path, class_name, read_file, singular, Error, Result, Source};
+use super::{ClassId, Format, Merge, Property, TopLevel, Type, TypeGraph};
+use std::collections::HashMap;
+
+// Line and column, from 1.
+type Pos = (usize, usize);
+type ParseResult<T> = std::result::Result<T, (Pos, String)>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Ident(String),
+  Str(String),
+  Num(String),
+  Punct(char),
+}
+
+#[derive(Debug, Clone)]
+enum Ty {
+  Ref {
+    name: String,
+    args: Vec<Ty>,
+    pos: Pos,
+  },
+  Str(String),
+  Num(String),
+  Array(Box<Ty>),
+  Tuple(Vec<Ty>),
+  Object(Vec<Member>),
+  Union(Vec<Ty>),
+  Intersection(Vec<Ty>),
+}
+
+#[derive(Debug, Clone)]
+enum Member {
+  Property {
+    name: String,
+    optional: bool,
+    ty: Ty,
+  },
+  /// `[key: string]: T`
+  Index(Ty),
+}
+
+enum Body {
+  Interface {
+    extends: Vec<Ty>,
+    members: Vec<Member>,
+  },
+  Alias(Ty),
+}
+
+struct Decl {
+  name: String,
+  body: Body,
+  /// Index into the labels of the parsed texts, for errors.
+  text: usize,
+  pos: Pos,
+}
+
+impl Decl {
+  // Declarations that become a class, reserved before anything is lowered so they keep their
+  // name and can refer to each other.
+  fn is_class(&self) -> bool {
+    match &self.body {
+      Body::Interface { extends, members } => !extends.is_empty() || has_properties(members),
+      Body::Alias(Ty::Object(members)) => has_properties(members),
+      Body::Alias(_) => false,
+    }
+  }
+}
+
+fn has_properties(members: &[Member]) -> bool {
+  members.iter().any(|m| matches!(m, Member::Property { .. }))
+}
+
+/// Lowers the declarations of `source` into `graph`, one top level per declaration.
+pub fn lower(source: &Source, graph: &mut TypeGraph) -> Result<()> {
+  let mut texts: Vec<(String, String)> = source
+    .samples
+    .iter()
+    .enumerate()
+    .map(|(i, text)| (format!("sample {}", i + 1), text.clone()))
+    .collect();
+  for path in source.files()? {
+    texts.push((path.display().to_string(), read_file(&path)?));
+  }
+
+  let mut lowerer = Lowerer {
+    source,
+    graph,
+    labels: texts.iter().map(|(label, _)| label.clone()).collect(),
+    decls: vec![],
+    by_name: HashMap::new(),
+    done: HashMap::new(),
+    pending: vec![],
+    merges: vec![],
+  };
+  for (text, (_, content)) in texts.iter().enumerate() {
+    let decls = tokenize(content)
+      .and_then(|tokens| Parser { tokens, at: 0 }.declarations())
+      .map_err(|(pos, message)| lowerer.error(text, pos, message))?;
+    for (name, body, pos) in decls {
+      lowerer.declare(name, body, text, pos);
+    }
+  }
+  if lowerer.decls.is_empty() {
+    return Err(Error::NoSamples(source.name.clone()));
+  }
+
+  for i in 0..lowerer.decls.len() {
+    if lowerer.decls[i].is_class() {
+      let path = lowerer.decl_path(i);
+      let id = lowerer.graph.reserve_class(&lowerer.decls[i].name, &path);
+      lowerer.done.insert(i, Type::Class { id });
+    }
+  }
+  for i in 0..lowerer.decls.len() {
+    let ty = lowerer.lower_decl(i)?;
+    if let Type::Class { id } = ty {
+      if lowerer.decls[i].is_class() {
+        lowerer.fill_class(i, id)?;
+      }
+    }
+    let name = class_name(&lowerer.decls[i].name);
+    lowerer.graph.top_levels.push(TopLevel { name, ty });
+  }
+  lowerer.graph.merge(&lowerer.merges);
+  Ok(())
+}
+
+struct Lowerer<'a> {
+  source: &'a Source,
+  graph: &'a mut TypeGraph,
+  labels: Vec<String>,
+  decls: Vec<Decl>,
+  by_name: HashMap<String, usize>,
+  done: HashMap<usize, Type>,
+  /// Aliases being lowered, referring back to one of them is a cycle.
+  pending: Vec<usize>,
+  /// Interfaces and intersections, filled in once the classes they extend are complete.
+  merges: Vec<Merge>,
+}
+
+impl Lowerer<'_> {
+  fn error(&self, text: usize, (line, column): Pos, message: String) -> Error {
+    Error::Parse {
+      name: self.source.name.clone(),
+      location: format!("{}:{}:{}", self.labels[text], line, column),
+      message,
+    }
+  }
+
+  // Interfaces declared twice are merged, as TypeScript does.
+  fn declare(&mut self, name: String, body: Body, text: usize, pos: Pos) {
+    if let Some(&i) = self.by_name.get(&name) {
+      if let (
+        Body::Interface { extends, members },
+        Body::Interface {
+          extends: more_extends,
+          members: more_members,
+        },
+      ) = (&mut self.decls[i].body, &body)
+      {
+        extends.extend(more_extends.iter().cloned());
+        members.extend(more_members.iter().cloned());
+        return;
+      }
+    }
+    self.by_name.insert(name.clone(), self.decls.len());
+    self.decls.push(Decl {
+      name,
+      body,
+      text,
+      pos,
+    });
+  }
+
+  /// Each declaration is its own root, so renames can tell apart their properties.
+  fn decl_path(&self, i: usize) -> String {
+    child_path("$", &self.decls[i].name)
+  }
+
+  fn lower_decl(&mut self, i: usize) -> Result<Type> {
+    if let Some(ty) = self.done.get(&i) {
+      return Ok(ty.clone());
+    }
+    let decl = &self.decls[i];
+    if self.pending.contains(&i) {
+      let message = format!("`{}` refers to itself outside of an object", decl.name);
+      return Err(self.error(decl.text, decl.pos, message));
+    }
+    let (name, text) = (decl.name.clone(), decl.text);
+    let ty = match &decl.body {
+      // only index signatures
+      Body::Interface { members, .. } => Ty::Object(members.clone()),
+      Body::Alias(ty) => ty.clone(),
+    };
+    let path = self.decl_path(i);
+    self.pending.push(i);
+    let ty = self.lower(&ty, text, &name, &path);
+    self.pending.pop();
+    let ty = ty?;
+    self.done.insert(i, ty.clone());
+    Ok(ty)
+  }
+
+  fn fill_class(&mut self, i: usize, id: ClassId) -> Result<()> {
+    let decl = &self.decls[i];
+    let (text, pos) = (decl.text, decl.pos);
+    let (extends, members) = match &decl.body {
+      Body::Interface { extends, members } => (extends.clone(), members.clone()),
+      Body::Alias(Ty::Object(members)) => (vec![], members.clone()),
+      Body::Alias(_) => unreachable!(),
+    };
+    let path = self.decl_path(i);
+    let mut parents = vec![];
+    for parent in &extends {
+      match self.lower(parent, text, "", &path)? {
+        Type::Class { id } => parents.push(id),
+        _ => return Err(self.error(text, pos, "can only extend interfaces".into())),
+      }
+    }
+    let own = self.lower_properties(&members, text, &path)?;
+    self.merges.push(Merge {
+      id,
+      members: parents,
+      own,
+    });
+    Ok(())
+  }
+
+  // `hint` names the classes and enums found in `ty`, `path` is where its values are in the data.
+  fn lower(&mut self, ty: &Ty, text: usize, hint: &str, path: &str) -> Result<Type> {
+    Ok(match ty {
+      Ty::Ref { name, args, pos } => return self.lower_ref(name, args, *pos, text, hint, path),
+      Ty::Str(value) => Type::Enum {
+        id: self.graph.add_enum(hint, path, vec![value.clone()]),
+      },
+      Ty::Num(n) if n.parse::<i64>().is_ok() => Type::Integer,
+      Ty::Num(_) => Type::Double,
+      Ty::Array(items) => {
+        Type::array(self.lower(items, text, &singular(hint), &format!("{}[*]", path))?)
+      }
+      Ty::Tuple(items) => {
+        let mut members = vec![];
+        for item in items {
+          members.push(self.lower(item, text, &singular(hint), &format!("{}[*]", path))?);
+        }
+        Type::array(if members.is_empty() {
+          Type::Any
+        } else {
+          Type::union(members)
+        })
+      }
+      Ty::Object(members) if has_properties(members) => {
+        let id = self.graph.reserve_class(hint, path);
+        let properties = self.lower_properties(members, text, path)?;
+        self.graph.classes[id].properties = properties;
+        Type::Class { id }
+      }
+      Ty::Object(members) => {
+        let values = match members.first() {
+          Some(Member::Index(values)) => {
+            self.lower(values, text, &singular(hint), &format!("{}.*", path))?
+          }
+          _ => Type::Any,
+        };
+        Type::Map {
+          values: Box::new(values),
+        }
+      }
+      Ty::Union(members) => {
+        // string literals form one enum
+        let values: Vec<String> = members
+          .iter()
+          .filter_map(|m| match m {
+            Ty::Str(value) => Some(value.clone()),
+            _ => None,
+          })
+          .collect();
+        let mut types = vec![];
+        if !values.is_empty() {
+          types.push(Type::Enum {
+            id: self.graph.add_enum(hint, path, values),
+          });
+        }
+        for member in members.iter().filter(|m| !matches!(m, Ty::Str(_))) {
+          types.push(self.lower(member, text, hint, path)?);
+        }
+        Type::union(types)
+      }
+      Ty::Intersection(members) => {
+        // reserved first so the merged class gets the name
+        let id = self.graph.reserve_class(hint, path);
+        let mut types = vec![];
+        for member in members {
+          types.push(self.lower(member, text, hint, path)?);
+        }
+        let classes: Vec<_> = types
+          .iter()
+          .filter_map(|t| match t {
+            Type::Class { id } => Some(*id),
+            _ => None,
+          })
+          .collect();
+        if classes.len() == types.len() {
+          self.merges.push(Merge {
+            id,
+            members: classes,
+            own: vec![],
+          });
+          Type::Class { id }
+        } else {
+          // the reserved class stays unused, generators only emit reachable classes
+          types
+            .into_iter()
+            .find(|t| *t != Type::Any)
+            .unwrap_or(Type::Any)
+        }
+      }
+    })
+  }
+
+  fn lower_ref(
+    &mut self,
+    name: &str,
+    args: &[Ty],
+    pos: Pos,
+    text: usize,
+    hint: &str,
+    path: &str,
+  ) -> Result<Type> {
+    let ty = match (name, args) {
+      ("string", []) => Type::String,
+      ("number", []) => Type::Double,
+      ("bigint", []) => Type::Integer,
+      ("boolean" | "true" | "false", []) => Type::Bool,
+      ("null" | "undefined", []) => Type::Null,
+      ("any" | "unknown" | "object" | "Object", []) => Type::Any,
+      ("Date", []) => Type::Format {
+        format: Format::DateTime,
+      },
+      ("Array" | "ReadonlyArray", [items]) => {
+        Type::array(self.lower(items, text, &singular(hint), &format!("{}[*]", path))?)
+      }
+      ("Record", [_, values]) => Type::Map {
+        values: Box::new(self.lower(values, text, &singular(hint), &format!("{}.*", path))?),
+      },
+      ("Readonly", [inner]) => self.lower(inner, text, hint, path)?,
+      (name, []) if self.by_name.contains_key(name) => {
+        return self.lower_decl(self.by_name[name]);
+      }
+      (name, []) => return Err(self.error(text, pos, format!("unknown type `{}`", name))),
+      (name, _) => {
+        let message = format!("unsupported type arguments for `{}`", name);
+        return Err(self.error(text, pos, message));
+      }
+    };
+    Ok(ty)
+  }
+
+  fn lower_properties(
+    &mut self,
+    members: &[Member],
+    text: usize,
+    path: &str,
+  ) -> Result<Vec<Property>> {
+    let mut properties = vec![];
+    for member in members {
+      // index signatures next to properties can't be typed, the properties win
+      let (name, optional, ty) = match member {
+        Member::Property { name, optional, ty } => (name, optional, ty),
+        Member::Index(_) => continue,
+      };
+      // `a: T | undefined` is as optional as `a?: T`
+      let (ty, undefined) = without_undefined(ty);
+      let ty = self.lower(&ty, text, name, &child_path(path, name))?;
+      properties.push(Property {
+        name: name.clone(),
+        ty,
+        optional: *optional || undefined,
+        rename: None,
+      });
+    }
+    Ok(properties)
+  }
+}
+
+fn without_undefined(ty: &Ty) -> (Ty, bool) {
+  let is_undefined = |t: &Ty| matches!(t, Ty::Ref { name, .. } if name == "undefined");
+  match ty {
+    Ty::Union(members) if members.iter().any(is_undefined) => {
+      let mut rest: Vec<Ty> = members
+        .iter()
+        .filter(|m| !is_undefined(m))
+        .cloned()
+        .collect();
+      let ty = match rest.len() {
+        1 => rest.remove(0),
+        _ => Ty::Union(rest),
+      };
+      (ty, true)
+    }
+    ty => (ty.clone(), false),
+  }
+}
+
+fn tokenize(text: &str) -> ParseResult<Vec<(Token, Pos)>> {
+  let chars: Vec<char> = text.chars().collect();
+  let mut tokens = vec![];
+  let (mut i, mut line, mut column) = (0, 1, 1);
+  // moves past `n` characters, keeping track of the position
+  let advance = |i: &mut usize, line: &mut usize, column: &mut usize, n: usize| {
+    for _ in 0..n {
+      if chars.get(*i) == Some(&'\n') {
+        *line += 1;
+        *column = 1;
+      } else {
+        *column += 1;
+      }
+      *i += 1;
+    }
+  };
+
+  while i < chars.len() {
+    let c = chars[i];
+    let pos = (line, column);
+    let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+    let len = if c.is_whitespace() {
+      1
+    } else if rest == "//" {
+      chars[i..]
+        .iter()
+        .position(|&c| c == '\n')
+        .unwrap_or(chars.len() - i)
+    } else if rest == "/*" {
+      match (i + 2..chars.len().saturating_sub(1)).find(|&j| chars[j] == '*' && chars[j + 1] == '/')
+      {
+        Some(end) => end + 2 - i,
+        None => return Err((pos, "unterminated comment".into())),
+      }
+    } else if c.is_alphabetic() || c == '_' || c == '$' {
+      let len = chars[i..]
+        .iter()
+        .position(|&c| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .unwrap_or(chars.len() - i);
+      tokens.push((Token::Ident(chars[i..i + len].iter().collect()), pos));
+      len
+    } else if c.is_ascii_digit() {
+      let len = chars[i..]
+        .iter()
+        .position(|&c| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+        .unwrap_or(chars.len() - i);
+      let number: String = chars[i..i + len].iter().filter(|&&c| c != '_').collect();
+      tokens.push((Token::Num(number), pos));
+      len
+    } else if c == '"' || c == '\'' || c == '`' {
+      let (value, len) = string(&chars[i..]).map_err(|message| (pos, message))?;
+      tokens.push((Token::Str(value), pos));
+      len
+    } else if "{}()[]<>;,:?|&=.-".contains(c) {
+      tokens.push((Token::Punct(c), pos));
+      1
+    } else {
+      return Err((pos, format!("unexpected `{}`", c)));
+    };
+    advance(&mut i, &mut line, &mut column, len);
+  }
+  Ok(tokens)
+}
+
+// A string literal at the start of `chars`, and how many characters it takes.
+fn string(chars: &[char]) -> std::result::Result<(String, usize), String> {
+  let quote = chars[0];
+  let mut value = String::new();
+  let mut i = 1;
+  while let Some(&c) = chars.get(i) {
+    match c {
+      c if c == quote => return Ok((value, i + 1)),
+      '\\' => {
+        let escaped = chars.get(i + 1).copied().unwrap_or('\\');
+        i += 2;
+        match escaped {
+          'n' => value.push('\n'),
+          't' => value.push('\t'),
+          'r' => value.push('\r'),
+          'u' => {
+            let hex: String = chars.iter().skip(i).take(4).collect();
+            let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+            value.push(c.ok_or("invalid `\\u` escape")?);
+            i += 4;
+          }
+          c => value.push(c),
+        }
+        continue;
+      }
+      '$' if quote == '`' && chars.get(i + 1) == Some(&'{') => {
+        return Err("template literal types aren't supported".into());
+      }
+      '\n' if quote != '`' => break,
+      c => value.push(c),
+    }
+    i += 1;
+  }
+  Err("unterminated string".into())
+}
+
+struct Parser {
+  tokens: Vec<(Token, Pos)>,
+  at: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.at).map(|(token, _)| token)
+  }
+
+  fn pos(&self) -> Pos {
+    match self.tokens.get(self.at).or(self.tokens.last()) {
+      Some((_, pos)) => *pos,
+      None => (1, 1),
+    }
+  }
+
+  fn next(&mut self) -> Option<Token> {
+    let token = self.peek().cloned();
+    self.at += 1;
+    token
+  }
+
+  fn is(&self, c: char) -> bool {
+    self.peek() == Some(&Token::Punct(c))
+  }
+
+  fn is_ident(&self, name: &str) -> bool {
+    matches!(self.peek(), Some(Token::Ident(ident)) if ident == name)
+  }
+
+  // Consumes `c` if it's next.
+  fn eat(&mut self, c: char) -> bool {
+    let is = self.is(c);
+    if is {
+      self.at += 1;
+    }
+    is
+  }
+
+  fn expect(&mut self, c: char) -> ParseResult<()> {
+    if self.eat(c) {
+      Ok(())
+    } else {
+      Err(self.unexpected(&format!("`{}`", c)))
+    }
+  }
+
+  fn unexpected(&self, expected: &str) -> (Pos, String) {
+    let found = match self.peek() {
+      Some(Token::Ident(s)) | Some(Token::Num(s)) => format!("`{}`", s),
+      Some(Token::Str(s)) => format!("{:?}", s),
+      Some(Token::Punct(c)) => format!("`{}`", c),
+      None => "the end".into(),
+    };
+    (
+      self.pos(),
+      format!("expected {}, found {}", expected, found),
+    )
+  }
+
+  fn ident(&mut self) -> ParseResult<String> {
+    match self.peek() {
+      Some(Token::Ident(name)) => {
+        let name = name.clone();
+        self.at += 1;
+        Ok(name)
+      }
+      _ => Err(self.unexpected("a name")),
+    }
+  }
+
+  fn declarations(mut self) -> ParseResult<Vec<(String, Body, Pos)>> {
+    let mut decls = vec![];
+    while let Some(token) = self.peek().cloned() {
+      let pos = self.pos();
+      match token {
+        Token::Punct(';') => self.at += 1,
+        Token::Ident(word) if ["export", "declare", "default"].contains(&word.as_str()) => {
+          self.at += 1
+        }
+        // `import { A } from "./a";` declares nothing, the files of a directory are read anyway
+        Token::Ident(word) if word == "import" => {
+          while !matches!(self.next(), Some(Token::Str(_)) | None) {}
+        }
+        Token::Ident(word) if word == "interface" => {
+          self.at += 1;
+          let name = self.ident()?;
+          self.no_type_parameters()?;
+          let mut extends = vec![];
+          if self.is_ident("extends") {
+            self.at += 1;
+            loop {
+              extends.push(self.postfix()?);
+              if !self.eat(',') {
+                break;
+              }
+            }
+          }
+          self.expect('{')?;
+          let members = self.members()?;
+          decls.push((name, Body::Interface { extends, members }, pos));
+        }
+        Token::Ident(word) if word == "type" => {
+          self.at += 1;
+          let name = self.ident()?;
+          self.no_type_parameters()?;
+          self.expect('=')?;
+          let ty = self.ty()?;
+          decls.push((name, Body::Alias(ty), pos));
+        }
+        _ => return Err(self.unexpected("`interface` or `type`")),
+      }
+    }
+    Ok(decls)
+  }
+
+  fn no_type_parameters(&self) -> ParseResult<()> {
+    if self.is('<') {
+      return Err((self.pos(), "generic declarations aren't supported".into()));
+    }
+    Ok(())
+  }
+
+  // The members of an object type, after its `{`.
+  fn members(&mut self) -> ParseResult<Vec<Member>> {
+    let mut members = vec![];
+    loop {
+      while self.eat(';') || self.eat(',') {}
+      if self.eat('}') {
+        return Ok(members);
+      }
+      if self.is_ident("readonly")
+        && !matches!(
+          self.tokens.get(self.at + 1),
+          Some((Token::Punct(':' | '?'), _))
+        )
+      {
+        self.at += 1;
+      }
+      if self.eat('[') {
+        self.ident()?;
+        self.expect(':')?;
+        self.ty()?;
+        self.expect(']')?;
+        self.expect(':')?;
+        members.push(Member::Index(self.ty()?));
+        continue;
+      }
+      let name = match self.next() {
+        Some(Token::Ident(name) | Token::Str(name) | Token::Num(name)) => name,
+        _ => {
+          self.at -= 1;
+          return Err(self.unexpected("a property"));
+        }
+      };
+      let optional = self.eat('?');
+      if self.is('(') || self.is('<') {
+        return Err((self.pos(), "methods aren't supported".into()));
+      }
+      self.expect(':')?;
+      let ty = self.ty()?;
+      members.push(Member::Property { name, optional, ty });
+    }
+  }
+
+  fn ty(&mut self) -> ParseResult<Ty> {
+    self.eat('|');
+    let mut members = vec![self.intersection()?];
+    while self.eat('|') {
+      members.push(self.intersection()?);
+    }
+    Ok(match members.len() {
+      1 => members.remove(0),
+      _ => Ty::Union(members),
+    })
+  }
+
+  fn intersection(&mut self) -> ParseResult<Ty> {
+    self.eat('&');
+    let mut members = vec![self.postfix()?];
+    while self.eat('&') {
+      members.push(self.postfix()?);
+    }
+    Ok(match members.len() {
+      1 => members.remove(0),
+      _ => Ty::Intersection(members),
+    })
+  }
+
+  // `T[]`
+  fn postfix(&mut self) -> ParseResult<Ty> {
+    let mut ty = self.primary()?;
+    while self.is('[') {
+      self.at += 1;
+      if !self.eat(']') {
+        return Err((self.pos(), "indexed access types aren't supported".into()));
+      }
+      ty = Ty::Array(Box::new(ty));
+    }
+    Ok(ty)
+  }
+
+  fn primary(&mut self) -> ParseResult<Ty> {
+    let pos = self.pos();
+    match self.next() {
+      Some(Token::Punct('(')) => {
+        let ty = self.ty()?;
+        self.expect(')')?;
+        if self.is('=') {
+          return Err((pos, "function types aren't supported".into()));
+        }
+        Ok(ty)
+      }
+      Some(Token::Punct('{')) => Ok(Ty::Object(self.members()?)),
+      Some(Token::Punct('[')) => {
+        let mut items = vec![];
+        while !self.eat(']') {
+          items.push(self.ty()?);
+          if !self.eat(',') {
+            self.expect(']')?;
+            break;
+          }
+        }
+        Ok(Ty::Tuple(items))
+      }
+      Some(Token::Str(value)) => Ok(Ty::Str(value)),
+      Some(Token::Num(n)) => Ok(Ty::Num(n)),
+      Some(Token::Punct('-')) => match self.next() {
+        Some(Token::Num(n)) => Ok(Ty::Num(format!("-{}", n))),
+        _ => Err((pos, "expected a number after `-`".into())),
+      },
+      Some(Token::Ident(word)) if ["typeof", "keyof", "infer", "new"].contains(&word.as_str()) => {
+        Err((pos, format!("`{}` types aren't supported", word)))
+      }
+      Some(Token::Ident(mut name)) => {
+        // `Api.User` is looked up as `User`
+        while self.eat('.') {
+          name = self.ident()?;
+        }
+        let mut args = vec![];
+        if self.eat('<') {
+          loop {
+            args.push(self.ty()?);
+            if !self.eat(',') {
+              break;
+            }
+          }
+          self.expect('>')?;
+        }
+        Ok(Ty::Ref { name, args, pos })
+      }
+      _ => {
+        self.at -= 1;
+        Err(self.unexpected("a type"))
+      }
+    }
+  }
+}
@@ -1,8 +1,12 @@
 use crate::{
-  app::window,
-  conf::{self, AppConf},
+  app::{apply, bundle},
+  cli,
+  conf::{self, profile, AppConf, Theme, UpdatePolicy},
   utils,
 };
+use log::{error, info};
+use serde_json::{json, Value};
+use std::sync::Mutex;
 use tauri::{
   AppHandle, CustomMenuItem, Manager, Menu, MenuItem, Submenu, SystemTray, SystemTrayEvent,
   SystemTrayMenu, SystemTrayMenuItem, WindowMenuEvent,
@@ -44,8 +48,8 @@ pub fn init() -> Menu {
   let theme_light = CustomMenuItem::new("theme_light".to_string(), "Light");
   let theme_dark = CustomMenuItem::new("theme_dark".to_string(), "Dark");
   let theme_system = CustomMenuItem::new("theme_system".to_string(), "System");
-  let is_dark = app_conf.clone().theme_check("dark");
-  let is_system = app_conf.clone().theme_check("system");
+  let is_dark = app_conf.theme == Theme::Dark;
+  let is_system = app_conf.theme == Theme::System;
 
   let update_prompt = CustomMenuItem::new("update_prompt".to_string(), "Prompt");
   let update_silent = CustomMenuItem::new("update_silent".to_string(), "Silent");
@@ -67,13 +71,41 @@ pub fn init() -> Menu {
     titlebar
   };
 
-  let system_tray = CustomMenuItem::new("system_tray".to_string(), "System Tray");
+  // a change wouldn't outlast the session, `AppConf::write` keeps the value on disk
+  let system_tray = if cli::overrides().contains_key("tray") {
+    CustomMenuItem::new("system_tray".to_string(), "System Tray (set by --no-tray)").disabled()
+  } else {
+    CustomMenuItem::new("system_tray".to_string(), "System Tray")
+  };
   let system_tray_menu = if app_conf.tray {
     system_tray.selected()
   } else {
     system_tray
   };
 
+  let mut profiles_menu = Menu::new();
+  let mut slots = PROFILE_SLOTS.lock().unwrap();
+  *slots = profile::Profile::list().into_iter().map(Some).collect();
+  slots.extend(std::iter::repeat(None).take(SPARE_PROFILE_SLOTS));
+  for (i, name) in slots.iter().enumerate() {
+    let item = match name {
+      Some(name) if *name == app_conf.profile => {
+        CustomMenuItem::new(profile_id(i), name).selected()
+      }
+      Some(name) => CustomMenuItem::new(profile_id(i), name),
+      None => CustomMenuItem::new(profile_id(i), EMPTY_SLOT).disabled(),
+    };
+    profiles_menu = profiles_menu.add_item(item);
+  }
+  drop(slots);
+  let profiles_menu =
+    profiles_menu
+      .add_native_item(MenuItem::Separator)
+      .add_item(CustomMenuItem::new(
+        "go_profiles".to_string(),
+        "Open Profiles Folder",
+      ));
+
   let auto_update = app_conf.get_auto_update();
   let preferences_menu = Submenu::new(
     "Preferences",
@@ -111,22 +143,23 @@ pub fn init() -> Menu {
       Submenu::new(
         "Auto Update",
         Menu::new()
-          .add_item(if auto_update == "prompt" {
+          .add_item(if auto_update == UpdatePolicy::Prompt {
             update_prompt.selected()
           } else {
             update_prompt
           })
-          .add_item(if auto_update == "silent" {
+          .add_item(if auto_update == UpdatePolicy::Silent {
             update_silent.selected()
           } else {
             update_silent
-          }), // .add_item(if auto_update == "disable" {
+          }), // .add_item(if auto_update == UpdatePolicy::Disable {
               //     update_disable.selected()
               // } else {
               //     update_disable
               // })
       )
       .into(),
+      Submenu::new("Profiles", profiles_menu).into(),
       MenuItem::Separator.into(),
       popup_search_menu.into(),
       MenuItem::Separator.into(),
@@ -136,6 +169,8 @@ pub fn init() -> Menu {
       CustomMenuItem::new("restart".to_string(), "Restart QuickType")
         .accelerator("CmdOrCtrl+Shift+R")
         .into(),
+      CustomMenuItem::new("export_settings".to_string(), "Export Settings...").into(),
+      CustomMenuItem::new("import_settings".to_string(), "Import Settings...").into(),
       CustomMenuItem::new("clear_conf".to_string(), "Clear Config").into(),
       MenuItem::Separator.into(),
     ]),
@@ -202,13 +237,51 @@ pub fn init() -> Menu {
     .add_submenu(help_menu)
 }
 
+const PROFILE_ID_PREFIX: &str = "profile_";
+// Items can't be added to a menu once it is built, so the Profiles submenu keeps a few empty slots
+// for profiles created while the app runs.
+const SPARE_PROFILE_SLOTS: usize = 5;
+const EMPTY_SLOT: &str = "-";
+
+// The profile shown in each slot of the Profiles submenu.
+static PROFILE_SLOTS: Mutex<Vec<Option<String>>> = Mutex::new(Vec::new());
+
+fn profile_id(slot: usize) -> String {
+  format!("{}{}", PROFILE_ID_PREFIX, slot)
+}
+
+/// Shows the saved profiles in the Profiles submenu, with `active` checked.
+pub fn refresh_profiles(app: &AppHandle, active: &str) {
+  let names = profile::Profile::list();
+  let mut slots = PROFILE_SLOTS.lock().unwrap();
+  if names.len() > slots.len() {
+    info!(
+      "profile_menu: {} of {} profiles shown until restart",
+      slots.len(),
+      names.len()
+    );
+  }
+  for (i, slot) in slots.iter_mut().enumerate() {
+    *slot = names.get(i).cloned();
+    let name = slot.as_deref();
+    for win in app.windows().values() {
+      if let Some(item) = win.menu_handle().try_get_item(&profile_id(i)) {
+        item
+          .set_title(name.unwrap_or(EMPTY_SLOT))
+          .and_then(|_| item.set_enabled(name.is_some()))
+          .and_then(|_| item.set_selected(name == Some(active)))
+          .unwrap_or_else(|err| error!("profile_menu: {}", err));
+      }
+    }
+  }
+}
+
 // --- Menu Event
 pub fn menu_handler(event: WindowMenuEvent<tauri::Wry>) {
   let win = Some(event.window()).unwrap();
   let app = win.app_handle();
   let script_path = utils::script_path().to_string_lossy().to_string();
   let menu_id = event.menu_item_id();
-  let menu_handle = win.menu_handle();
 
   match menu_id {
     // App
@@ -227,96 +300,66 @@ pub fn menu_handler(event: WindowMenuEvent<tauri::Wry>) {
     "restart" => tauri::api::process::restart(&app.env()),
     "inject_script" => open(&app, script_path),
     "go_conf" => utils::open_file(utils::app_root()),
+    "go_profiles" => {
+      let dir = profile::Profile::dir();
+      std::fs::create_dir_all(&dir).unwrap();
+      utils::open_file(dir);
+    }
+    id if id.starts_with(PROFILE_ID_PREFIX) => {
+      let slot = id[PROFILE_ID_PREFIX.len()..].parse::<usize>().ok();
+      let name = slot.and_then(|i| PROFILE_SLOTS.lock().unwrap().get(i).cloned().flatten());
+      let name = match name {
+        Some(v) => v,
+        None => return,
+      };
+      match profile::switch(&name) {
+        Ok(app_conf) => apply::save(&app, app_conf),
+        Err(err) => {
+          tauri::api::dialog::message(app.get_window("core").as_ref(), "Profiles", err.to_string())
+        }
+      }
+    }
+    "export_settings" => bundle::export_dialog(&app),
+    "import_settings" => bundle::import_dialog(&app),
     "clear_conf" => utils::clear_conf(&app),
     "popup_search" => {
       let app_conf = AppConf::read();
       let popup_search = !app_conf.popup_search;
-      menu_handle
-        .get_item(menu_id)
-        .set_selected(popup_search)
-        .unwrap();
-      app_conf
-        .amend(serde_json::json!({ "popup_search": popup_search }))
-        .write();
-      window::cmd::window_reload(app.clone(), "core");
-      window::cmd::window_reload(app, "tray");
+      amend(&app, app_conf, json!({ "popup_search": popup_search }));
     }
     "hide_dock_icon" => {
-      AppConf::read()
-        .amend(serde_json::json!({ "hide_dock_icon": true }))
-        .write()
-        .restart(app);
+      amend(&app, AppConf::read(), json!({ "hide_dock_icon": true }));
     }
     "titlebar" => {
       let app_conf = AppConf::read();
-      app_conf
-        .clone()
-        .amend(serde_json::json!({ "titlebar": !app_conf.titlebar }))
-        .write()
-        .restart(app);
+      let titlebar = !app_conf.titlebar;
+      amend(&app, app_conf, json!({ "titlebar": titlebar }));
     }
-    "system_tray" => {
+    "system_tray" if !cli::overrides().contains_key("tray") => {
       let app_conf = AppConf::read();
-      app_conf
-        .clone()
-        .amend(serde_json::json!({ "tray": !app_conf.tray }))
-        .write()
-        .restart(app);
+      let tray = !app_conf.tray;
+      amend(&app, app_conf, json!({ "tray": tray }));
     }
     "theme_light" | "theme_dark" | "theme_system" => {
       let theme = match menu_id {
-        "theme_dark" => "dark",
-        "theme_system" => "system",
-        _ => "light",
+        "theme_dark" => Theme::Dark,
+        "theme_system" => Theme::System,
+        _ => Theme::Light,
       };
-      AppConf::read()
-        .amend(serde_json::json!({ "theme": theme }))
-        .write()
-        .restart(app);
+      amend(&app, AppConf::read(), json!({ "theme": theme }));
     }
     "update_prompt" | "update_silent" | "update_disable" => {
-      // for id in ["update_prompt", "update_silent", "update_disable"] {
-      for id in ["update_prompt", "update_silent"] {
-        menu_handle.get_item(id).set_selected(false).unwrap();
-      }
       let auto_update = match menu_id {
-        "update_silent" => {
-          menu_handle
-            .get_item("update_silent")
-            .set_selected(true)
-            .unwrap();
-          "silent"
-        }
-        "update_disable" => {
-          menu_handle
-            .get_item("update_disable")
-            .set_selected(true)
-            .unwrap();
-          "disable"
-        }
-        _ => {
-          menu_handle
-            .get_item("update_prompt")
-            .set_selected(true)
-            .unwrap();
-          "prompt"
-        }
+        "update_silent" => UpdatePolicy::Silent,
+        "update_disable" => UpdatePolicy::Disable,
+        _ => UpdatePolicy::Prompt,
       };
-      AppConf::read()
-        .amend(serde_json::json!({ "auto_update": auto_update }))
-        .write();
+      amend(&app, AppConf::read(), json!({ "auto_update": auto_update }));
     }
     "stay_on_top" => {
       let app_conf = AppConf::read();
       let stay_on_top = !app_conf.stay_on_top;
-      menu_handle
-        .get_item(menu_id)
-        .set_selected(stay_on_top)
-        .unwrap();
-      win.set_always_on_top(stay_on_top).unwrap();
-      app_conf
-        .amend(serde_json::json!({ "stay_on_top": stay_on_top }))
-        .write();
+      amend(&app, app_conf, json!({ "stay_on_top": stay_on_top }));
     }
     // View
     "zoom_0" => win.eval("window.__zoom0 && window.__zoom0()").unwrap(),
@@ -354,6 +397,8 @@ pub fn menu_handler(event: WindowMenuEvent<tauri::Wry>) {
 }
 
 // --- SystemTray Menu
+pub const TRAY_ID: &str = "main";
+
 pub fn tray_menu() -> SystemTray {
   if cfg!(target_os = "macos") {
     let mut tray_menu = SystemTrayMenu::new();
@@ -372,13 +417,13 @@ pub fn tray_menu() -> SystemTray {
         .add_item(CustomMenuItem::new("show_core".to_string(), "Show Window"));
     }
 
-    SystemTray::new().with_menu(
+    SystemTray::new().with_id(TRAY_ID).with_menu(
       tray_menu
         .add_native_item(SystemTrayMenuItem::Separator)
         .add_item(CustomMenuItem::new("quit".to_string(), "Quit")),
     )
   } else {
-    SystemTray::new().with_menu(
+    SystemTray::new().with_id(TRAY_ID).with_menu(
       SystemTrayMenu::new()
         .add_item(CustomMenuItem::new("show_core".to_string(), "Show Window"))
         .add_native_item(SystemTrayMenuItem::Separator)
@@ -416,18 +461,12 @@ pub fn tray_handler(handle: &AppHandle, event: SystemTrayEvent) {
     SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
       "restart" => tauri::api::process::restart(&handle.env()),
       "show_dock_icon" => {
-        AppConf::read()
-          .amend(serde_json::json!({ "hide_dock_icon": false }))
-          .write()
-          .restart(app);
+        amend(&app, AppConf::read(), json!({ "hide_dock_icon": false }));
       }
       "hide_dock_icon" => {
         let app_conf = AppConf::read();
         if !app_conf.hide_dock_icon {
-          app_conf
-            .amend(serde_json::json!({ "hide_dock_icon": true }))
-            .write()
-            .restart(app);
+          amend(&app, app_conf, json!({ "hide_dock_icon": true }));
         }
       }
       "show_core" => {
@@ -447,6 +486,15 @@ pub fn tray_handler(handle: &AppHandle, event: SystemTrayEvent) {
   }
 }
 
+// Saves settings changed from a menu. A rejected change is logged and the settings stay as they
+// are.
+fn amend(app: &AppHandle, app_conf: AppConf, patch: Value) {
+  match app_conf.try_amend(patch) {
+    Ok(app_conf) => apply::save(app, app_conf),
+    Err(err) => error!("menu_amend: {}", err),
+  }
+}
+
 pub fn open(app: &AppHandle, path: String) {
   tauri::api::shell::open(&app.shell_scope(), path, None).unwrap();
 }
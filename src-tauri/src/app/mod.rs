@@ -1,6 +1,11 @@
-pub mod setup;
-pub mod window;
-pub mod menu;
+pub mod apply;
+pub mod bundle;
 pub mod cmd;
+pub mod codegen;
 pub mod fs_extra;
+pub mod infer;
+pub mod menu;
+pub mod setup;
 pub mod update;
+pub mod watch;
+pub mod window;
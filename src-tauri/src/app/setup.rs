@@ -1,14 +1,35 @@
-use crate::{app::window, conf::AppConf, utils};
+use crate::{
+  app::{apply, watch, window},
+  cli,
+  conf::{AppConf, ConfError, UpdatePolicy},
+  utils,
+};
 use log::{error, info};
+use std::sync::Mutex;
 use tauri::{
   utils::config::WindowUrl, 
   window::WindowBuilder, App, 
-  GlobalShortcutManager, Manager
+  Manager
 };
-use wry::application::accelerator::Accelerator;
 
 pub fn init(app: &mut App) -> std::result::Result<(), Box<dyn std::error::Error>> {
   info!("stepup");
+  let msg = match AppConf::load() {
+    Err(err @ ConfError::Fields(_)) => Some(format!(
+      "Some settings in {} are invalid and were replaced by their defaults for this session:\n\n{}",
+      AppConf::file_path().display(),
+      err
+    )),
+    Err(err @ ConfError::UnsupportedVersion(_)) => Some(format!(
+      "{}: {}.\n\nIts settings are used as far as this version understands them, but changes are not saved.",
+      AppConf::file_path().display(),
+      err
+    )),
+    _ => None,
+  };
+  if let Some(msg) = msg {
+    tauri::api::dialog::message(None::<&tauri::Window>, "QuickType Config", msg);
+  }
   let app_conf = AppConf::read();
   let url = app_conf.main_origin.to_string();
   let theme = AppConf::theme_mode();
@@ -19,36 +40,28 @@ pub fn init(app: &mut App) -> std::result::Result<(), Box<dyn std::error::Error>
     window::tray_window(&handle);
   });
 
-  if let Some(v) = app_conf.clone().global_shortcut {
-    info!("global_shortcut: `{}`", v);
-    match v.parse::<Accelerator>() {
-      Ok(_) => {
-        info!("global_shortcut_register");
-        let handle = app.app_handle();
-        let mut shortcut = app.global_shortcut_manager();
-        shortcut
-          .register(&v, move || {
-            if let Some(w) = handle.get_window("core") {
-              if w.is_visible().unwrap() {
-                w.hide().unwrap();
-              } else {
-                w.show().unwrap();
-                w.set_focus().unwrap();
-              }
-            }
-          })
-          .unwrap_or_else(|err| {
-            error!("global_shortcut_register_error: {}", err);
-          });
-      }
-      Err(err) => {
-        error!("global_shortcut_parse_error: {}", err);
-      }
-    }
+  if let Some(v) = &app_conf.global_shortcut {
+    apply::register_shortcut(&app.app_handle(), v);
   } else {
     info!("global_shortcut_unregister");
   };
 
+  // the fs allowlist only covers ~/.quickType
+  let scope = app.fs_scope();
+  if cli::args().home.is_some() {
+    scope
+      .allow_directory(utils::app_root(), true)
+      .unwrap_or_else(|err| error!("fs_scope: {}", err));
+  }
+  if cli::args().config.is_some() {
+    scope
+      .allow_file(AppConf::file_path())
+      .unwrap_or_else(|err| error!("fs_scope: {}", err));
+  }
+
+  app.manage(apply::AppliedConf(Mutex::new(app_conf.clone())));
+  watch::conf(app.app_handle());
+
   let app_conf2 = app_conf.clone();
   if app_conf.hide_dock_icon {
     #[cfg(target_os = "macos")]
@@ -96,10 +109,10 @@ pub fn init(app: &mut App) -> std::result::Result<(), Box<dyn std::error::Error>
 
   // auto_update
   let auto_update = app_conf.get_auto_update();
-  if auto_update != "disable" {
+  if auto_update != UpdatePolicy::Disable {
     info!("run_check_update");
     let app = app.handle();
-    utils::run_check_update(app, auto_update == "silent", None);
+    utils::run_check_update(app, auto_update == UpdatePolicy::Silent, None);
   }
 
   Ok(())
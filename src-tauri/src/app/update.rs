@@ -4,7 +4,7 @@ use crate::{
   };
   use log::{error, info};
   use regex::Regex;
-  use std::{collections::HashMap, fs, path::PathBuf, vec};
+  use std::{collections::HashMap, path::PathBuf, vec};
   use tauri::{command};
   use walkdir::WalkDir;
   
@@ -21,13 +21,13 @@ pub struct FileMetadata {
 pub fn get_download_list(pathname: &str) -> (Vec<serde_json::Value>, PathBuf) {
   info!("get_download_list: {}", pathname);
   let download_path = utils::app_root().join(PathBuf::from(pathname));
-  let content = fs::read_to_string(&download_path).unwrap_or_else(|err| {
+  let list = utils::read_json::<Vec<serde_json::Value>>(&download_path).unwrap_or_else(|err| {
     error!("download_list: {}", err);
-    fs::write(&download_path, "[]").unwrap();
-    "[]".to_string()
-  });
-  let list = serde_json::from_str::<Vec<serde_json::Value>>(&content).unwrap_or_else(|err| {
-    error!("download_list_parse: {}", err);
+    if !utils::exists(&download_path) {
+      if let Err(err) = utils::write_json(&download_path, &[] as &[serde_json::Value]) {
+        error!("download_list: {}", err);
+      }
+    }
     vec![]
   });
 
@@ -93,5 +93,7 @@ pub fn download_list(pathname: &str, dir: &str, filename: Option<String>, id: Op
     a1.cmp(&b1).reverse()
   });
 
-  fs::write(data.1, serde_json::to_string_pretty(&list).unwrap()).unwrap();
+  utils::write_json(&data.1, &list).unwrap_or_else(|err| {
+    error!("download_list_write: {}", err);
+  });
 }
\ No newline at end of file
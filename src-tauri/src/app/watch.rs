@@ -0,0 +1,157 @@
+use crate::{
+  app::apply,
+  conf::{AppConf, ConfError},
+};
+use log::{error, info};
+use notify::{RecursiveMode, Watcher};
+use std::{ffi::OsStr, sync::mpsc, thread, time::Duration};
+use tauri::{api::notification::Notification, AppHandle};
+
+// Editors (and `utils::write_atomic`) touch the file several times per save.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches `quick.conf.json` and applies edits made outside the app while it is running.
+pub fn conf(app: AppHandle) {
+  thread::spawn(move || {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(tx) {
+      Ok(v) => v,
+      Err(err) => {
+        error!("conf_watch: {}", err);
+        return;
+      }
+    };
+    // the file is replaced by a rename on every write, so watch its directory instead
+    let path = AppConf::file_path();
+    let (dir, name) = match (path.parent(), path.file_name()) {
+      (Some(dir), Some(name)) => (dir, name),
+      _ => return,
+    };
+    if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+      error!("conf_watch: {}", err);
+      return;
+    }
+    info!("conf_watch: {}", path.display());
+
+    while let Ok(res) = rx.recv() {
+      let event: notify::Event = match res {
+        Ok(v) => v,
+        Err(err) => {
+          error!("conf_watch: {}", err);
+          continue;
+        }
+      };
+      if !is_edit(&event, name) {
+        continue;
+      }
+      debounce(&rx, DEBOUNCE);
+
+      match AppConf::load() {
+        Ok(conf) => {
+          let restart = apply::apply(&app, conf);
+          if !restart.is_empty() {
+            notify(
+              &app,
+              format!(
+                "Changes to {} take effect after restarting QuickType.",
+                restart.join(", ")
+              ),
+            );
+          }
+        }
+        // removed, or caught between two steps of a save
+        Err(ConfError::Io(err)) => error!("conf_reload: {}", err),
+        // an invalid edit is left in the file to be fixed, the app keeps its current settings
+        Err(err) => {
+          error!("conf_reload: {}", err);
+          notify(
+            &app,
+            format!("Changes to {} were not applied:\n{}", path.display(), err),
+          );
+        }
+      }
+    }
+  });
+}
+
+// Reads of the file, and events for its neighbours in the same directory, are not edits.
+fn is_edit(event: &notify::Event, name: &OsStr) -> bool {
+  !event.kind.is_access() && event.paths.iter().any(|p| p.file_name() == Some(name))
+}
+
+// Waits for the save to settle and drops the events it queued meanwhile, so it reloads once.
+fn debounce<T>(rx: &mpsc::Receiver<T>, delay: Duration) {
+  thread::sleep(delay);
+  while rx.try_recv().is_ok() {}
+}
+
+fn notify(app: &AppHandle, body: String) {
+  Notification::new(&app.config().tauri.bundle.identifier)
+    .title("QuickType Config")
+    .body(body)
+    .show()
+    .unwrap_or_else(|err| error!("conf_reload_notify: {}", err));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use notify::{
+    event::{AccessKind, CreateKind, ModifyKind, RenameMode},
+    Event, EventKind,
+  };
+
+  fn event(kind: EventKind, path: &str) -> Event {
+    Event::new(kind).add_path(path.into())
+  }
+
+  #[test]
+  fn only_edits_of_the_conf_are_reloaded() {
+    let name = OsStr::new("quick.conf.json");
+    let modify = EventKind::Modify(ModifyKind::Any);
+
+    assert!(is_edit(&event(modify, "/app/quick.conf.json"), name));
+    assert!(is_edit(
+      &event(EventKind::Create(CreateKind::File), "/app/quick.conf.json"),
+      name
+    ));
+    // `write_atomic` renames a temporary file over the conf
+    assert!(is_edit(
+      &event(
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+        "/app/quick.conf.json.1.0.tmp"
+      )
+      .add_path("/app/quick.conf.json".into()),
+      name
+    ));
+    assert!(!is_edit(&event(modify, "/app/quick.conf.json.bak"), name));
+    assert!(!is_edit(
+      &event(EventKind::Access(AccessKind::Any), "/app/quick.conf.json"),
+      name
+    ));
+  }
+
+  #[test]
+  fn a_burst_of_events_is_reloaded_once() {
+    let (tx, rx) = mpsc::channel();
+    for i in 0..3 {
+      tx.send(i).unwrap();
+    }
+    assert_eq!(rx.recv().unwrap(), 0);
+
+    let handle = {
+      let tx = tx.clone();
+      thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx.send(3).unwrap();
+      })
+    };
+    debounce(&rx, Duration::from_millis(50));
+    handle.join().unwrap();
+    assert!(rx.try_recv().is_err());
+
+    // an edit after the save settled is a new reload
+    tx.send(4).unwrap();
+    assert_eq!(rx.recv().unwrap(), 4);
+  }
+}
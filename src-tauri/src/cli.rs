@@ -0,0 +1,127 @@
+use crate::conf::profile::Profile;
+use serde_json::{Map, Value};
+use std::{path::PathBuf, sync::OnceLock};
+
+pub const HOME_ENV: &str = "QUICKTYPE_HOME";
+
+const USAGE: &str = "Usage: quick_type [OPTIONS]
+
+Options:
+  --config <FILE>    Read and write settings from FILE instead of quick.conf.json
+  --origin <URL>     Load URL in the main and tray windows
+  --profile <NAME>   Use the origins, user agents and user script of a saved profile
+  --no-tray          Don't show the system tray icon
+  -h, --help         Print this help
+
+Environment:
+  QUICKTYPE_HOME     Data directory, defaults to ~/.quickType
+
+Options only apply to this session, they are never written to the config.";
+
+/// Settings for the current session, taken from the command line and the environment.
+#[derive(Debug, Default)]
+pub struct Args {
+  pub home: Option<PathBuf>,
+  pub config: Option<PathBuf>,
+  /// `AppConf` fields set for this session only, they are never written back to disk.
+  pub overrides: Map<String, Value>,
+}
+
+static ARGS: OnceLock<Args> = OnceLock::new();
+static OVERRIDES: OnceLock<Map<String, Value>> = OnceLock::new();
+
+/// Parses the process arguments, must run before anything reads the config.
+pub fn init() {
+  let home = std::env::var_os(HOME_ENV)
+    .filter(|v| !v.is_empty())
+    .map(PathBuf::from);
+  let parsed = parse(home, std::env::args().skip(1)).unwrap_or_else(|msg| exit(&msg));
+  ARGS.set(parsed).unwrap();
+
+  // reading a profile needs `app_root`, so it waits until the data directory is known
+  let mut overrides = args().overrides.clone();
+  if let Some(name) = args().overrides.get("profile").and_then(Value::as_str) {
+    let profile = Profile::read(name).unwrap_or_else(|err| exit(&format!("--profile: {}", err)));
+    if let Ok(Value::Object(fields)) = serde_json::to_value(profile) {
+      fields.into_iter().for_each(|(key, value)| {
+        // an explicit --origin wins over the profile
+        overrides.entry(key).or_insert(value);
+      });
+    }
+  }
+  OVERRIDES.set(overrides).unwrap();
+}
+
+fn exit(msg: &str) -> ! {
+  eprintln!("{}\n\n{}", msg, USAGE);
+  std::process::exit(2);
+}
+
+pub fn args() -> &'static Args {
+  ARGS.get_or_init(|| Args {
+    // tests never touch the real data directory
+    #[cfg(test)]
+    home: Some(std::env::temp_dir().join(format!("quick_type-home-{}", std::process::id()))),
+    ..Args::default()
+  })
+}
+
+/// All `AppConf` fields overridden for this session, including those of `--profile`.
+pub fn overrides() -> &'static Map<String, Value> {
+  OVERRIDES.get().unwrap_or(&args().overrides)
+}
+
+/// Empties the scratch data directory tests run against. Tests that read or write it hold the
+/// returned guard until they are done, so they never run at the same time.
+#[cfg(test)]
+pub fn test_home() -> (std::sync::MutexGuard<'static, ()>, PathBuf) {
+  static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+  let guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+  let home = crate::utils::app_root();
+  std::fs::remove_dir_all(&home).ok();
+  std::fs::create_dir_all(&home).unwrap();
+  (guard, home)
+}
+
+fn parse(home: Option<PathBuf>, mut argv: impl Iterator<Item = String>) -> Result<Args, String> {
+  let mut args = Args {
+    home,
+    ..Args::default()
+  };
+  while let Some(arg) = argv.next() {
+    let (flag, inline) = match arg.split_once('=') {
+      Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+      _ => (arg, None),
+    };
+    let mut value = || {
+      inline
+        .clone()
+        .or_else(|| argv.next())
+        .ok_or_else(|| format!("{} needs a value", flag))
+    };
+    match flag.as_str() {
+      "--config" => args.config = Some(PathBuf::from(value()?)),
+      "--origin" => {
+        let origin = Value::from(value()?);
+        args.overrides.insert("main_origin".into(), origin.clone());
+        args.overrides.insert("tray_origin".into(), origin);
+      }
+      "--profile" => {
+        args
+          .overrides
+          .insert("profile".into(), Value::from(value()?));
+      }
+      "--no-tray" => {
+        args.overrides.insert("tray".into(), Value::Bool(false));
+      }
+      "-h" | "--help" => {
+        println!("{}", USAGE);
+        std::process::exit(0);
+      }
+      // added by macOS when launched from Finder
+      v if v.starts_with("-psn_") => {}
+      v => return Err(format!("unknown option `{}`", v)),
+    }
+  }
+  Ok(args)
+}
@@ -0,0 +1,144 @@
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+
+/// A single rejected setting.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum FieldError {
+  UnknownKey {
+    key: String,
+  },
+  /// Managed by the app, the settings form can't change it.
+  ReadOnly {
+    key: String,
+  },
+  WrongType {
+    key: String,
+    value: Value,
+    expected: &'static str,
+  },
+  InvalidValue {
+    key: String,
+    value: Value,
+    allowed: &'static [&'static str],
+  },
+  OutOfRange {
+    key: String,
+    value: f64,
+    min: f64,
+    max: f64,
+  },
+  InvalidAccelerator {
+    key: String,
+    value: String,
+    message: String,
+  },
+}
+
+impl FieldError {
+  pub fn key(&self) -> &str {
+    match self {
+      Self::UnknownKey { key }
+      | Self::ReadOnly { key }
+      | Self::WrongType { key, .. }
+      | Self::InvalidValue { key, .. }
+      | Self::OutOfRange { key, .. }
+      | Self::InvalidAccelerator { key, .. } => key,
+    }
+  }
+}
+
+impl fmt::Display for FieldError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::UnknownKey { key } => write!(f, "`{}`: unknown setting", key),
+      Self::ReadOnly { key } => write!(f, "`{}`: can't be changed", key),
+      Self::WrongType {
+        key,
+        value,
+        expected,
+      } => write!(f, "`{}`: expected {}, found {}", key, expected, value),
+      Self::InvalidValue {
+        key,
+        value,
+        allowed,
+      } => write!(
+        f,
+        "`{}`: invalid value {}, expected one of: {}",
+        key,
+        value,
+        allowed.join(", ")
+      ),
+      Self::OutOfRange {
+        key,
+        value,
+        min,
+        max,
+      } => write!(
+        f,
+        "`{}`: {} is out of range, expected {} to {}",
+        key, value, min, max
+      ),
+      Self::InvalidAccelerator {
+        key,
+        value,
+        message,
+      } => write!(f, "`{}`: invalid shortcut {:?}, {}", key, value, message),
+    }
+  }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfError {
+  #[error(transparent)]
+  Io(#[from] std::io::Error),
+  #[error(transparent)]
+  Json(#[from] serde_json::Error),
+  #[error(transparent)]
+  Other(#[from] anyhow::Error),
+  #[error("config version {0} is newer than this version of QuickType supports")]
+  UnsupportedVersion(u64),
+  #[error("profile `{0}` not found")]
+  ProfileNotFound(String),
+  #[error("profile `{0}` already exists")]
+  ProfileExists(String),
+  #[error("invalid profile name `{0}`, use letters, digits, `-` and `_`")]
+  InvalidProfileName(String),
+  #[error("the default profile can't be renamed or removed")]
+  DefaultProfile,
+  #[error("profile `{0}` is active, switch to another profile first")]
+  ProfileActive(String),
+  #[error("profiles can't be changed while `--profile` is set")]
+  ProfileOverridden,
+  #[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))]
+  Fields(Vec<FieldError>),
+}
+
+impl ConfError {
+  /// Keys of the rejected settings, empty unless this is `ConfError::Fields`.
+  pub fn keys(&self) -> Vec<&str> {
+    match self {
+      Self::Fields(errors) => errors.iter().map(FieldError::key).collect(),
+      _ => vec![],
+    }
+  }
+}
+
+// `{ "message": "...", "fields": [{ "key": "...", "reason": "...", ... }] }`, so the settings
+// form can point at the offending inputs.
+impl Serialize for ConfError {
+  fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let fields: &[FieldError] = match self {
+      Self::Fields(errors) => errors,
+      _ => &[],
+    };
+    let mut state = serializer.serialize_struct("ConfError", 2)?;
+    state.serialize_field("message", &self.to_string())?;
+    state.serialize_field("fields", fields)?;
+    state.end()
+  }
+}
@@ -0,0 +1,39 @@
+use log::info;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Schema version written by this build. Bump it together with a new entry in `MIGRATIONS`.
+pub const CONF_VERSION: u64 = 1;
+
+type Migration = fn(&mut Map<String, Value>);
+
+// Entry `i` upgrades a config from version `i` to version `i + 1`.
+const MIGRATIONS: [(&str, Migration); CONF_VERSION as usize] = [("stamp_version", v0_to_v1)];
+
+/// Version of a raw config, configs written before versioning was introduced are `0`.
+pub fn version_of(conf: &Map<String, Value>) -> u64 {
+  conf.get("version").and_then(Value::as_u64).unwrap_or(0)
+}
+
+/// Upgrades `conf` step by step to `CONF_VERSION`. Keys the migrations don't know about are left
+/// untouched. Returns the version the config had before migrating.
+pub fn upgrade(conf: &mut Map<String, Value>) -> u64 {
+  let from = version_of(conf);
+  for (version, (name, migration)) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+    migration(conf);
+    conf.insert("version".into(), Value::from(version as u64 + 1));
+    info!("conf_migrate: v{} -> v{} ({})", version, version + 1, name);
+  }
+  from
+}
+
+/// Copy kept next to the config before a migration rewrites it, e.g. `quick.conf.json.v0.bak`.
+pub fn backup_path(path: &Path, version: u64) -> PathBuf {
+  let mut name = path.file_name().unwrap_or_default().to_os_string();
+  name.push(format!(".v{}.bak", version));
+  path.with_file_name(name)
+}
+
+// v0: the unversioned config shipped up to v0.1.21. Its fields are all still valid, missing ones
+// are filled in from defaults when deserializing, so only the version needs stamping.
+fn v0_to_v1(_conf: &mut Map<String, Value>) {}
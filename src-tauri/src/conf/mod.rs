@@ -0,0 +1,624 @@
+pub mod error;
+pub mod migrate;
+pub mod profile;
+pub mod schema;
+#[cfg(test)]
+mod tests;
+
+use log::{error, info};
+use serde_json::{Map, Value};
+use std::{
+  collections::BTreeMap,
+  path::{Path, PathBuf},
+};
+use wry::application::accelerator::Accelerator;
+
+#[cfg(target_os = "macos")]
+use tauri::TitleBarStyle;
+
+use crate::{
+  cli,
+  utils::{self, app_root},
+};
+pub use error::{ConfError, FieldError};
+
+pub const UPDATE_LOG_URL: &str = "https://github.com/jxo-me/QuickType/blob/main/UPDATE_LOG.md";
+
+pub const APP_CONF_PATH: &str = "quick.conf.json";
+pub const APP_URL: &str = "https://app.quicktype.io";
+pub const UA_MOBILE: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 16_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.0 Mobile/15E148 Safari/604.1";
+
+// Collects the `///` lines among a field's attributes.
+macro_rules! field_docs {
+  ([$($doc:literal)*]) => { &[$($doc),*] };
+  ([$($doc:literal)*] [doc = $line:literal] $($rest:tt)*) => {
+    field_docs!([$($doc)* $line] $($rest)*)
+  };
+  ([$($doc:literal)*] [$($attr:tt)*] $($rest:tt)*) => { field_docs!([$($doc)*] $($rest)*) };
+}
+
+// The key a field is serialized under.
+macro_rules! field_key {
+  ($field:ident) => { stringify!($field) };
+  ($field:ident [serde(rename = $key:literal)] $($rest:tt)*) => { $key };
+  ($field:ident [$($attr:tt)*] $($rest:tt)*) => { field_key!($field $($rest)*) };
+}
+
+macro_rules! pub_struct {
+  ($name:ident {$($(#[$($attr:tt)*])* $field:ident: $t:ty,)*}) => {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+    #[serde(default)]
+    pub struct $name {
+      $($(#[$($attr)*])* pub $field: $t),*
+    }
+
+    impl $name {
+      /// Key, doc comment lines and type schema of every field, in declaration order.
+      pub fn fields() -> Vec<(&'static str, &'static [&'static str], Value)> {
+        vec![$((
+          field_key!($field $([$($attr)*])*),
+          field_docs!([] $([$($attr)*])*),
+          <$t as schema::ConfSchema>::schema(),
+        )),*]
+      }
+    }
+  }
+}
+
+// Settings that only take one of a fixed set of values. Reading is lenient about case and
+// surrounding whitespace, so values written by hand or by older versions (`"Dark"`) still parse.
+macro_rules! conf_enum {
+  ($name:ident {$($variant:ident => $value:literal,)*}) => {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum $name {
+      $($variant),*
+    }
+
+    impl $name {
+      pub const ALLOWED: &'static [&'static str] = &[$($value),*];
+
+      pub fn as_str(&self) -> &'static str {
+        match self {
+          $(Self::$variant => $value),*
+        }
+      }
+    }
+
+    impl std::str::FromStr for $name {
+      type Err = ();
+
+      fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+          $($value => Ok(Self::$variant),)*
+          _ => Err(()),
+        }
+      }
+    }
+
+    impl schema::ConfSchema for $name {
+      fn schema() -> Value {
+        serde_json::json!({ "type": "string", "enum": Self::ALLOWED })
+      }
+    }
+
+    impl serde::Serialize for $name {
+      fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+      }
+    }
+
+    impl<'de> serde::Deserialize<'de> for $name {
+      fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(|_| {
+          serde::de::Error::invalid_value(
+            serde::de::Unexpected::Str(&s),
+            &Self::ALLOWED.join(", ").as_str(),
+          )
+        })
+      }
+    }
+  };
+}
+
+conf_enum!(Theme {
+  Light => "light",
+  Dark => "dark",
+  System => "system",
+});
+
+conf_enum!(UpdatePolicy {
+  Prompt => "prompt",
+  Silent => "silent",
+  Disable => "disable",
+});
+
+impl Theme {
+  /// Resolves `Theme::System` against the current OS appearance.
+  pub fn window_theme(self) -> tauri::Theme {
+    match self {
+      Theme::System => match dark_light::detect() {
+        // Dark mode
+        dark_light::Mode::Dark => tauri::Theme::Dark,
+        // Light mode
+        dark_light::Mode::Light => tauri::Theme::Light,
+        // Unspecified
+        dark_light::Mode::Default => tauri::Theme::Light,
+      },
+      Theme::Dark => tauri::Theme::Dark,
+      Theme::Light => tauri::Theme::Light,
+    }
+  }
+}
+
+// `///` comments become the descriptions in `quick.conf.schema.json`, see `schema::generate`.
+pub_struct!(AppConf {
+  /// JSON Schema of this file, lets editors complete and check it.
+  #[serde(rename = "$schema")]
+  schema: String,
+  /// Config format version, upgraded automatically. Don't edit.
+  version: u64,
+  /// macOS: show the native titlebar.
+  titlebar: bool,
+  /// macOS: hide the Dock icon, leaving only the tray icon.
+  hide_dock_icon: bool,
+  /// Window theme on macOS and Windows.
+  theme: Theme,
+  /// What to do when a new version is available.
+  auto_update: UpdatePolicy,
+  /// Keep the main window above other windows.
+  stay_on_top: bool,
+  /// Restore the size and position of windows on launch.
+  save_window_state: bool,
+  /// Shortcut that shows or hides the main window, e.g. `CmdOrCtrl+Shift+O`.
+  global_shortcut: Option<String>,
+  /// URL of the hosted quicktype app, used where no origin is set.
+  default_origin: String,
+  /// Voice used to read text aloud, as named by the OS. Empty for the system default.
+  speech_lang: String,
+  /// Active profile, the name of a file in `profiles/`.
+  profile: String,
+
+  // Main Window
+  /// Ask what closing the main window should do on the next close.
+  isinit: bool,
+  /// Open a popup with the selected text when searching.
+  popup_search: bool,
+  /// Quit when the main window is closed, instead of minimizing it.
+  main_close: bool,
+  /// Open the dashboard instead of `main_origin` in the main window.
+  main_dashboard: bool,
+  /// URL loaded in the main window.
+  main_origin: String,
+  /// User agent of the main window. Empty for the webview default.
+  ua_window: String,
+  /// Width of the main window, in logical pixels.
+  main_width: f64,
+  /// Height of the main window, in logical pixels.
+  main_height: f64,
+
+  // Tray Window
+  /// Width of the tray window, in logical pixels.
+  tray_width: f64,
+  /// Height of the tray window, in logical pixels.
+  tray_height: f64,
+  /// Show the tray icon and window.
+  tray: bool,
+  /// Open the dashboard instead of `tray_origin` in the tray window.
+  tray_dashboard: bool,
+  /// URL loaded in the tray window.
+  tray_origin: String,
+  /// User agent of the tray window.
+  ua_tray: String,
+
+  // keys this version doesn't know about, kept so they survive a rewrite
+  #[serde(flatten)]
+  extra: BTreeMap<String, Value>,
+});
+
+impl AppConf {
+  pub fn new() -> Self {
+    info!("conf_init");
+    Self {
+      schema: format!("./{}", schema::SCHEMA_PATH),
+      version: migrate::CONF_VERSION,
+      titlebar: true,
+      hide_dock_icon: false,
+      save_window_state: false,
+      theme: Theme::Light,
+      auto_update: UpdatePolicy::Prompt,
+      #[cfg(target_os = "macos")]
+      speech_lang: "com.apple.eloquence.en-US.Rocko".into(),
+      #[cfg(not(target_os = "macos"))]
+      speech_lang: "".into(),
+      tray: true,
+      popup_search: false,
+      isinit: true,
+      main_close: false,
+      stay_on_top: false,
+      main_dashboard: false,
+      tray_dashboard: false,
+      main_width: 1440.0,
+      main_height: 900.0,
+      tray_width: 1440.0,
+      tray_height: 900.0,
+      main_origin: APP_URL.into(),
+      tray_origin: APP_URL.into(),
+      default_origin: APP_URL.into(),
+      ua_tray: UA_MOBILE.into(),
+      ua_window: "".into(),
+      global_shortcut: None,
+      profile: profile::DEFAULT_PROFILE.into(),
+      extra: BTreeMap::new(),
+    }
+  }
+
+  /// `quick.conf.json` in the data directory, unless another file is given with `--config`.
+  pub fn file_path() -> PathBuf {
+    cli::args()
+      .config
+      .clone()
+      .unwrap_or_else(|| app_root().join(APP_CONF_PATH))
+  }
+
+  /// Reads the config, failing on the first setting that doesn't validate.
+  pub fn load() -> Result<Self, ConfError> {
+    Self::load_from(&Self::file_path())
+  }
+
+  fn load_from(path: &Path) -> Result<Self, ConfError> {
+    let mut map = read_map(path)?;
+    Self::upgrade(path, &mut map)?;
+    Self::validate(&map)?;
+    map.extend(cli::overrides().clone());
+    serde_json::from_value(Value::Object(map)).map_err(Into::into)
+  }
+
+  /// Like `load`, but invalid settings fall back to their defaults one by one instead of
+  /// discarding the whole file.
+  pub fn read() -> Self {
+    Self::read_from(&Self::file_path())
+  }
+
+  fn read_from(path: &Path) -> Self {
+    // unlike `load`, a corrupt file is recovered from its backup copy
+    let mut map = match utils::read_json::<Map<String, Value>>(path) {
+      Ok(v) => v,
+      Err(err) => {
+        error!("conf_read_error: {}", err);
+        return Self::default();
+      }
+    };
+    // a newer config is still read as far as this version understands it, `write` leaves it alone
+    if let Err(err) = Self::upgrade(path, &mut map) {
+      error!("conf_read: {}", err);
+    }
+    if let Err(err) = Self::validate(&map) {
+      error!("conf_read_invalid: {}", err);
+      for key in err.keys() {
+        map.remove(key);
+      }
+    }
+    map.extend(cli::overrides().clone());
+    serde_json::from_value(Value::Object(map)).unwrap_or_else(|err| {
+      error!("conf_read_parse_error: {}", err);
+      Self::default()
+    })
+  }
+
+  // Runs pending migrations on a raw config. A migrated config is written back right away, after
+  // the original file has been copied aside. Configs from a newer version are refused.
+  fn upgrade(path: &Path, map: &mut Map<String, Value>) -> Result<(), ConfError> {
+    let from = migrate::version_of(map);
+    if from > migrate::CONF_VERSION {
+      return Err(ConfError::UnsupportedVersion(from));
+    }
+    if from == migrate::CONF_VERSION {
+      return Ok(());
+    }
+
+    let backup = migrate::backup_path(path, from);
+    match std::fs::copy(path, &backup) {
+      Ok(_) => info!("conf_backup: {}", backup.display()),
+      Err(err) => error!("conf_backup: {}", err),
+    }
+    migrate::upgrade(map);
+    utils::write_json(path, map).unwrap_or_else(|err| error!("conf_write: {}", err));
+    Ok(())
+  }
+
+  /// Checks the values of a raw config, collecting every rejected setting.
+  /// Keys this version doesn't know about are passed through, see `try_amend` for a strict check.
+  pub fn validate(map: &Map<String, Value>) -> Result<(), ConfError> {
+    let defaults = Self::default().to_map();
+    let errors: Vec<FieldError> = map
+      .iter()
+      .filter_map(|(key, value)| check_field(key, value, defaults.get(key)?))
+      .collect();
+
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(ConfError::Fields(errors))
+    }
+  }
+
+  pub fn to_map(&self) -> Map<String, Value> {
+    match serde_json::to_value(self) {
+      Ok(Value::Object(map)) => map,
+      _ => Map::new(),
+    }
+  }
+
+  /// Settings overridden on the command line are written with the values they have on disk, so
+  /// the overrides only last for the session.
+  pub fn write(self) -> Self {
+    if let Err(err) = self.write_to(&Self::file_path()) {
+      error!("conf_write: {}", err);
+    }
+    self
+  }
+
+  // A config written by a newer version is never replaced, it would lose the settings this
+  // version doesn't know.
+  fn write_to(&self, path: &Path) -> Result<(), ConfError> {
+    if let Ok(disk) = read_map(path) {
+      let version = migrate::version_of(&disk);
+      if version > migrate::CONF_VERSION {
+        return Err(ConfError::UnsupportedVersion(version));
+      }
+    }
+    if !path.exists() {
+      info!("conf_create");
+    }
+    utils::write_json(path, &self.stored().to_map())?;
+    Ok(())
+  }
+
+  /// The config without this session's command line overrides.
+  pub fn stored(&self) -> Self {
+    let overrides = cli::overrides();
+    if overrides.is_empty() {
+      return self.clone();
+    }
+    let disk = read_map(&Self::file_path()).unwrap_or_default();
+    let mut map = self.to_map();
+    for key in overrides.keys() {
+      match disk.get(key) {
+        Some(value) => map.insert(key.clone(), value.clone()),
+        None => map.remove(key),
+      };
+    }
+    serde_json::from_value(Value::Object(map)).unwrap_or_else(|_| self.clone())
+  }
+
+  /// Applies the settings in `json` only if every one of them is known and valid, otherwise
+  /// reports all rejected keys at once.
+  pub fn try_amend(self, json: Value) -> Result<Self, ConfError> {
+    let patch: Map<String, Value> = serde_json::from_value(json)?;
+    let defaults = Self::default().to_map();
+    let mut config = self.to_map();
+    let mut errors = vec![];
+    for (key, value) in &patch {
+      match defaults.get(key) {
+        // sent back unchanged by a form that posts every setting
+        Some(_) if READ_ONLY.contains(&key.as_str()) && config.get(key) != Some(value) => {
+          errors.push(FieldError::ReadOnly { key: key.clone() })
+        }
+        Some(default) => errors.extend(check_field(key, value, default)),
+        None => errors.push(FieldError::UnknownKey { key: key.clone() }),
+      }
+    }
+    if !errors.is_empty() {
+      return Err(ConfError::Fields(errors));
+    }
+
+    config.extend(patch);
+    serde_json::from_value(Value::Object(config)).map_err(Into::into)
+  }
+
+  #[cfg(target_os = "macos")]
+  pub fn titlebar(self) -> TitleBarStyle {
+    if self.titlebar {
+      TitleBarStyle::Transparent
+    } else {
+      TitleBarStyle::Overlay
+    }
+  }
+
+  pub fn theme_mode() -> tauri::Theme {
+    Self::get_theme().window_theme()
+  }
+
+  pub fn get_theme() -> Theme {
+    Self::read().theme
+  }
+
+  pub fn get_auto_update(self) -> UpdatePolicy {
+    self.auto_update
+  }
+}
+
+impl Default for AppConf {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+pub const WINDOW_SIZE_RANGE: (f64, f64) = (200.0, 10000.0);
+
+// Settings the app maintains itself.
+const READ_ONLY: &[&str] = &["$schema", "version"];
+
+fn read_map(path: &Path) -> Result<Map<String, Value>, ConfError> {
+  let content = std::fs::read_to_string(path)?;
+  Ok(serde_json::from_str(&content)?)
+}
+
+// Checks a known setting against the type of its default value and any further constraint on it.
+fn check_field(key: &str, value: &Value, default: &Value) -> Option<FieldError> {
+  let expected = match default {
+    Value::Bool(_) => "boolean",
+    Value::Number(_) => "number",
+    // only `global_shortcut` is optional
+    Value::String(_) | Value::Null => "string",
+    _ => return None,
+  };
+  let type_ok = match default {
+    Value::Null => value.is_null() || value.is_string(),
+    Value::Bool(_) => value.is_boolean(),
+    Value::Number(_) => value.is_number(),
+    _ => value.is_string(),
+  };
+  if !type_ok {
+    return Some(FieldError::WrongType {
+      key: key.into(),
+      value: value.clone(),
+      expected,
+    });
+  }
+
+  match key {
+    // `1.5` or `-1` would pass as a number but fail to deserialize
+    "version" if value.as_u64().is_none() => Some(FieldError::WrongType {
+      key: key.into(),
+      value: value.clone(),
+      expected: "non-negative integer",
+    }),
+    "theme" => check_enum::<Theme>(key, value, Theme::ALLOWED),
+    "auto_update" => check_enum::<UpdatePolicy>(key, value, UpdatePolicy::ALLOWED),
+    "main_width" | "main_height" | "tray_width" | "tray_height" => {
+      let (min, max) = WINDOW_SIZE_RANGE;
+      let size = value.as_f64().unwrap_or_default();
+      if (min..=max).contains(&size) {
+        None
+      } else {
+        Some(FieldError::OutOfRange {
+          key: key.into(),
+          value: size,
+          min,
+          max,
+        })
+      }
+    }
+    "global_shortcut" => {
+      let shortcut = value.as_str()?;
+      shortcut
+        .parse::<Accelerator>()
+        .err()
+        .map(|err| FieldError::InvalidAccelerator {
+          key: key.into(),
+          value: shortcut.into(),
+          message: err.to_string(),
+        })
+    }
+    _ => None,
+  }
+}
+
+fn check_enum<T: std::str::FromStr>(
+  key: &str,
+  value: &Value,
+  allowed: &'static [&'static str],
+) -> Option<FieldError> {
+  match value.as_str().map(str::parse::<T>) {
+    Some(Ok(_)) => None,
+    _ => Some(FieldError::InvalidValue {
+      key: key.into(),
+      value: value.clone(),
+      allowed,
+    }),
+  }
+}
+
+pub mod cmd {
+  use super::{profile, AppConf, ConfError, Theme};
+  use crate::app::{apply, menu};
+  use tauri::{command, AppHandle, Manager};
+
+  #[command]
+  pub fn get_app_conf() -> AppConf {
+    AppConf::read()
+  }
+
+  #[command]
+  pub fn reset_app_conf() -> AppConf {
+    AppConf::default().write()
+  }
+
+  #[command]
+  pub fn get_theme() -> Theme {
+    AppConf::get_theme()
+  }
+
+  /// JSON Schema of the config, for rendering the settings form.
+  #[command]
+  pub fn get_conf_schema() -> serde_json::Value {
+    super::schema::generate()
+  }
+
+  /// Saves the settings form. On failure the error lists every rejected key, so the form can
+  /// highlight the fields that need fixing.
+  #[command]
+  pub fn form_confirm(app: AppHandle, data: serde_json::Value) -> Result<AppConf, ConfError> {
+    let app_conf = AppConf::read().try_amend(data)?;
+    apply::save(&app, app_conf.clone());
+    Ok(app_conf)
+  }
+
+  #[command]
+  pub fn get_profiles() -> Vec<String> {
+    profile::Profile::list()
+  }
+
+  #[command]
+  pub fn switch_profile(app: AppHandle, name: &str) -> Result<AppConf, ConfError> {
+    let app_conf = profile::switch(name)?;
+    apply::save(&app, app_conf.clone());
+    Ok(app_conf)
+  }
+
+  /// Saves the current origins and user agents as the profile `name`.
+  #[command]
+  pub fn create_profile(app: AppHandle, name: &str) -> Result<Vec<String>, ConfError> {
+    profile::create(name)?;
+    menu::refresh_profiles(&app, &AppConf::read().profile);
+    Ok(profile::Profile::list())
+  }
+
+  #[command]
+  pub fn rename_profile(app: AppHandle, from: &str, to: &str) -> Result<AppConf, ConfError> {
+    let app_conf = profile::rename(from, to)?;
+    apply::save(&app, app_conf.clone());
+    menu::refresh_profiles(&app, &app_conf.profile);
+    Ok(app_conf)
+  }
+
+  #[command]
+  pub fn delete_profile(app: AppHandle, name: &str) -> Result<Vec<String>, ConfError> {
+    profile::delete(name)?;
+    menu::refresh_profiles(&app, &AppConf::read().profile);
+    Ok(profile::Profile::list())
+  }
+
+  #[command]
+  pub fn form_cancel(app: AppHandle, label: &str, title: &str, msg: &str) {
+    let win = app.app_handle().get_window(label).unwrap();
+    tauri::api::dialog::ask(
+      app.app_handle().get_window(label).as_ref(),
+      title,
+      msg,
+      move |is_cancel| {
+        if is_cancel {
+          win.close().unwrap();
+        }
+      },
+    );
+  }
+
+  #[command]
+  pub fn form_msg(app: AppHandle, label: &str, title: &str, msg: &str) {
+    let win = app.app_handle().get_window(label);
+    tauri::api::dialog::message(win.as_ref(), title, msg);
+  }
+}
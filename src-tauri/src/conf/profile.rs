@@ -0,0 +1,214 @@
+use super::{AppConf, ConfError};
+use crate::{
+  cli,
+  utils::{self, app_root},
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+pub const PROFILES_DIR: &str = "profiles";
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// The part of `AppConf` that depends on which quicktype instance the app is pointed at. Stored
+/// as `~/.quickType/profiles/<name>.json`, with the profile's user script next to it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Profile {
+  pub main_origin: String,
+  pub tray_origin: String,
+  pub ua_window: String,
+  pub ua_tray: String,
+}
+
+impl Profile {
+  pub fn dir() -> PathBuf {
+    app_root().join(PROFILES_DIR)
+  }
+
+  pub fn file_path(name: &str) -> PathBuf {
+    Self::dir().join(format!("{}.json", name))
+  }
+
+  /// User script of a profile. The default profile keeps using `~/.quickType/main.js`.
+  pub fn script_path(name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+      app_root().join("main.js")
+    } else {
+      Self::dir().join(format!("{}.js", name))
+    }
+  }
+
+  /// Names of the saved profiles, sorted, always including the default one.
+  pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(Self::dir())
+      .into_iter()
+      .flatten()
+      .filter_map(|e| e.ok())
+      .filter_map(|e| {
+        let path = e.path();
+        match path.extension() {
+          Some(ext) if ext == "json" => Some(path.file_stem()?.to_str()?.to_string()),
+          _ => None,
+        }
+      })
+      .filter(|name| is_valid_name(name))
+      .collect();
+    if !names.iter().any(|name| name == DEFAULT_PROFILE) {
+      names.push(DEFAULT_PROFILE.into());
+    }
+    names.sort();
+    names
+  }
+
+  pub fn read(name: &str) -> Result<Self, ConfError> {
+    let path = Self::file_path(name);
+    if !utils::exists(&path) {
+      return Err(ConfError::ProfileNotFound(name.into()));
+    }
+    Ok(utils::read_json(&path)?)
+  }
+
+  pub fn write(&self, name: &str) -> Result<(), ConfError> {
+    Ok(utils::write_json(&Self::file_path(name), self)?)
+  }
+
+  pub fn of(conf: &AppConf) -> Self {
+    Self {
+      main_origin: conf.main_origin.clone(),
+      tray_origin: conf.tray_origin.clone(),
+      ua_window: conf.ua_window.clone(),
+      ua_tray: conf.ua_tray.clone(),
+    }
+  }
+}
+
+impl Default for Profile {
+  fn default() -> Self {
+    Self::of(&AppConf::default())
+  }
+}
+
+/// Profile names double as file names.
+pub fn is_valid_name(name: &str) -> bool {
+  !name.is_empty()
+    && name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// The profile of this session is fixed by `--profile`.
+fn check_overridden() -> Result<(), ConfError> {
+  if cli::overrides().contains_key("profile") {
+    return Err(ConfError::ProfileOverridden);
+  }
+  Ok(())
+}
+
+fn check_name(name: &str) -> Result<(), ConfError> {
+  if !is_valid_name(name) {
+    return Err(ConfError::InvalidProfileName(name.into()));
+  }
+  Ok(())
+}
+
+fn exists(name: &str) -> bool {
+  Profile::list().iter().any(|n| n == name)
+}
+
+// The default profile exists without a file until it is first left.
+fn read_or_default(name: &str) -> Result<Profile, ConfError> {
+  if name == DEFAULT_PROFILE && !utils::exists(&Profile::file_path(name)) {
+    Ok(Profile::default())
+  } else {
+    Profile::read(name)
+  }
+}
+
+/// Returns the config with `name` as the active profile. The settings of the profile being left
+/// are saved first, so edits made while it was active aren't lost.
+pub fn switch(name: &str) -> Result<AppConf, ConfError> {
+  check_overridden()?;
+  check_name(name)?;
+  let app_conf = AppConf::read();
+  if app_conf.profile == name {
+    return Ok(app_conf);
+  }
+
+  Profile::of(&app_conf.stored()).write(&app_conf.profile)?;
+  let profile = read_or_default(name)?;
+  info!("profile_switch: {} -> {}", app_conf.profile, name);
+
+  Ok(AppConf {
+    profile: name.into(),
+    main_origin: profile.main_origin,
+    tray_origin: profile.tray_origin,
+    ua_window: profile.ua_window,
+    ua_tray: profile.ua_tray,
+    ..app_conf
+  })
+}
+
+/// Saves the origins and user agents in use as a new profile.
+pub fn create(name: &str) -> Result<(), ConfError> {
+  check_name(name)?;
+  if exists(name) {
+    return Err(ConfError::ProfileExists(name.into()));
+  }
+  info!("profile_create: {}", name);
+  Profile::of(&AppConf::read().stored()).write(name)
+}
+
+/// Renames a profile and its user script. Returns the config, with the new name if the profile
+/// was active.
+pub fn rename(from: &str, to: &str) -> Result<AppConf, ConfError> {
+  check_overridden()?;
+  if from == DEFAULT_PROFILE {
+    return Err(ConfError::DefaultProfile);
+  }
+  check_name(to)?;
+  if !exists(from) {
+    return Err(ConfError::ProfileNotFound(from.into()));
+  }
+  if exists(to) {
+    return Err(ConfError::ProfileExists(to.into()));
+  }
+
+  let app_conf = AppConf::read();
+  Profile::read(from)?.write(to)?;
+  let script = Profile::script_path(from);
+  if utils::exists(&script) {
+    fs::rename(script, Profile::script_path(to))?;
+  }
+  fs::remove_file(Profile::file_path(from))?;
+  info!("profile_rename: {} -> {}", from, to);
+
+  if app_conf.profile != from {
+    return Ok(app_conf);
+  }
+  Ok(AppConf {
+    profile: to.into(),
+    ..app_conf
+  })
+}
+
+/// Removes a profile other than the active and the default one, with its user script.
+pub fn delete(name: &str) -> Result<(), ConfError> {
+  check_overridden()?;
+  if name == DEFAULT_PROFILE {
+    return Err(ConfError::DefaultProfile);
+  }
+  if !exists(name) {
+    return Err(ConfError::ProfileNotFound(name.into()));
+  }
+  if AppConf::read().profile == name {
+    return Err(ConfError::ProfileActive(name.into()));
+  }
+  for path in [Profile::file_path(name), Profile::script_path(name)] {
+    if utils::exists(&path) {
+      fs::remove_file(path)?;
+    }
+  }
+  info!("profile_delete: {}", name);
+  Ok(())
+}
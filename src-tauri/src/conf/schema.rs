@@ -0,0 +1,105 @@
+use super::{AppConf, WINDOW_SIZE_RANGE};
+use crate::utils;
+use log::{error, info};
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Written next to the config, which points at it through its `$schema` key.
+pub const SCHEMA_PATH: &str = "quick.conf.schema.json";
+
+/// JSON Schema of a config value type.
+pub trait ConfSchema {
+  fn schema() -> Value;
+}
+
+impl ConfSchema for bool {
+  fn schema() -> Value {
+    json!({ "type": "boolean" })
+  }
+}
+
+impl ConfSchema for u64 {
+  fn schema() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+  }
+}
+
+impl ConfSchema for f64 {
+  fn schema() -> Value {
+    json!({ "type": "number" })
+  }
+}
+
+impl ConfSchema for String {
+  fn schema() -> Value {
+    json!({ "type": "string" })
+  }
+}
+
+impl<T: ConfSchema> ConfSchema for Option<T> {
+  fn schema() -> Value {
+    let mut schema = T::schema();
+    match schema.get("type").cloned() {
+      Some(ty @ Value::String(_)) if schema.get("enum").is_none() => {
+        schema["type"] = json!([ty, "null"]);
+        schema
+      }
+      _ => json!({ "anyOf": [schema, { "type": "null" }] }),
+    }
+  }
+}
+
+impl ConfSchema for BTreeMap<String, Value> {
+  fn schema() -> Value {
+    json!({ "type": "object" })
+  }
+}
+
+pub fn file_path() -> PathBuf {
+  AppConf::file_path().with_file_name(SCHEMA_PATH)
+}
+
+/// Builds the schema of `quick.conf.json` from the fields of `AppConf`, with their doc comments
+/// as descriptions and the values of `AppConf::default()` as defaults.
+pub fn generate() -> Value {
+  let defaults = AppConf::default().to_map();
+  let mut properties = Map::new();
+  for (key, docs, mut schema) in AppConf::fields() {
+    // flattened fields aren't keys of their own
+    let default = match defaults.get(key) {
+      Some(v) => v.clone(),
+      None => continue,
+    };
+    let description: Vec<&str> = docs.iter().map(|line| line.trim()).collect();
+    if !description.is_empty() {
+      schema["description"] = Value::from(description.join(" "));
+    }
+    if key == "main_width" || key == "main_height" || key == "tray_width" || key == "tray_height" {
+      schema["minimum"] = json!(WINDOW_SIZE_RANGE.0);
+      schema["maximum"] = json!(WINDOW_SIZE_RANGE.1);
+    }
+    schema["default"] = default;
+    properties.insert(key.into(), schema);
+  }
+
+  json!({
+    "$schema": "http://json-schema.org/draft-07/schema#",
+    "title": "QuickType config",
+    "description": "Settings of the QuickType desktop app, see Preferences > Go to Config.",
+    "type": "object",
+    "properties": properties,
+    // kept as is, they may belong to a newer version
+    "additionalProperties": true,
+  })
+}
+
+/// Writes the schema next to the config, unless it is already up to date.
+pub fn write() {
+  let path = file_path();
+  let content = serde_json::to_vec_pretty(&generate()).unwrap_or_default();
+  if std::fs::read(&path).is_ok_and(|current| current == content) {
+    return;
+  }
+  info!("conf_schema: {}", path.display());
+  utils::write_atomic(&path, &content).unwrap_or_else(|err| error!("conf_schema: {}", err));
+}
@@ -0,0 +1,260 @@
+use super::{
+  migrate,
+  profile::{self, Profile, DEFAULT_PROFILE},
+  AppConf, ConfError, FieldError, Theme, UpdatePolicy, APP_CONF_PATH,
+};
+use crate::cli;
+use serde_json::{json, Value};
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+const V0: &str = include_str!("fixtures/v0.json");
+const V0_EARLY: &str = include_str!("fixtures/v0_early.json");
+const FUTURE: &str = include_str!("fixtures/future.json");
+
+// `quick.conf.json` with `contents`, alone in a fresh directory.
+fn conf_file(test: &str, contents: &str) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!("quick_type-{}-{}", test, std::process::id()));
+  fs::remove_dir_all(&dir).ok();
+  fs::create_dir_all(&dir).unwrap();
+  let path = dir.join(APP_CONF_PATH);
+  fs::write(&path, contents).unwrap();
+  path
+}
+
+fn files_in(path: &Path) -> Vec<String> {
+  let mut names: Vec<String> = fs::read_dir(path.parent().unwrap())
+    .unwrap()
+    .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+    .collect();
+  names.sort();
+  names
+}
+
+fn disk(path: &Path) -> Value {
+  serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn v0_is_migrated() {
+  let path = conf_file("v0", V0);
+  let conf = AppConf::read_from(&path);
+
+  assert_eq!(conf.version, migrate::CONF_VERSION);
+  assert_eq!(conf.theme, Theme::Dark);
+  assert_eq!(conf.auto_update, UpdatePolicy::Silent);
+  assert_eq!(conf.main_origin, "https://app.quicktype.io/#l=rust");
+  assert_eq!(conf.tray_width, 360.0);
+  assert_eq!(disk(&path)["version"], json!(migrate::CONF_VERSION));
+}
+
+#[test]
+fn v0_is_backed_up() {
+  let path = conf_file("v0-backup", V0);
+  AppConf::read_from(&path);
+
+  let backup = migrate::backup_path(&path, 0);
+  assert!(backup.ends_with("quick.conf.json.v0.bak"));
+  assert_eq!(fs::read_to_string(backup).unwrap(), V0);
+}
+
+#[test]
+fn v0_unknown_keys_survive() {
+  let path = conf_file("v0-extra", V0);
+  let conf = AppConf::read_from(&path);
+
+  let sidebar = json!({ "collapsed": true });
+  assert_eq!(conf.extra.get("sidebar"), Some(&sidebar));
+  assert_eq!(disk(&path)["sidebar"], sidebar);
+  conf.write_to(&path).unwrap();
+  assert_eq!(disk(&path)["sidebar"], sidebar);
+}
+
+#[test]
+fn v0_early_fills_in_defaults() {
+  let path = conf_file("v0-early", V0_EARLY);
+  let conf = AppConf::read_from(&path);
+  let defaults = AppConf::default();
+
+  assert_eq!(conf.version, migrate::CONF_VERSION);
+  assert!(!conf.tray);
+  assert_eq!(conf.auto_update, defaults.auto_update);
+  assert_eq!(conf.global_shortcut, None);
+  assert_eq!(conf.main_width, defaults.main_width);
+  assert!(conf.extra.is_empty());
+}
+
+#[test]
+fn current_version_is_not_rewritten() {
+  let contents = serde_json::to_string_pretty(&AppConf::default()).unwrap();
+  let path = conf_file("current", &contents);
+  AppConf::load_from(&path).unwrap();
+
+  assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+  assert_eq!(files_in(&path), [APP_CONF_PATH]);
+}
+
+#[test]
+fn future_version_is_refused() {
+  let path = conf_file("future", FUTURE);
+
+  assert!(matches!(
+    AppConf::load_from(&path),
+    Err(ConfError::UnsupportedVersion(99))
+  ));
+  let conf = AppConf::read_from(&path);
+  assert_eq!(conf.theme, Theme::Dark);
+  assert!(matches!(
+    conf.write_to(&path),
+    Err(ConfError::UnsupportedVersion(99))
+  ));
+  assert_eq!(fs::read_to_string(&path).unwrap(), FUTURE);
+  assert_eq!(files_in(&path), [APP_CONF_PATH]);
+}
+
+#[test]
+fn invalid_version_is_rejected() {
+  for version in [json!(1.5), json!(-1), json!("1")] {
+    let map = json!({ "version": version });
+    let err = AppConf::validate(map.as_object().unwrap()).unwrap_err();
+    assert_eq!(err.keys(), ["version"]);
+  }
+  let map = json!({ "version": 1 });
+  assert!(AppConf::validate(map.as_object().unwrap()).is_ok());
+}
+
+#[test]
+fn form_cannot_change_managed_keys() {
+  let conf = AppConf::default();
+  let err = conf
+    .clone()
+    .try_amend(json!({ "version": 0, "$schema": "https://example.com", "tray": false }))
+    .unwrap_err();
+  match err {
+    ConfError::Fields(errors) => {
+      assert!(errors
+        .iter()
+        .all(|err| matches!(err, FieldError::ReadOnly { .. })));
+      assert_eq!(errors.len(), 2);
+    }
+    err => panic!("{}", err),
+  }
+
+  // a form posting every setting back sends them unchanged
+  let amended = conf.clone().try_amend(json!({
+    "version": conf.version,
+    "$schema": conf.schema,
+    "tray": false,
+  }));
+  assert!(!amended.unwrap().tray);
+}
+
+// Writes a config using `origin` on the active profile.
+fn with_origin(origin: &str) -> AppConf {
+  AppConf {
+    main_origin: origin.into(),
+    ..AppConf::default()
+  }
+  .write()
+}
+
+#[test]
+fn switching_profiles_keeps_the_one_left() {
+  let (_home, _) = cli::test_home();
+  with_origin("https://a.example.com");
+  profile::create("staging").unwrap();
+
+  // the new profile starts as a copy of the active one
+  let conf = with_origin("https://b.example.com");
+  let staging = profile::switch("staging").unwrap().write();
+  assert_eq!(staging.profile, "staging");
+  assert_eq!(staging.main_origin, "https://a.example.com");
+  assert_eq!(
+    Profile::read(DEFAULT_PROFILE).unwrap().main_origin,
+    conf.main_origin
+  );
+
+  let back = profile::switch(DEFAULT_PROFILE).unwrap();
+  assert_eq!(back.main_origin, "https://b.example.com");
+  assert_eq!(Profile::list(), [DEFAULT_PROFILE, "staging"]);
+}
+
+#[test]
+fn switching_to_an_unknown_profile_fails() {
+  let (_home, _) = cli::test_home();
+  assert!(matches!(
+    profile::switch("missing"),
+    Err(ConfError::ProfileNotFound(_))
+  ));
+  assert!(matches!(
+    profile::switch("../conf"),
+    Err(ConfError::InvalidProfileName(_))
+  ));
+}
+
+#[test]
+fn creating_a_profile_checks_its_name() {
+  let (_home, _) = cli::test_home();
+  profile::create("local").unwrap();
+  assert!(matches!(
+    profile::create("local"),
+    Err(ConfError::ProfileExists(_))
+  ));
+  assert!(matches!(
+    profile::create(DEFAULT_PROFILE),
+    Err(ConfError::ProfileExists(_))
+  ));
+  assert!(matches!(
+    profile::create("a b"),
+    Err(ConfError::InvalidProfileName(_))
+  ));
+}
+
+#[test]
+fn renaming_a_profile_moves_its_script() {
+  let (_home, _) = cli::test_home();
+  profile::create("old").unwrap();
+  profile::switch("old").unwrap().write();
+  fs::write(Profile::script_path("old"), "// user script").unwrap();
+
+  let conf = profile::rename("old", "new").unwrap();
+  assert_eq!(conf.profile, "new");
+  assert_eq!(Profile::list(), [DEFAULT_PROFILE, "new"]);
+  assert!(!Profile::script_path("old").exists());
+  assert_eq!(
+    fs::read_to_string(Profile::script_path("new")).unwrap(),
+    "// user script"
+  );
+
+  assert!(matches!(
+    profile::rename(DEFAULT_PROFILE, "other"),
+    Err(ConfError::DefaultProfile)
+  ));
+  assert!(matches!(
+    profile::rename("missing", "other"),
+    Err(ConfError::ProfileNotFound(_))
+  ));
+}
+
+#[test]
+fn deleting_a_profile_spares_the_active_one() {
+  let (_home, _) = cli::test_home();
+  profile::create("active").unwrap();
+  profile::create("unused").unwrap();
+  fs::write(Profile::script_path("unused"), "").unwrap();
+  profile::switch("active").unwrap().write();
+
+  assert!(matches!(
+    profile::delete("active"),
+    Err(ConfError::ProfileActive(_))
+  ));
+  assert!(matches!(
+    profile::delete(DEFAULT_PROFILE),
+    Err(ConfError::DefaultProfile)
+  ));
+  profile::delete("unused").unwrap();
+  assert_eq!(Profile::list(), ["active", DEFAULT_PROFILE]);
+  assert!(!Profile::script_path("unused").exists());
+}
@@ -2,23 +2,32 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod app;
+mod cli;
 mod conf;
 mod utils;
 
-use conf::AppConf;
-use app::{setup, cmd, fs_extra, menu, window, update};
+use app::{bundle, cmd, codegen, fs_extra, infer, menu, setup, update, window};
+use conf::{AppConf, ConfError};
 use tauri_plugin_autostart::MacosLauncher;
 use tauri_plugin_log::{
-    fern::colors::{Color, ColoredLevelConfig},
-    LogTarget,
+  fern::colors::{Color, ColoredLevelConfig},
+  LogTarget,
 };
 
 fn main() {
-    let app_conf = AppConf::read().write();
+  cli::init();
+  let app_conf = match AppConf::load() {
+    Ok(v) => v.write(),
+    // leave a hand-edited config with invalid values on disk so it can be fixed, and one
+    // written by a newer version as it is
+    Err(ConfError::Fields(_) | ConfError::UnsupportedVersion(_)) => AppConf::read(),
+    Err(_) => AppConf::read().write(),
+  };
+  conf::schema::write();
 
-    let context = tauri::generate_context!();
+  let context = tauri::generate_context!();
 
-    let mut log = tauri_plugin_log::Builder::default()
+  let mut log = tauri_plugin_log::Builder::default()
     .targets([
       // LogTarget::LogDir,
       // LOG PATH: ~/.quickType/quickType.log
@@ -28,55 +37,65 @@ fn main() {
     ])
     .level(log::LevelFilter::Debug);
 
-    if cfg!(debug_assertions) {
-        log = log.with_colors(ColoredLevelConfig {
-        error: Color::Red,
-        warn: Color::Yellow,
-        debug: Color::Blue,
-        info: Color::BrightGreen,
-        trace: Color::Cyan,
-        });
-    }
+  if cfg!(debug_assertions) {
+    log = log.with_colors(ColoredLevelConfig {
+      error: Color::Red,
+      warn: Color::Yellow,
+      debug: Color::Blue,
+      info: Color::BrightGreen,
+      trace: Color::Cyan,
+    });
+  }
 
-    let mut builder = tauri::Builder::default()
-        .plugin(log.build())
-        .plugin(tauri_plugin_positioner::init())
-        .plugin(tauri_plugin_autostart::init(
-          MacosLauncher::LaunchAgent,
-          None,
-        ))
-        .invoke_handler(tauri::generate_handler![
-            cmd::drag_window,
-            cmd::fullscreen,
-            cmd::download,
-            cmd::save_file,
-            cmd::open_link,
-            cmd::run_check_update,
-            cmd::open_file,
-            cmd::get_data,
-            update::download_list,
-            update::get_download_list,
-            fs_extra::metadata,
-            conf::cmd::get_app_conf,
-            conf::cmd::reset_app_conf,
-            conf::cmd::get_theme,
-            conf::cmd::form_confirm,
-            conf::cmd::form_cancel,
-            conf::cmd::form_msg,
-            window::cmd::wa_window,
-            window::cmd::window_reload,
-        ])
-        .setup(setup::init)
-        .menu(menu::init());
+  let mut builder = tauri::Builder::default()
+    .plugin(log.build())
+    .plugin(tauri_plugin_positioner::init())
+    .plugin(tauri_plugin_autostart::init(
+      MacosLauncher::LaunchAgent,
+      None,
+    ))
+    .invoke_handler(tauri::generate_handler![
+      cmd::drag_window,
+      cmd::fullscreen,
+      cmd::download,
+      cmd::save_file,
+      cmd::open_link,
+      cmd::run_check_update,
+      cmd::open_file,
+      cmd::get_data,
+      update::download_list,
+      update::get_download_list,
+      fs_extra::metadata,
+      conf::cmd::get_app_conf,
+      conf::cmd::reset_app_conf,
+      conf::cmd::get_theme,
+      conf::cmd::get_conf_schema,
+      conf::cmd::form_confirm,
+      conf::cmd::form_cancel,
+      conf::cmd::form_msg,
+      conf::cmd::get_profiles,
+      conf::cmd::switch_profile,
+      conf::cmd::create_profile,
+      conf::cmd::rename_profile,
+      conf::cmd::delete_profile,
+      window::cmd::wa_window,
+      window::cmd::window_reload,
+      bundle::cmd::export_settings,
+      bundle::cmd::import_settings,
+      infer::cmd::infer_types,
+      codegen::cmd::generate_code,
+    ])
+    .setup(setup::init)
+    .menu(menu::init());
 
-    if app_conf.tray {
-        builder = builder.system_tray(menu::tray_menu());
-    }
-    if app_conf.save_window_state {
-        builder = builder.plugin(tauri_plugin_window_state::Builder::default().build());
-    }
+  if app_conf.tray {
+    builder = builder.system_tray(menu::tray_menu());
+  }
+  if app_conf.save_window_state {
+    builder = builder.plugin(tauri_plugin_window_state::Builder::default().build());
+  }
 
-    builder
+  builder
     .on_menu_event(menu::menu_handler)
     .on_system_tray_event(menu::tray_handler)
     .on_window_event(move |event| {
@@ -90,9 +109,13 @@ fn main() {
               "",
               "Do you want to exit the application when you click the [x] button?",
               move |is_ok| {
-                app_conf
-                  .amend(serde_json::json!({ "isinit" : false, "main_close": is_ok }))
-                  .write();
+                let patch = serde_json::json!({ "isinit": false, "main_close": is_ok });
+                match app_conf.try_amend(patch) {
+                  Ok(app_conf) => {
+                    app_conf.write();
+                  }
+                  Err(err) => log::error!("conf_amend: {}", err),
+                }
                 if is_ok {
                   std::process::exit(0);
                 } else {
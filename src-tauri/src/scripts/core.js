@@ -8,6 +8,12 @@ async function init() {
     document.addEventListener('contextmenu', event => event.preventDefault());
 }
 
+// called by the app when the theme setting changes, see `app::apply`
+window.__setTheme = function (mode) {
+    document.documentElement.style.colorScheme = mode;
+    document.documentElement.dataset.theme = mode;
+}
+
 if (
     document.readyState === "complete" ||
     document.readyState === "interactive"
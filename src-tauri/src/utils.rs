@@ -1,17 +1,29 @@
 use anyhow::Result;
 use log::{error, info};
+use serde::{de::DeserializeOwned, Serialize};
 
+use crate::{
+  cli,
+  conf::{profile::Profile, AppConf},
+};
 use std::{
   collections::HashMap,
+  ffi::OsString,
   fs::{self, File},
+  io::Write,
   path::{Path, PathBuf},
   process::Command,
+  sync::atomic::{AtomicUsize, Ordering},
 };
 use tauri::updater::UpdateResponse;
 use tauri::{utils::config::Config, AppHandle, Manager, Wry};
 
+/// Data directory, `~/.quickType` unless `QUICKTYPE_HOME` is set.
 pub fn app_root() -> PathBuf {
-  tauri::api::path::home_dir().unwrap().join(".quickType")
+  cli::args()
+    .home
+    .clone()
+    .unwrap_or_else(|| tauri::api::path::home_dir().unwrap().join(".quickType"))
 }
 
 pub fn get_tauri_conf() -> Option<Config> {
@@ -31,8 +43,84 @@ pub fn create_file(path: &Path) -> Result<File> {
   File::create(path).map_err(Into::into)
 }
 
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+  let mut name: OsString = path.file_name().unwrap_or_default().into();
+  name.push(suffix);
+  path.with_file_name(name)
+}
+
+/// Last good copy of a file written by `write_json`, e.g. `quick.conf.json.bak`.
+pub fn backup_path(path: &Path) -> PathBuf {
+  with_suffix(path, ".bak")
+}
+
+/// Replaces `path` with `contents` so that a crash leaves either the old or the new file behind,
+/// never a truncated one: the data goes to a temp file next to it, is synced, then renamed over.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+  static TMP_ID: AtomicUsize = AtomicUsize::new(0);
+
+  let dir = path.parent().unwrap_or_else(|| Path::new("."));
+  fs::create_dir_all(dir)?;
+  let tmp = with_suffix(
+    path,
+    &format!(
+      ".{}.{}.tmp",
+      std::process::id(),
+      TMP_ID.fetch_add(1, Ordering::Relaxed)
+    ),
+  );
+
+  let res = File::create(&tmp)
+    .and_then(|mut file| {
+      file.write_all(contents)?;
+      file.sync_all()
+    })
+    .and_then(|_| fs::rename(&tmp, path));
+  if res.is_err() {
+    fs::remove_file(&tmp).ok();
+  }
+  res?;
+
+  // persist the rename itself, not supported on windows
+  #[cfg(unix)]
+  File::open(dir).and_then(|d| d.sync_all()).ok();
+
+  Ok(())
+}
+
+/// Atomically writes `value` as pretty JSON. The file being replaced is rotated into
+/// `backup_path` first, unless it is corrupt itself.
+pub fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+  if let Ok(current) = fs::read(path) {
+    if serde_json::from_slice::<serde::de::IgnoredAny>(&current).is_ok() {
+      write_atomic(&backup_path(path), &current)?;
+    }
+  }
+  write_atomic(path, serde_json::to_string_pretty(value)?.as_bytes())
+}
+
+/// Reads a file written by `write_json`. A file that doesn't parse is recovered from its backup, a
+/// missing one is not: removing the file resets it.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+  let content = fs::read_to_string(path)?;
+  serde_json::from_str(&content).or_else(|err| {
+    let backup = backup_path(path);
+    if !exists(&backup) {
+      return Err(err.into());
+    }
+    error!(
+      "read_json: {}: {}, recovering from {}",
+      path.display(),
+      err,
+      backup.display()
+    );
+    let content = fs::read_to_string(&backup).map_err(|_| err)?;
+    Ok(serde_json::from_str(&content)?)
+  })
+}
+
 pub fn script_path() -> PathBuf {
-  let script_file = app_root().join("main.js");
+  let script_file = Profile::script_path(&AppConf::read().profile);
   if !exists(&script_file) {
     create_file(&script_file).unwrap();
     fs::write(
@@ -90,7 +178,7 @@ pub fn clear_conf(app: &tauri::AppHandle) {
   let root = app_root();
   let msg = format!(
     "Path: {}\n
-    Are you sure you want to clear all QuickType configurations? Performing this operation data can not be restored, please back up in advance.\n
+    Are you sure you want to clear all QuickType configurations? Performing this operation data can not be restored, please back up in advance (Preferences > Export Settings).\n
     Note: The application will exit automatically after the configuration cleanup!",
     root.to_string_lossy()
   );
@@ -246,3 +334,45 @@ pub fn vec_to_hashmap(
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("quick_type-{}-{}", test, std::process::id()));
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  #[test]
+  fn write_json_keeps_a_backup() {
+    let path = dir("write_json").join("list.json");
+    write_json(&path, &[1]).unwrap();
+    write_json(&path, &[2]).unwrap();
+
+    assert_eq!(read_json::<Vec<u8>>(&path).unwrap(), [2]);
+    assert_eq!(read_json::<Vec<u8>>(&backup_path(&path)).unwrap(), [1]);
+  }
+
+  #[test]
+  fn read_json_recovers_a_corrupt_file() {
+    let path = dir("read_json_corrupt").join("list.json");
+    write_json(&path, &[1]).unwrap();
+    write_json(&path, &[2]).unwrap();
+    fs::write(&path, "[2").unwrap();
+
+    assert_eq!(read_json::<Vec<u8>>(&path).unwrap(), [1]);
+  }
+
+  #[test]
+  fn read_json_does_not_recover_a_removed_file() {
+    let path = dir("read_json_removed").join("list.json");
+    write_json(&path, &[1]).unwrap();
+    write_json(&path, &[2]).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(read_json::<Vec<u8>>(&path).is_err());
+  }
+}